@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use melter::nonlinearity::{cubic, cubic_block};
+
+/// A stereo buffer of 512 samples at 4x oversampling.
+const NUM_SAMPLES: usize = 512 * 4 * 2;
+const DRIVE: f32 = 0.3;
+const OFFSET: f32 = 0.1;
+
+/// Compares shaping a block sample by sample with shaping the whole block at once.
+fn cubic_shaper(c: &mut Criterion) {
+    let input: Vec<f32> = (0..NUM_SAMPLES)
+        .map(|i| (i as f32 * 0.05).sin() * 0.5)
+        .collect();
+    let mut samples = input.clone();
+
+    let mut group = c.benchmark_group("cubic");
+    group.throughput(Throughput::Elements(NUM_SAMPLES as u64));
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            samples.copy_from_slice(&input);
+            for sample in &mut samples {
+                *sample = cubic(*sample, black_box(DRIVE), black_box(OFFSET));
+            }
+        })
+    });
+    group.bench_function("block", |b| {
+        b.iter(|| {
+            samples.copy_from_slice(&input);
+            cubic_block(&mut samples, black_box(DRIVE), black_box(OFFSET));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cubic_shaper);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use melter::{Melter, MAX_OVERSAMPLING_FACTOR};
+
+const SAMPLE_RATE: f32 = 48000.0;
+/// The plugin processes a whole host buffer at once, so small buffers pay the per-block overhead
+/// more often than large ones.
+const BUFFER_SIZES: [usize; 2] = [32, 512];
+const NUM_CHANNELS: usize = 2;
+
+/// Runs the whole processing loop over a stereo buffer at every oversampling factor and for
+/// every buffer size.
+fn process(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process");
+    for buffer_size in BUFFER_SIZES {
+        let input: Vec<f32> = (0..buffer_size)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5)
+            .collect();
+
+        group.throughput(Throughput::Elements((buffer_size * NUM_CHANNELS) as u64));
+        for oversampling_factor in 0..=MAX_OVERSAMPLING_FACTOR {
+            let mut melter = Melter::new_offline(
+                NUM_CHANNELS as u32,
+                SAMPLE_RATE,
+                buffer_size as u32,
+                oversampling_factor,
+            );
+            let mut channels = vec![input.clone(); NUM_CHANNELS];
+
+            group.bench_with_input(
+                BenchmarkId::new(
+                    format!("{buffer_size}_samples/oversampling_factor"),
+                    oversampling_factor,
+                ),
+                &oversampling_factor,
+                |b, _| {
+                    b.iter(|| {
+                        // The buffer is processed in place, so it needs to be refilled every time
+                        for channel in &mut channels {
+                            channel.copy_from_slice(&input);
+                        }
+                        melter.process_offline(&mut channels);
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, process);
+criterion_main!(benches);
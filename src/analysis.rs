@@ -0,0 +1,197 @@
+use atomic_float::AtomicF32;
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The number of samples in each FFT. At 48 kHz the bins are about 23 Hz apart.
+pub const FFT_SIZE: usize = 2048;
+/// The level reported for bins without any signal, in decibels.
+pub const SPECTRUM_FLOOR_DB: f32 = -100.0;
+
+/// A ring buffer holding the last [`FFT_SIZE`] samples of the output, mixed down to mono. The
+/// audio thread fills it with [`push()`][Self::push()] and the editor reads it through a
+/// [`SpectrumAnalyzer`]. Both sides only use atomics, so neither of them can block the other. The
+/// reader can see a few samples from the next block mixed in, which doesn't matter for a display.
+#[derive(Debug)]
+pub struct SpectrumBuffer {
+    samples: Vec<AtomicF32>,
+    /// The index the next sample is written to. Only the audio thread writes to the buffer.
+    write_pos: AtomicUsize,
+    /// The sample rate of the samples in the buffer, used to label the bins.
+    sample_rate: AtomicF32,
+}
+
+impl SpectrumBuffer {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            samples: (0..FFT_SIZE).map(|_| AtomicF32::new(0.0)).collect(),
+            write_pos: AtomicUsize::new(0),
+            sample_rate: AtomicF32::new(sample_rate),
+        }
+    }
+
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        for sample in &self.samples {
+            sample.store(0.0, Ordering::Relaxed);
+        }
+        self.write_pos.store(0, Ordering::Release);
+    }
+
+    /// Copy a block of output into the ring buffer. The channels are averaged, and the new write
+    /// position is only published once the whole block has been copied.
+    pub fn push(&self, channels: &[&mut [f32]]) {
+        let num_samples = channels.first().map_or(0, |channel| channel.len());
+        if num_samples == 0 {
+            return;
+        }
+
+        let channel_weight = (channels.len() as f32).recip();
+        let mut write_pos = self.write_pos.load(Ordering::Relaxed);
+        for sample_idx in 0..num_samples {
+            let sum: f32 = channels.iter().map(|channel| channel[sample_idx]).sum();
+            self.samples[write_pos].store(sum * channel_weight, Ordering::Relaxed);
+            write_pos = (write_pos + 1) % FFT_SIZE;
+        }
+        self.write_pos.store(write_pos, Ordering::Release);
+    }
+}
+
+/// Computes the magnitude spectrum of the samples in a [`SpectrumBuffer`] with a Hann window.
+/// This does all of the work on the editor's side, so the audio thread only has to copy samples.
+/// Only used by the editor.
+#[allow(dead_code)]
+pub struct SpectrumAnalyzer {
+    buffer: Arc<SpectrumBuffer>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Scales the bins so a full scale sine at a bin's center frequency reads as 0 dB.
+    magnitude_scale: f32,
+    input: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+    /// The level of each bin in decibels, from DC up to the Nyquist frequency.
+    magnitudes_db: Vec<f32>,
+}
+
+#[allow(dead_code)]
+impl SpectrumAnalyzer {
+    pub fn new(buffer: Arc<SpectrumBuffer>) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let magnitude_scale = 2.0 / window.iter().sum::<f32>();
+
+        Self {
+            buffer,
+            input: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            magnitudes_db: vec![SPECTRUM_FLOOR_DB; FFT_SIZE / 2 + 1],
+            fft,
+            window,
+            magnitude_scale,
+        }
+    }
+
+    /// Recompute the spectrum from the most recent samples in the buffer.
+    pub fn update(&mut self) -> &[f32] {
+        // The oldest sample is the one that will be overwritten next
+        let oldest = self.buffer.write_pos.load(Ordering::Acquire);
+        for (i, (input, window)) in self.input.iter_mut().zip(&self.window).enumerate() {
+            let sample = self.buffer.samples[(oldest + i) % FFT_SIZE].load(Ordering::Relaxed);
+            *input = sample * window;
+        }
+
+        self.fft
+            .process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch)
+            .unwrap();
+        for (magnitude_db, bin) in self.magnitudes_db.iter_mut().zip(&self.spectrum) {
+            let magnitude = bin.norm() * self.magnitude_scale;
+            *magnitude_db = (20.0 * magnitude.log10()).max(SPECTRUM_FLOOR_DB);
+        }
+
+        &self.magnitudes_db
+    }
+
+    /// The level of each bin in decibels as of the last [`update()`][Self::update()].
+    pub fn magnitudes_db(&self) -> &[f32] {
+        &self.magnitudes_db
+    }
+
+    /// The center frequency of a bin in Hertz.
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.buffer.sample_rate.load(Ordering::Relaxed) / FFT_SIZE as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn push_sine(buffer: &SpectrumBuffer, frequency: f32, amplitude: f32, num_samples: usize) {
+        let mut samples: Vec<f32> = (0..num_samples)
+            .map(|i| (TAU * frequency * i as f32 / SAMPLE_RATE).sin() * amplitude)
+            .collect();
+        // Pushed in small blocks so the writes wrap around the ring buffer a few times
+        for block in samples.chunks_mut(100) {
+            buffer.push(&[block]);
+        }
+    }
+
+    #[test]
+    fn sine_has_a_single_dominant_bin() {
+        const BIN: usize = 100;
+        const AMPLITUDE: f32 = 0.5;
+
+        let buffer = Arc::new(SpectrumBuffer::new(SAMPLE_RATE));
+        let mut analyzer = SpectrumAnalyzer::new(buffer.clone());
+        let frequency = analyzer.bin_frequency(BIN);
+        approx::assert_relative_eq!(frequency, 2343.75);
+        push_sine(&buffer, frequency, AMPLITUDE, FFT_SIZE * 3 + 17);
+
+        let magnitudes_db = analyzer.update();
+        let loudest = (0..magnitudes_db.len())
+            .max_by(|&a, &b| magnitudes_db[a].total_cmp(&magnitudes_db[b]))
+            .unwrap();
+        assert_eq!(loudest, BIN);
+        approx::assert_relative_eq!(magnitudes_db[BIN], 20.0 * AMPLITUDE.log10(), epsilon = 0.01);
+
+        // The Hann window spreads the sine over the neighbouring bins, but no further
+        for (bin, magnitude_db) in magnitudes_db.iter().enumerate() {
+            if bin.abs_diff(BIN) > 1 {
+                assert!(*magnitude_db < -80.0, "bin {bin}: {magnitude_db}");
+            }
+        }
+    }
+
+    #[test]
+    fn channels_are_averaged() {
+        let buffer = SpectrumBuffer::new(SAMPLE_RATE);
+        buffer.push(&[&mut [1.0, 0.5], &mut [0.0, -0.5]]);
+        assert_eq!(buffer.samples[0].load(Ordering::Relaxed), 0.5);
+        assert_eq!(buffer.samples[1].load(Ordering::Relaxed), 0.0);
+        assert_eq!(buffer.write_pos.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn silence_reads_as_the_floor() {
+        let buffer = Arc::new(SpectrumBuffer::new(SAMPLE_RATE));
+        let mut analyzer = SpectrumAnalyzer::new(buffer.clone());
+        push_sine(&buffer, 1000.0, 1.0, FFT_SIZE);
+        buffer.reset();
+
+        assert!(analyzer
+            .update()
+            .iter()
+            .all(|&magnitude_db| magnitude_db == SPECTRUM_FLOOR_DB));
+    }
+}
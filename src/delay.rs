@@ -0,0 +1,70 @@
+/// A fixed delay line used to keep the dry signal aligned with the oversampled wet signal. The
+/// buffer is allocated up front for the maximum delay, so the delay can be changed while
+/// processing without allocating.
+#[derive(Debug)]
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    write_pos: usize,
+    delay: usize,
+}
+
+impl DelayLine {
+    /// Create a delay line that can delay the signal by up to `max_delay` samples.
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_delay + 1],
+            write_pos: 0,
+            delay: 0,
+        }
+    }
+
+    /// Set the delay in samples. This is clamped to the maximum delay.
+    pub fn set_delay(&mut self, delay: usize) {
+        self.delay = delay.min(self.buffer.len() - 1);
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
+
+    /// Write a sample to the delay line and return the sample from `delay` samples ago.
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.buffer[self.write_pos] = input;
+        let read_pos = (self.write_pos + self.buffer.len() - self.delay) % self.buffer.len();
+        self.write_pos = (self.write_pos + 1) % self.buffer.len();
+
+        self.buffer[read_pos]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_by_the_set_amount() {
+        let mut delay_line = DelayLine::new(8);
+        delay_line.set_delay(3);
+
+        let output: Vec<f32> = (1..=6).map(|i| delay_line.process(i as f32)).collect();
+        assert_eq!(output, [0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn zero_delay_passes_through() {
+        let mut delay_line = DelayLine::new(8);
+        for i in 0..20 {
+            assert_eq!(delay_line.process(i as f32), i as f32);
+        }
+    }
+
+    #[test]
+    fn clamps_to_max_delay() {
+        let mut delay_line = DelayLine::new(2);
+        delay_line.set_delay(100);
+
+        let output: Vec<f32> = (1..=4).map(|i| delay_line.process(i as f32)).collect();
+        assert_eq!(output, [0.0, 0.0, 1.0, 2.0]);
+    }
+}
@@ -0,0 +1,512 @@
+use nih_plug::util::{db_to_gain, gain_to_db};
+
+/// The attack and release times of the noise gate's level detector, in milliseconds. The detector
+/// reacts quickly, the gate's own attack and release determine how fast it opens and closes.
+const GATE_DETECTOR_ATTACK_MS: f32 = 0.1;
+const GATE_DETECTOR_RELEASE_MS: f32 = 10.0;
+/// Once open, the level needs to fall this far below the threshold before the gate closes again.
+/// This keeps signals hovering around the threshold from opening and closing the gate repeatedly.
+const GATE_HYSTERESIS_DB: f32 = -6.0;
+
+/// Follows the level of a signal with separate attack and release times. The input is rectified
+/// and then smoothed with a one-pole filter that uses the attack coefficient while the level is
+/// rising and the release coefficient while it's falling.
+#[derive(Debug)]
+pub struct EnvelopeFollower {
+    envelope: f32,
+    sample_rate: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(sample_rate: f32, attack_ms: f32, release_ms: f32) -> Self {
+        let mut follower = Self {
+            envelope: 0.0,
+            sample_rate,
+            attack_ms,
+            release_ms,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+        };
+        follower.update_coefficients();
+
+        follower
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let level = input.abs();
+        let coeff = if level > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope = level + coeff * (self.envelope - level);
+
+        self.envelope
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coeff = time_to_coefficient(self.attack_ms, self.sample_rate);
+        self.release_coeff = time_to_coefficient(self.release_ms, self.sample_rate);
+    }
+}
+
+/// A noise gate that silences the signal when its level drops below a threshold. After the level
+/// falls below the threshold the gate stays open for the hold time before it starts closing.
+#[derive(Debug)]
+pub struct NoiseGate {
+    detector: EnvelopeFollower,
+    sample_rate: f32,
+    /// The threshold as a linear gain.
+    threshold: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    hold_ms: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    hold_samples: usize,
+    hold_samples_left: usize,
+    open: bool,
+    gain: f32,
+}
+
+impl NoiseGate {
+    pub fn new(
+        sample_rate: f32,
+        threshold_db: f32,
+        attack_ms: f32,
+        release_ms: f32,
+        hold_ms: f32,
+    ) -> Self {
+        let mut gate = Self {
+            detector: EnvelopeFollower::new(
+                sample_rate,
+                GATE_DETECTOR_ATTACK_MS,
+                GATE_DETECTOR_RELEASE_MS,
+            ),
+            sample_rate,
+            threshold: db_to_gain(threshold_db),
+            attack_ms,
+            release_ms,
+            hold_ms,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            hold_samples: 0,
+            hold_samples_left: 0,
+            open: true,
+            gain: 1.0,
+        };
+        gate.update_coefficients();
+
+        gate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.detector.set_sample_rate(sample_rate);
+            self.update_coefficients();
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold_db: f32) {
+        self.threshold = db_to_gain(threshold_db);
+    }
+
+    pub fn set_release(&mut self, release_ms: f32) {
+        if release_ms != self.release_ms {
+            self.release_ms = release_ms;
+            self.update_coefficients();
+        }
+    }
+
+    /// Open the gate and forget the signal's level.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.hold_samples_left = 0;
+        self.open = true;
+        self.gain = 1.0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let level = self.detector.process(input);
+        if level >= self.threshold {
+            self.open = true;
+            self.hold_samples_left = self.hold_samples;
+        } else if self.hold_samples_left > 0 {
+            self.hold_samples_left -= 1;
+        } else if level < self.threshold * db_to_gain(GATE_HYSTERESIS_DB) {
+            self.open = false;
+        }
+
+        let (target, coeff) = if self.open {
+            (1.0, self.attack_coeff)
+        } else {
+            (0.0, self.release_coeff)
+        };
+        self.gain = target + coeff * (self.gain - target);
+
+        input * self.gain
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coeff = time_to_coefficient(self.attack_ms, self.sample_rate);
+        self.release_coeff = time_to_coefficient(self.release_ms, self.sample_rate);
+        self.hold_samples = (self.hold_ms / 1000.0 * self.sample_rate).round() as usize;
+    }
+}
+
+/// A brickwall limiter without lookahead. The gain drops instantly whenever a sample would go
+/// over the ceiling, and then recovers with the release time. Signals below the ceiling pass
+/// through unchanged once the gain has recovered.
+#[derive(Debug)]
+pub struct Limiter {
+    /// The ceiling as a linear gain.
+    ceiling: f32,
+    sample_rate: f32,
+    release_ms: f32,
+    release_coeff: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: f32, ceiling_db: f32, release_ms: f32) -> Self {
+        Self {
+            ceiling: db_to_gain(ceiling_db),
+            sample_rate,
+            release_ms,
+            release_coeff: time_to_coefficient(release_ms, sample_rate),
+            gain: 1.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.release_coeff = time_to_coefficient(self.release_ms, sample_rate);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let released_gain = 1.0 + self.release_coeff * (self.gain - 1.0);
+        let level = input.abs();
+        if level * released_gain > self.ceiling {
+            self.gain = self.ceiling / level;
+            self.ceiling.copysign(input)
+        } else {
+            self.gain = released_gain;
+            input * released_gain
+        }
+    }
+}
+
+/// Turns the level of a signal into an amount of extra drive, the same way a compressor turns it
+/// into gain reduction. Below the threshold this returns zero, above it the amount grows with
+/// `1 - 1 / ratio` per decibel, with a soft knee centered on the threshold. Unlike the plain
+/// envelope follower, quiet signals don't get any extra drive at all.
+#[derive(Debug)]
+pub struct DynamicDrive {
+    detector: EnvelopeFollower,
+    threshold_db: f32,
+    ratio: f32,
+    knee_db: f32,
+}
+
+impl DynamicDrive {
+    pub fn new(sample_rate: f32, attack_ms: f32, release_ms: f32) -> Self {
+        Self {
+            detector: EnvelopeFollower::new(sample_rate, attack_ms, release_ms),
+            threshold_db: 0.0,
+            ratio: 1.0,
+            knee_db: 0.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.detector.set_sample_rate(sample_rate);
+    }
+
+    /// A ratio of 1 disables the extra drive.
+    pub fn set_params(&mut self, threshold_db: f32, ratio: f32, knee_db: f32) {
+        self.threshold_db = threshold_db;
+        self.ratio = ratio.max(1.0);
+        self.knee_db = knee_db.max(0.0);
+    }
+
+    pub fn reset(&mut self) {
+        self.detector.reset();
+    }
+
+    /// Follow the input's level and return the extra drive for it in decibels.
+    pub fn process(&mut self, input: f32) -> f32 {
+        let level_db = gain_to_db(self.detector.process(input));
+        let over_db = level_db - self.threshold_db;
+        let slope = 1.0 - 1.0 / self.ratio;
+
+        if 2.0 * over_db <= -self.knee_db {
+            0.0
+        } else if 2.0 * over_db < self.knee_db {
+            slope * (over_db + self.knee_db / 2.0).powi(2) / (2.0 * self.knee_db)
+        } else {
+            slope * over_db
+        }
+    }
+}
+
+/// The one-pole coefficient for a time constant in milliseconds. A time of zero makes the filter
+/// follow the input immediately.
+fn time_to_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    let time_samples = time_ms / 1000.0 * sample_rate;
+    if time_samples <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / time_samples).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    mod envelope_follower {
+        use super::*;
+
+        #[test]
+        fn reaches_steady_level() {
+            let mut follower = EnvelopeFollower::new(SAMPLE_RATE, 1.0, 50.0);
+            let envelope = (0..4800).fold(0.0, |_, _| follower.process(-0.5));
+
+            approx::assert_relative_eq!(envelope, 0.5, epsilon = 1e-4);
+        }
+
+        /// After one time constant the envelope should have covered `1 - 1/e` of the distance.
+        #[test]
+        fn attack_and_release_times() {
+            let attack_samples = (0.01 * SAMPLE_RATE) as usize;
+            let release_samples = (0.1 * SAMPLE_RATE) as usize;
+            let mut follower = EnvelopeFollower::new(SAMPLE_RATE, 10.0, 100.0);
+
+            let envelope = (0..attack_samples).fold(0.0, |_, _| follower.process(1.0));
+            approx::assert_relative_eq!(envelope, 1.0 - (-1.0f32).exp(), epsilon = 1e-3);
+
+            // Let the envelope settle first
+            for _ in 0..attack_samples * 20 {
+                follower.process(1.0);
+            }
+            let envelope = (0..release_samples).fold(0.0, |_, _| follower.process(0.0));
+            approx::assert_relative_eq!(envelope, (-1.0f32).exp(), epsilon = 1e-3);
+        }
+
+        #[test]
+        fn zero_time_follows_immediately() {
+            let mut follower = EnvelopeFollower::new(SAMPLE_RATE, 0.0, 0.0);
+            assert_eq!(follower.process(0.75), 0.75);
+            assert_eq!(follower.process(0.25), 0.25);
+        }
+
+        #[test]
+        fn reset_clears_envelope() {
+            let mut follower = EnvelopeFollower::new(SAMPLE_RATE, 1.0, 50.0);
+            follower.process(1.0);
+            follower.reset();
+
+            assert_eq!(follower.process(0.0), 0.0);
+        }
+    }
+
+    mod dynamic_drive {
+        use super::*;
+
+        /// The extra drive once the detector has settled on a constant level.
+        fn settled_offset(drive: &mut DynamicDrive, level_db: f32) -> f32 {
+            let input = db_to_gain(level_db);
+            (0..4800).fold(0.0, |_, _| drive.process(input))
+        }
+
+        #[test]
+        fn nothing_below_threshold() {
+            let mut drive = DynamicDrive::new(SAMPLE_RATE, 1.0, 50.0);
+            drive.set_params(-20.0, 4.0, 0.0);
+
+            assert_eq!(settled_offset(&mut drive, -30.0), 0.0);
+        }
+
+        #[test]
+        fn follows_ratio_above_threshold() {
+            let mut drive = DynamicDrive::new(SAMPLE_RATE, 1.0, 50.0);
+            drive.set_params(-20.0, 4.0, 0.0);
+
+            // 12 dB over the threshold at 4:1 is 9 dB of extra drive
+            approx::assert_relative_eq!(settled_offset(&mut drive, -8.0), 9.0, epsilon = 1e-2);
+        }
+
+        #[test]
+        fn unity_ratio_is_disabled() {
+            let mut drive = DynamicDrive::new(SAMPLE_RATE, 1.0, 50.0);
+            drive.set_params(-40.0, 1.0, 6.0);
+
+            assert_eq!(settled_offset(&mut drive, 0.0), 0.0);
+        }
+
+        /// The knee should join up with both straight segments without any jumps.
+        #[test]
+        fn soft_knee_is_continuous() {
+            let mut drive = DynamicDrive::new(SAMPLE_RATE, 0.0, 0.0);
+            drive.set_params(-20.0, 2.0, 12.0);
+
+            approx::assert_relative_eq!(drive.process(db_to_gain(-26.0)), 0.0, epsilon = 1e-3);
+            approx::assert_relative_eq!(drive.process(db_to_gain(-20.0)), 0.75, epsilon = 1e-3);
+            approx::assert_relative_eq!(drive.process(db_to_gain(-14.0)), 3.0, epsilon = 1e-3);
+
+            let mut previous = 0.0;
+            for step in 0..=400 {
+                let offset = drive.process(db_to_gain(-30.0 + step as f32 * 0.05));
+                assert!(offset >= previous && offset - previous < 0.05, "{}", step);
+                previous = offset;
+            }
+        }
+    }
+
+    mod limiter {
+        use super::*;
+
+        #[test]
+        fn never_exceeds_ceiling() {
+            let mut limiter = Limiter::new(SAMPLE_RATE, -0.3, 100.0);
+            let ceiling = db_to_gain(-0.3);
+            for i in 0..48000 {
+                let input = (i as f32 * 0.03).sin() * (1.0 + (i as f32 * 0.0007).sin().abs() * 3.0);
+                let output = limiter.process(input);
+                assert!(output.abs() <= ceiling, "{}: {}", i, output);
+            }
+        }
+
+        #[test]
+        fn passes_quiet_signals() {
+            let mut limiter = Limiter::new(SAMPLE_RATE, -0.3, 100.0);
+            for i in 0..4800 {
+                let input = (i as f32 * 0.03).sin() * 0.5;
+                assert_eq!(limiter.process(input), input);
+            }
+        }
+
+        #[test]
+        fn recovers_after_release() {
+            let mut limiter = Limiter::new(SAMPLE_RATE, -0.3, 10.0);
+            limiter.process(4.0);
+            approx::assert_relative_eq!(
+                limiter.process(0.5),
+                0.5 * db_to_gain(-0.3) / 4.0,
+                epsilon = 1e-2
+            );
+
+            for _ in 0..4800 {
+                limiter.process(0.0);
+            }
+            approx::assert_relative_eq!(limiter.process(0.5), 0.5, epsilon = 1e-4);
+        }
+    }
+
+    mod noise_gate {
+        use super::*;
+
+        const PERIOD: usize = 48;
+
+        fn sine(num_samples: usize, amplitude: impl Fn(usize) -> f32) -> Vec<f32> {
+            (0..num_samples)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * amplitude(i))
+                .collect()
+        }
+
+        /// The peak level of every period of a signal.
+        fn peaks(signal: &[f32]) -> Vec<f32> {
+            signal
+                .chunks(PERIOD)
+                .map(|period| period.iter().fold(0.0f32, |peak, x| peak.max(x.abs())))
+                .collect()
+        }
+
+        #[test]
+        fn passes_signal_above_threshold() {
+            let mut gate = NoiseGate::new(SAMPLE_RATE, -40.0, 1.0, 50.0, 10.0);
+            let input = sine(4800, |_| 0.1);
+            let output: Vec<f32> = input.iter().map(|x| gate.process(*x)).collect();
+
+            // The gate may briefly close on the silence at the very start
+            for (output, input) in output.iter().zip(&input).skip(480) {
+                approx::assert_relative_eq!(*output, *input, epsilon = 1e-5);
+            }
+        }
+
+        /// A loud signal drops below the threshold, and then comes back. The gate should close
+        /// during the quiet part and let the next transient through again.
+        #[test]
+        fn closes_below_threshold_and_reopens() {
+            let mut gate = NoiseGate::new(SAMPLE_RATE, -40.0, 1.0, 20.0, 10.0);
+            let input = sine(48000, |i| {
+                if (9600..38400).contains(&i) {
+                    0.001
+                } else {
+                    0.5
+                }
+            });
+            let output: Vec<f32> = input.iter().map(|x| gate.process(*x)).collect();
+            let peaks = peaks(&output);
+
+            // Closed well before the end of the quiet part
+            let quiet_peak = peaks[700];
+            assert!(quiet_peak < 0.001 * 0.01, "{}", quiet_peak);
+
+            // Open again shortly after the transient
+            let reopened_peak = peaks[38400 / PERIOD + 10];
+            approx::assert_relative_eq!(reopened_peak, 0.5, epsilon = 1e-3);
+        }
+
+        /// A slowly decaying signal with some ripple on it passes through the threshold. The gate
+        /// should close once and then stay closed, so the output never gets louder again.
+        #[test]
+        fn does_not_chatter_around_threshold() {
+            let mut gate = NoiseGate::new(SAMPLE_RATE, -40.0, 1.0, 20.0, 10.0);
+            let input = sine(96000, |i| {
+                let decay = 0.1 * (-(i as f32) / 12000.0).exp();
+                let ripple = 1.0 + 0.3 * (std::f32::consts::TAU * i as f32 / 2400.0).sin();
+                decay * ripple
+            });
+            let output: Vec<f32> = input.iter().map(|x| gate.process(*x)).collect();
+
+            let gains: Vec<f32> = peaks(&output)
+                .iter()
+                .zip(peaks(&input))
+                .map(|(output, input)| output / input)
+                .collect();
+            assert!(gains[0] > 0.99);
+            assert!(*gains.last().unwrap() < 0.01);
+
+            // Once the gate starts closing, it never opens back up
+            let closing_idx = gains.iter().position(|gain| *gain < 0.99).unwrap();
+            for pair in gains[closing_idx..].windows(2) {
+                assert!(pair[1] <= pair[0] + 1e-3, "{} -> {}", pair[0], pair[1]);
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use atomic_float::AtomicF32;
+use nih_plug::prelude::*;
+use nih_plug_egui::{create_egui_editor, egui, widgets, EguiState};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::analysis::{SpectrumAnalyzer, SpectrumBuffer, SPECTRUM_FLOOR_DB};
+use crate::presets::{RandomPatch, FACTORY_PRESETS};
+use crate::MelterParams;
+
+/// The number of line segments used to draw the transfer curve.
+const CURVE_SEGMENTS: usize = 128;
+/// The lowest level shown on the meters, in decibels.
+const METER_FLOOR_DB: f32 = -60.0;
+/// The frequency range shown by the spectrum analyzer, in Hertz.
+const SPECTRUM_MIN_HZ: f32 = 20.0;
+const SPECTRUM_MAX_HZ: f32 = 20000.0;
+
+pub fn default_state() -> Arc<EguiState> {
+    EguiState::from_size(420, 660)
+}
+
+/// Create the editor. The peak and RMS levels come from the plugin's
+/// [`OutputMeter`][crate::meter::OutputMeter], and the DC offset comes from its
+/// [`DcMeter`][crate::meter::DcMeter]. The spectrum is computed here from the output the plugin
+/// copies into `spectrum`.
+pub fn create(
+    params: Arc<MelterParams>,
+    peak: Arc<AtomicF32>,
+    rms: Arc<AtomicF32>,
+    dc_offset: Arc<AtomicF32>,
+    spectrum: Arc<SpectrumBuffer>,
+) -> Option<Box<dyn Editor>> {
+    create_egui_editor(
+        params.editor_state.clone(),
+        SpectrumAnalyzer::new(spectrum),
+        |_, _| {},
+        move |egui_ctx, setter, analyzer| {
+            egui::CentralPanel::default().show(egui_ctx, |ui| {
+                ui.heading("Melter");
+
+                ui.label("Gain");
+                ui.add(widgets::ParamSlider::for_param(&params.gain, setter));
+                ui.label("Drive");
+                ui.add(widgets::ParamSlider::for_param(&params.drive, setter));
+                ui.label("Distortion Type");
+                ui.horizontal(|ui| {
+                    ui.add(widgets::ParamSlider::for_param(
+                        &params.distortion_type,
+                        setter,
+                    ));
+                    ui.add(widgets::ParamSlider::for_param(
+                        &params.distortion_type_b,
+                        setter,
+                    ));
+                });
+                ui.label("Shape Morph");
+                ui.add(widgets::ParamSlider::for_param(
+                    &params.shaper_morph,
+                    setter,
+                ));
+
+                draw_transfer_curve(ui, &params);
+
+                ui.horizontal(|ui| {
+                    ui.menu_button("Presets", |ui| {
+                        for preset in &FACTORY_PRESETS {
+                            if ui.button(preset.name).clicked() {
+                                preset.apply(setter, &params);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    // Every click should give a different patch, so the seed comes from the clock
+                    if ui.button("Randomize").clicked() {
+                        let seed = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map_or(0, |time| time.as_nanos() as u64);
+                        RandomPatch::new(seed).apply(setter, &params);
+                    }
+                });
+
+                // The shelves have a slope where the mid band has a Q
+                ui.separator();
+                for (name, boost, freq, shape) in [
+                    (
+                        "Low",
+                        &params.low_boost,
+                        &params.low_freq,
+                        &params.low_slope,
+                    ),
+                    ("Mid", &params.mid_boost, &params.mid_freq, &params.mid_q),
+                    (
+                        "High",
+                        &params.high_boost,
+                        &params.high_freq,
+                        &params.high_slope,
+                    ),
+                ] {
+                    ui.label(name);
+                    ui.horizontal(|ui| {
+                        ui.add(widgets::ParamSlider::for_param(boost, setter));
+                        ui.add(widgets::ParamSlider::for_param(freq, setter));
+                        ui.add(widgets::ParamSlider::for_param(shape, setter));
+                    });
+                }
+
+                ui.separator();
+                draw_meter(ui, "Peak", peak.load(Ordering::Relaxed));
+                draw_meter(ui, "RMS", rms.load(Ordering::Relaxed));
+                ui.label(format!(
+                    "DC Offset: {:+.3}",
+                    dc_offset.load(Ordering::Relaxed)
+                ));
+                draw_spectrum(ui, analyzer);
+            });
+
+            // The curve, the meters and the spectrum change without any interaction
+            egui_ctx.request_repaint();
+        },
+    )
+}
+
+fn draw_transfer_curve(ui: &mut egui::Ui, params: &MelterParams) {
+    let size = egui::vec2(ui.available_width(), 160.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 4.0, egui::Color32::from_gray(24));
+
+    // The input goes from -1 on the left to 1 on the right, and the output is clamped to the
+    // same range vertically
+    let to_screen = |x: f32, y: f32| {
+        egui::pos2(
+            rect.left() + (x + 1.0) * 0.5 * rect.width(),
+            rect.center().y - y.clamp(-1.0, 1.0) * 0.5 * rect.height(),
+        )
+    };
+    let grid_stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(60));
+    painter.line_segment([to_screen(-1.0, 0.0), to_screen(1.0, 0.0)], grid_stroke);
+    painter.line_segment([to_screen(0.0, -1.0), to_screen(0.0, 1.0)], grid_stroke);
+
+    let points: Vec<egui::Pos2> = (0..=CURVE_SEGMENTS)
+        .map(|i| {
+            let x = i as f32 / CURVE_SEGMENTS as f32 * 2.0 - 1.0;
+            to_screen(x, params.transfer_curve(x))
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 140, 40)),
+    ));
+}
+
+fn draw_spectrum(ui: &mut egui::Ui, analyzer: &mut SpectrumAnalyzer) {
+    let size = egui::vec2(ui.available_width(), 120.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 4.0, egui::Color32::from_gray(24));
+
+    // The frequencies are spread out logarithmically, and the levels go from the floor at the
+    // bottom to 0 dB at the top
+    let octaves = (SPECTRUM_MAX_HZ / SPECTRUM_MIN_HZ).log2();
+    let to_screen = |freq: f32, level_db: f32| {
+        egui::pos2(
+            rect.left() + (freq / SPECTRUM_MIN_HZ).log2() / octaves * rect.width(),
+            rect.top() + (level_db / SPECTRUM_FLOOR_DB).clamp(0.0, 1.0) * rect.height(),
+        )
+    };
+
+    analyzer.update();
+    let points: Vec<egui::Pos2> = analyzer
+        .magnitudes_db()
+        .iter()
+        .enumerate()
+        .map(|(bin, &level_db)| (analyzer.bin_frequency(bin), level_db))
+        .filter(|(freq, _)| (SPECTRUM_MIN_HZ..=SPECTRUM_MAX_HZ).contains(freq))
+        .map(|(freq, level_db)| to_screen(freq, level_db))
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(80, 180, 255)),
+    ));
+}
+
+fn draw_meter(ui: &mut egui::Ui, name: &str, level: f32) {
+    let level_db = util::gain_to_db(level).max(METER_FLOOR_DB);
+    let fill = 1.0 - level_db / METER_FLOOR_DB;
+    ui.horizontal(|ui| {
+        ui.label(name);
+        ui.add(egui::ProgressBar::new(fill).text(format!("{level_db:.1} dB")));
+    });
+}
@@ -1,43 +1,155 @@
+// The maximum number of bands a `ParametricEQ` can hold
+pub const MAX_BANDS: usize = 16;
+
+// The most biquads a `CascadedFilter` can chain, for a slope of 48 dB/octave
+pub const MAX_CASCADE_STAGES: usize = 4;
+
+// Filter state below this value is flushed to zero
+const DENORMAL_THRESHOLD: f64 = 1e-30;
+
+// Converts a bandwidth in octaves to the equivalent Q, so 1 octave is roughly a Q of 1.41
+pub fn q_from_bandwidth_octaves(bw: f32) -> f32 {
+    let ratio = 2.0f32.powf(bw);
+    ratio.sqrt() / (ratio - 1.0)
+}
+
+// The inverse of `q_from_bandwidth_octaves()`
+pub fn bandwidth_octaves_from_q(q: f32) -> f32 {
+    let x = 1.0 / (2.0 * q);
+    2.0 * (x + (x * x + 1.0).sqrt()).log2()
+}
+
+// Whether two parameter values are close enough to share the same coefficients
+fn nearly_equal(a: f32, b: f32) -> bool {
+    (a - b).abs() <= PARAM_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
 // Enum to represent different types of EQ bands
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BandType {
     LowShelf,
     Peak,
     HighShelf,
+    #[allow(dead_code)]
+    Notch,
+    #[allow(dead_code)]
+    Allpass,
+    HighPass,
+    LowPass,
+    #[allow(dead_code)]
+    Tilt,
 }
 
-// Struct to hold biquad filter coefficients
+// The highest frequency a band can be set to, as a fraction of the sample rate. Above Nyquist
+// the biquad formulas stop making sense, and close to it the bands get very sensitive.
+pub const MAX_FREQ_RATIO: f32 = 0.45;
+// The lowest frequency a band can be set to, in Hz
+const MIN_FREQ_HZ: f32 = 1.0;
+// The lowest Q a band can be set to. A Q of zero or less would divide by zero.
+const MIN_Q: f32 = 0.01;
+// The most a band can boost or cut by, in decibels
+const MAX_GAIN_DB: f32 = 30.0;
+// The range of the shelves' slope, which they take in place of the Q. This is the shelf slope S
+// from the RBJ cookbook. At 1 the transition is as steep as it gets without the response
+// overshooting the gain, lower slopes spread it out over more octaves.
+pub const MIN_SHELF_SLOPE: f32 = 0.1;
+pub const MAX_SHELF_SLOPE: f32 = 1.0;
+
+// Parameters that changed by less than this, relative to their size, don't cause the coefficients
+// to be recomputed
+const PARAM_TOLERANCE: f32 = 1e-6;
+
+// The smallest a0 the coefficients can still be normalized by. Anything smaller than this means
+// the band's parameters were invalid.
+const MIN_A0: f64 = 1e-6;
+
+// Struct to hold biquad filter coefficients. Like the filter state these are kept in double
+// precision. At low frequencies and high sample rates the poles get so close to the unit circle
+// that rounding the coefficients to single precision can put them on or even outside of it.
 #[derive(Clone, Copy)]
 pub struct BiquadCoeffs {
-    b0: f32,
-    b1: f32,
-    b2: f32,
+    b0: f64,
+    b1: f64,
+    b2: f64,
     #[allow(dead_code)]
-    a0: f32, // Not used in the filter, but included for completeness
-    a1: f32,
-    a2: f32,
+    a0: f64, // Not used in the filter, but included for completeness
+    a1: f64,
+    a2: f64,
 }
 
-// Struct to hold filter state variables
+impl BiquadCoeffs {
+    pub const IDENTITY: BiquadCoeffs = BiquadCoeffs {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a0: 1.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    // The per-sample increment needed to get from `self` to `target` in `steps` samples
+    fn step_towards(&self, target: &BiquadCoeffs, steps: usize) -> BiquadCoeffs {
+        let steps = steps as f64;
+        BiquadCoeffs {
+            b0: (target.b0 - self.b0) / steps,
+            b1: (target.b1 - self.b1) / steps,
+            b2: (target.b2 - self.b2) / steps,
+            a0: 0.0,
+            a1: (target.a1 - self.a1) / steps,
+            a2: (target.a2 - self.a2) / steps,
+        }
+    }
+
+    fn add_step(&mut self, step: &BiquadCoeffs) {
+        self.b0 += step.b0;
+        self.b1 += step.b1;
+        self.b2 += step.b2;
+        self.a1 += step.a1;
+        self.a2 += step.a2;
+    }
+}
+
+// Struct to hold filter state variables. The filter is a transposed direct form II biquad, so
+// there are only two state variables and the coefficient changes from automation are absorbed by
+// the state instead of being applied to delayed samples. These are kept in double precision since
+// the recursion otherwise accumulates audible quantization noise at high Qs and low frequencies,
+// especially at oversampled sample rates.
 #[derive(Clone, Copy)]
 pub struct FilterState {
-    x1: f32,
-    x2: f32,
-    y1: f32,
-    y2: f32,
+    s1: f64,
+    s2: f64,
 }
 
+#[derive(Clone)]
 pub struct ParametricEQ {
     sample_rate: f32,
+    smoothing_samples: usize,
     bands: Vec<EQBand>,
+    // Only the first `active_bands` bands are processed. This allows bands to be switched on and
+    // off on the audio thread without allocating.
+    active_bands: usize,
 }
 
+#[derive(Clone)]
 pub struct EQBand {
     band_type: BandType,
     freq: f32,
     gain: f32,
     q: f32,
+    // The sample rate the coefficients were last computed for
+    sample_rate: f32,
+    // Whether the coefficients still need to be computed from the parameters above. When this is
+    // false, setting the same parameters again doesn't do anything.
+    dirty: bool,
+    enabled: bool,
+    // The coefficients currently used for filtering. When the parameters change these are
+    // linearly interpolated towards `target_coeffs` over `smoothing_samples` samples to avoid
+    // zipper noise.
     coeffs: BiquadCoeffs,
+    target_coeffs: BiquadCoeffs,
+    coeff_step: BiquadCoeffs,
+    smoothing_samples: usize,
+    smoothing_steps_left: usize,
     state: FilterState,
 }
 
@@ -45,14 +157,33 @@ impl ParametricEQ {
     pub fn new(sample_rate: f32) -> Self {
         ParametricEQ {
             sample_rate,
+            smoothing_samples: 0,
             bands: Vec::new(),
+            active_bands: MAX_BANDS,
         }
     }
 
+    // The sample rate the bands' coefficients are currently computed for
+    #[allow(dead_code)]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
         for band in &mut self.bands {
             band.set_params(band.freq, band.gain, band.q, sample_rate);
+            // Gliding between coefficients computed for different sample rates makes no sense
+            band.skip_smoothing();
+        }
+    }
+
+    // Set the number of samples it takes for the bands to glide to new coefficients after their
+    // parameters change. Zero disables the smoothing.
+    pub fn set_smoothing_samples(&mut self, samples: usize) {
+        self.smoothing_samples = samples;
+        for band in &mut self.bands {
+            band.set_smoothing_samples(samples);
         }
     }
 
@@ -64,16 +195,34 @@ impl ParametricEQ {
         gain_db: f32,
         q: f32,
     ) -> Result<(), &'static str> {
-        if self.bands.len() >= 16 {
+        self.insert_band(self.bands.len(), band_type, freq, gain_db, q)
+    }
+
+    // Insert a new band at `index`, shifting all bands after it back by one
+    pub fn insert_band(
+        &mut self,
+        index: usize,
+        band_type: BandType,
+        freq: f32,
+        gain_db: f32,
+        q: f32,
+    ) -> Result<(), &'static str> {
+        if self.bands.len() >= MAX_BANDS {
             return Err("Maximum number of bands (16) reached");
         }
+        if index > self.bands.len() {
+            return Err("Band index out of range");
+        }
         let mut new_band = EQBand::new(band_type, freq, gain_db, q);
         new_band.set_params(freq, gain_db, q, self.sample_rate);
-        self.bands.push(new_band);
+        new_band.skip_smoothing();
+        new_band.set_smoothing_samples(self.smoothing_samples);
+        self.bands.insert(index, new_band);
         Ok(())
     }
 
     // Remove a band from the EQ
+    #[allow(dead_code)]
     pub fn remove_band(&mut self, index: usize) -> Result<(), &'static str> {
         if index >= self.bands.len() {
             return Err("Band index out of range");
@@ -82,7 +231,20 @@ impl ParametricEQ {
         Ok(())
     }
 
-    // Set parameters for a specific band
+    // Only process the first `count` bands. The other bands keep their parameters, but they are
+    // skipped entirely. Bands that become active again start from a clean state.
+    pub fn set_active_bands(&mut self, count: usize) {
+        let count = count.min(MAX_BANDS);
+        if count > self.active_bands {
+            for band in self.bands.iter_mut().take(count).skip(self.active_bands) {
+                band.reset();
+            }
+        }
+        self.active_bands = count;
+    }
+
+    // Set parameters for a specific band. Out of range parameters are clamped, see
+    // `EQBand::set_params()`.
     pub fn set_band_params(
         &mut self,
         band: usize,
@@ -97,14 +259,116 @@ impl ParametricEQ {
         Ok(())
     }
 
+    // Give the bands the same parameters as `other`'s bands by copying its coefficients instead
+    // of computing them again. The bands keep their own filter state and glide to the new
+    // coefficients like they would after `set_band_params()`. Both EQs need to use the same band
+    // types and sample rate for this to make sense.
+    pub fn copy_params_from(&mut self, other: &ParametricEQ) {
+        for (band, other_band) in self.bands.iter_mut().zip(&other.bands) {
+            band.copy_params_from(other_band);
+        }
+    }
+
+    // The coefficients a band is set to, or gliding towards
+    #[allow(dead_code)]
+    pub fn band_coeffs(&self, band: usize) -> Result<BiquadCoeffs, &'static str> {
+        match self.bands.get(band) {
+            Some(band) => Ok(band.target_coeffs),
+            None => Err("Band index out of range"),
+        }
+    }
+
+    // Give a band coefficients that were computed ahead of time with `set_band_params()` on
+    // another EQ with the same band types and sample rate. The band glides to them like it would
+    // after `set_band_params()`.
+    #[allow(dead_code)]
+    pub fn set_band_coeffs(
+        &mut self,
+        band: usize,
+        coeffs: BiquadCoeffs,
+    ) -> Result<(), &'static str> {
+        if band >= self.bands.len() {
+            return Err("Band index out of range");
+        }
+        self.bands[band].set_coeffs(coeffs);
+        Ok(())
+    }
+
+    // Enable or bypass a specific band without removing it
+    #[allow(dead_code)]
+    pub fn set_band_enabled(&mut self, band: usize, enabled: bool) -> Result<(), &'static str> {
+        if band >= self.bands.len() {
+            return Err("Band index out of range");
+        }
+        self.bands[band].set_enabled(enabled);
+        Ok(())
+    }
+
+    // Clear the filter state of all bands
+    pub fn reset(&mut self) {
+        for band in &mut self.bands {
+            band.reset();
+        }
+    }
+
+    // The combined magnitude response of all bands at `freq`, in decibels
+    pub fn magnitude_db(&self, freq: f32) -> f32 {
+        self.bands
+            .iter()
+            .take(self.active_bands)
+            .map(|band| band.magnitude_db(freq))
+            .sum()
+    }
+
+    // The average power gain of the EQ across the audible spectrum in decibels, measured at
+    // logarithmically spaced frequencies. Attenuating the signal by this amount after the EQ keeps
+    // the broadband level roughly constant regardless of how much the bands boost or cut.
+    pub fn broadband_gain_db(&self) -> f32 {
+        const NUM_POINTS: usize = 24;
+        const MIN_FREQ: f32 = 20.0;
+        const MAX_FREQ: f32 = 20000.0;
+
+        let max_freq = MAX_FREQ.min(self.sample_rate * 0.45);
+        let freq_ratio = (max_freq / MIN_FREQ).powf(1.0 / (NUM_POINTS - 1) as f32);
+        let total_power_gain: f32 = (0..NUM_POINTS)
+            .map(|i| 10.0f32.powf(self.magnitude_db(MIN_FREQ * freq_ratio.powi(i as i32)) / 10.0))
+            .sum();
+
+        10.0 * (total_power_gain / NUM_POINTS as f32).log10()
+    }
+
+    // The combined phase response of all bands at `freq`, in radians. Like the phase response of
+    // the individual bands this is wrapped to the `[-π, π]` range.
+    #[allow(dead_code)]
+    pub fn phase_radians(&self, freq: f32) -> f32 {
+        let (re, im) = self
+            .bands
+            .iter()
+            .take(self.active_bands)
+            .map(|band| band.frequency_response(freq))
+            .fold((1.0, 0.0), |(acc_re, acc_im), (re, im)| {
+                (acc_re * re - acc_im * im, acc_re * im + acc_im * re)
+            });
+        im.atan2(re) as f32
+    }
+
     // Process a single sample through all bands
     pub fn process(&mut self, input: f32) -> f32 {
         let mut output = input;
-        for band in &mut self.bands {
+        for band in self.bands.iter_mut().take(self.active_bands) {
             output = band.process(output);
         }
         output
     }
+
+    // Process a block of samples in place through all bands. This runs the bands one after another
+    // over the entire block, which is friendlier to the cache than `process()`.
+    #[allow(dead_code)]
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        for band in self.bands.iter_mut().take(self.active_bands) {
+            band.process_block(samples);
+        }
+    }
 }
 
 impl EQBand {
@@ -114,60 +378,91 @@ impl EQBand {
             freq,
             gain,
             q,
-            coeffs: BiquadCoeffs {
-                b0: 1.0,
+            sample_rate: 44100.0,
+            dirty: true,
+            enabled: true,
+            coeffs: BiquadCoeffs::IDENTITY,
+            target_coeffs: BiquadCoeffs::IDENTITY,
+            coeff_step: BiquadCoeffs {
+                b0: 0.0,
                 b1: 0.0,
                 b2: 0.0,
-                a0: 1.0,
+                a0: 0.0,
                 a1: 0.0,
                 a2: 0.0,
             },
-            state: FilterState {
-                x1: 0.0,
-                x2: 0.0,
-                y1: 0.0,
-                y2: 0.0,
-            },
+            smoothing_samples: 0,
+            smoothing_steps_left: 0,
+            state: FilterState { s1: 0.0, s2: 0.0 },
         }
     }
 
-    // Set parameters for the band and calculate filter coefficients
+    // Set parameters for the band and calculate filter coefficients. The frequency is clamped to
+    // between `MIN_FREQ_HZ` and `MAX_FREQ_RATIO` times the sample rate, the Q to at least `MIN_Q`,
+    // and the gain to `MAX_GAIN_DB` in either direction. The shelves use `q` as their slope
+    // instead, which is clamped to between `MIN_SHELF_SLOPE` and `MAX_SHELF_SLOPE`. The requested
+    // values are still what gets stored, so raising the sample rate later can move the band back
+    // to the requested frequency.
     pub fn set_params(&mut self, freq: f32, gain_db: f32, q: f32, sample_rate: f32) {
-        self.freq = freq;
-        let a = 10.0f32.powf(gain_db / 40.0); // Square root of the linear gain
+        // This gets called every block, and most of the time nothing has changed
+        if !self.dirty
+            && nearly_equal(freq, self.freq)
+            && nearly_equal(gain_db, self.gain)
+            && nearly_equal(q, self.q)
+            && sample_rate == self.sample_rate
+        {
+            return;
+        }
 
-        // Adjust Q for shelving filters
-        let adjusted_q = match self.band_type {
-            BandType::LowShelf | BandType::HighShelf => q * a.max(1.0),
-            BandType::Peak => q,
-        };
-        self.q = adjusted_q;
+        // The unadjusted values are stored so the coefficients can be recomputed from them when the
+        // sample rate changes
+        self.freq = freq;
+        self.gain = gain_db;
+        self.q = q;
+        self.sample_rate = sample_rate;
+        let gain_db = gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB) as f64;
+        let a = 10.0f64.powf(gain_db / 40.0); // Square root of the linear gain
+        let freq = freq.clamp(MIN_FREQ_HZ, (sample_rate * MAX_FREQ_RATIO).max(MIN_FREQ_HZ)) as f64;
+        let q = q.max(MIN_Q) as f64;
 
-        // Calculate omega directly without pre-warping
-        let omega = 2.0 * std::f32::consts::PI * freq / sample_rate;
+        // The bilinear transform maps the center frequency exactly, but it squashes the bandwidth
+        // more and more as the frequency approaches Nyquist. For the bands where Q describes a
+        // bandwidth this pre-warps that bandwidth by `omega / sin(omega)` so the band's shape stays
+        // the same regardless of the (oversampled) sample rate. For small omegas this is equal to
+        // the usual `sin(omega) / (2 * Q)`. The Q of the high-pass and low-pass filters describes
+        // their resonance instead, so those are left alone.
+        let omega = 2.0 * std::f64::consts::PI * freq / sample_rate as f64;
         let sin_omega = omega.sin();
         let cos_omega = omega.cos();
-        let alpha = sin_omega / (2.0 * adjusted_q);
+        let alpha = match self.band_type {
+            // The slope alone sets the shelves' shape. Their Q used to be scaled by the gain, which
+            // made boosts and cuts by the same amount look different and made the shelf narrower
+            // as its gain went up.
+            BandType::LowShelf | BandType::HighShelf => {
+                let slope = q.clamp(MIN_SHELF_SLOPE as f64, MAX_SHELF_SLOPE as f64);
+                sin_omega / 2.0 * ((a + 1.0 / a) * (1.0 / slope - 1.0) + 2.0).sqrt()
+            }
+            BandType::HighPass | BandType::LowPass => sin_omega / (2.0 * q),
+            _ if sin_omega <= 0.0 => sin_omega / (2.0 * q),
+            _ => sin_omega * ((1.0 / (2.0 * q)).asinh() * omega / sin_omega).sinh(),
+        };
 
         let (b0, b1, b2, a0, a1, a2) = match self.band_type {
             BandType::LowShelf => {
-                // Use a for boost (a > 1) and 1/a for cut (a < 1)
-                let (ap1, am1) = if a > 1.0 {
-                    (a + 1.0, a - 1.0)
-                } else {
-                    let recip_a = 1.0 / a;
-                    (recip_a + 1.0, recip_a - 1.0)
-                };
+                // A cut is the exact inverse of a boost by the same amount
+                let ap1 = a + 1.0;
+                let am1 = a - 1.0;
                 let ap1_cos = ap1 * cos_omega;
                 let am1_cos = am1 * cos_omega;
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
 
                 (
-                    a * (ap1 - am1_cos + alpha),
+                    a * (ap1 - am1_cos + sqrt_a_alpha),
                     2.0 * a * (am1 - ap1_cos),
-                    a * (ap1 - am1_cos - alpha),
-                    ap1 + am1_cos + alpha,
+                    a * (ap1 - am1_cos - sqrt_a_alpha),
+                    ap1 + am1_cos + sqrt_a_alpha,
                     -2.0 * (am1 + ap1_cos),
-                    ap1 + am1_cos - alpha,
+                    ap1 + am1_cos - sqrt_a_alpha,
                 )
             }
             BandType::Peak => {
@@ -183,53 +478,1814 @@ impl EQBand {
                 )
             }
             BandType::HighShelf => {
-                // Use a for boost (a > 1) and 1/a for cut (a < 1)
-                let (ap1, am1) = if a > 1.0 {
-                    (a + 1.0, a - 1.0)
-                } else {
-                    let recip_a = 1.0 / a;
-                    (recip_a + 1.0, recip_a - 1.0)
-                };
+                let ap1 = a + 1.0;
+                let am1 = a - 1.0;
                 let ap1_cos = ap1 * cos_omega;
                 let am1_cos = am1 * cos_omega;
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
 
                 (
-                    a * (ap1 + am1_cos + alpha),
+                    a * (ap1 + am1_cos + sqrt_a_alpha),
                     -2.0 * a * (am1 + ap1_cos),
-                    a * (ap1 + am1_cos - alpha),
-                    ap1 - am1_cos + alpha,
+                    a * (ap1 + am1_cos - sqrt_a_alpha),
+                    ap1 - am1_cos + sqrt_a_alpha,
+                    2.0 * (am1 - ap1_cos),
+                    ap1 - am1_cos - sqrt_a_alpha,
+                )
+            }
+            BandType::Notch => {
+                // The gain is ignored, the Q alone sets the width of the notch
+                (
+                    1.0,
+                    -2.0 * cos_omega,
+                    1.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                )
+            }
+            BandType::Allpass => {
+                // Unity magnitude everywhere, the phase rotates by a full turn around `freq`
+                (
+                    1.0 - alpha,
+                    -2.0 * cos_omega,
+                    1.0 + alpha,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                )
+            }
+            BandType::HighPass => {
+                // Resonant 12 dB/octave cutoff, the gain is ignored
+                let one_plus_cos = 1.0 + cos_omega;
+                (
+                    one_plus_cos / 2.0,
+                    -one_plus_cos,
+                    one_plus_cos / 2.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                )
+            }
+            BandType::LowPass => {
+                // Resonant 12 dB/octave cutoff, the gain is ignored
+                let one_minus_cos = 1.0 - cos_omega;
+                (
+                    one_minus_cos / 2.0,
+                    one_minus_cos,
+                    one_minus_cos / 2.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                )
+            }
+            BandType::Tilt => {
+                // A high shelf boosting by the full gain paired with a broadband cut of half that
+                // gain, so the lows go down by half the gain while the highs go up by the other
+                // half. The shelf's leading `a` factor cancels out against that cut. Unlike the
+                // shelves above this works for both positive and negative gains.
+                let ap1 = a + 1.0;
+                let am1 = a - 1.0;
+                let ap1_cos = ap1 * cos_omega;
+                let am1_cos = am1 * cos_omega;
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+                (
+                    ap1 + am1_cos + sqrt_a_alpha,
+                    -2.0 * (am1 + ap1_cos),
+                    ap1 + am1_cos - sqrt_a_alpha,
+                    ap1 - am1_cos + sqrt_a_alpha,
                     2.0 * (am1 - ap1_cos),
-                    ap1 - am1_cos - alpha,
+                    ap1 - am1_cos - sqrt_a_alpha,
                 )
             }
         };
 
-        // Normalize the coefficients by a0
-        let epsilon = 1e-6; // Small value to prevent division by zero
-        self.coeffs = BiquadCoeffs {
-            b0: b0 / (a0 + epsilon),
-            b1: b1 / (a0 + epsilon),
-            b2: b2 / (a0 + epsilon),
-            a0: 1.0,
-            a1: a1 / (a0 + epsilon),
-            a2: a2 / (a0 + epsilon),
+        // Normalize the coefficients by a0. For valid parameters a0 is always finite and well away
+        // from zero, invalid ones like a NaN frequency can break that. The band passes
+        // the signal through unchanged in that case instead of blowing up.
+        self.target_coeffs = if a0.is_finite() && a0.abs() > MIN_A0 {
+            BiquadCoeffs {
+                b0: b0 / a0,
+                b1: b1 / a0,
+                b2: b2 / a0,
+                a0: 1.0,
+                a1: a1 / a0,
+                a2: a2 / a0,
+            }
+        } else {
+            BiquadCoeffs::IDENTITY
         };
+        self.dirty = false;
+
+        self.start_glide();
+    }
+
+    // Take over the parameters and target coefficients of another band of the same type
+    pub fn copy_params_from(&mut self, other: &EQBand) {
+        debug_assert!(self.band_type == other.band_type);
+
+        // Copying the same parameters again would restart the glide, which the other band doesn't
+        // do either
+        if !self.dirty
+            && self.freq == other.freq
+            && self.gain == other.gain
+            && self.q == other.q
+            && self.sample_rate == other.sample_rate
+        {
+            return;
+        }
+
+        self.freq = other.freq;
+        self.gain = other.gain;
+        self.q = other.q;
+        self.sample_rate = other.sample_rate;
+        self.target_coeffs = other.target_coeffs;
+        self.dirty = other.dirty;
+        self.start_glide();
+    }
+
+    // Glide to coefficients computed by another band of the same type
+    #[allow(dead_code)]
+    pub fn set_coeffs(&mut self, coeffs: BiquadCoeffs) {
+        self.target_coeffs = coeffs;
+        // The parameters no longer match the coefficients, so setting or copying parameters
+        // always updates the coefficients again
+        self.dirty = true;
+        self.start_glide();
+    }
+
+    // Glide from wherever the coefficients currently are to the target coefficients
+    fn start_glide(&mut self) {
+        if self.smoothing_samples == 0 {
+            self.skip_smoothing();
+        } else {
+            self.coeff_step = self
+                .coeffs
+                .step_towards(&self.target_coeffs, self.smoothing_samples);
+            self.smoothing_steps_left = self.smoothing_samples;
+        }
+    }
+
+    // Set the number of samples it takes to glide to new coefficients after `set_params()`
+    pub fn set_smoothing_samples(&mut self, samples: usize) {
+        self.smoothing_samples = samples;
+    }
+
+    // Immediately jump to the target coefficients
+    pub fn skip_smoothing(&mut self) {
+        self.coeffs = self.target_coeffs;
+        self.smoothing_steps_left = 0;
+    }
+
+    // Evaluate the band's transfer function on the unit circle at `freq`. This uses the target
+    // coefficients, so it's the response the band settles on and not the one mid-glide. Returns the
+    // real and imaginary parts of the response.
+    fn frequency_response(&self, freq: f32) -> (f64, f64) {
+        if !self.enabled {
+            return (1.0, 0.0);
+        }
+
+        let omega = 2.0 * std::f64::consts::PI * freq as f64 / self.sample_rate as f64;
+        let (sin_omega, cos_omega) = omega.sin_cos();
+        let (sin_2omega, cos_2omega) = (2.0 * omega).sin_cos();
+        let coeffs = &self.target_coeffs;
+
+        // H(z) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2), with z = e^(jω)
+        let num_re = coeffs.b0 + coeffs.b1 * cos_omega + coeffs.b2 * cos_2omega;
+        let num_im = -(coeffs.b1 * sin_omega + coeffs.b2 * sin_2omega);
+        let den_re = 1.0 + coeffs.a1 * cos_omega + coeffs.a2 * cos_2omega;
+        let den_im = -(coeffs.a1 * sin_omega + coeffs.a2 * sin_2omega);
+
+        let den_norm = den_re * den_re + den_im * den_im;
+        (
+            (num_re * den_re + num_im * den_im) / den_norm,
+            (num_im * den_re - num_re * den_im) / den_norm,
+        )
+    }
+
+    // The band's magnitude response at `freq`, in decibels
+    pub fn magnitude_db(&self, freq: f32) -> f32 {
+        let (re, im) = self.frequency_response(freq);
+        (10.0 * (re * re + im * im).log10()) as f32
+    }
+
+    // The band's phase response at `freq`, in radians wrapped to the `[-π, π]` range
+    #[allow(dead_code)]
+    pub fn phase_radians(&self, freq: f32) -> f32 {
+        let (re, im) = self.frequency_response(freq);
+        im.atan2(re) as f32
+    }
+
+    // Enable or bypass the band. A bypassed band keeps filtering in the background so its delay
+    // lines are up to date and re-enabling it doesn't cause a click.
+    #[allow(dead_code)]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // Clear the filter state
+    pub fn reset(&mut self) {
+        self.state = FilterState { s1: 0.0, s2: 0.0 };
     }
 
     // Process a single sample through the band's filter
     pub fn process(&mut self, input: f32) -> f32 {
-        let output = self.coeffs.b0 * input
-            + self.coeffs.b1 * self.state.x1
-            + self.coeffs.b2 * self.state.x2
-            - self.coeffs.a1 * self.state.y1
-            - self.coeffs.a2 * self.state.y2;
-
-        // Update delay lines
-        self.state.x2 = self.state.x1;
-        self.state.x1 = input;
-        self.state.y2 = self.state.y1;
-        self.state.y1 = output;
+        if self.smoothing_steps_left > 0 {
+            self.smoothing_steps_left -= 1;
+            if self.smoothing_steps_left == 0 {
+                // Avoids accumulating rounding errors from the interpolation
+                self.coeffs = self.target_coeffs;
+            } else {
+                self.coeffs.add_step(&self.coeff_step);
+            }
+        }
+
+        let input_f64 = input as f64;
+        let output = self.coeffs.b0 * input_f64 + self.state.s1;
+
+        // Update the state. Once the signal has decayed to silence the recursion would otherwise
+        // keep producing ever smaller subnormal numbers, which are very slow to compute with.
+        self.state.s1 = self.coeffs.b1 * input_f64 - self.coeffs.a1 * output + self.state.s2;
+        self.state.s2 = self.coeffs.b2 * input_f64 - self.coeffs.a2 * output;
+        if self.state.s1.abs() < DENORMAL_THRESHOLD && self.state.s2.abs() < DENORMAL_THRESHOLD {
+            self.state.s1 = 0.0;
+            self.state.s2 = 0.0;
+        }
+
+        if self.enabled {
+            output as f32
+        } else {
+            input
+        }
+    }
+
+    // Process a block of samples in place through the band's filter
+    #[allow(dead_code)]
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+// The Q of the `stage`th of `num_stages` biquads that together form a Butterworth filter of order
+// `2 * num_stages`. Chaining biquads with these staggered Qs keeps the passband maximally flat and
+// the response at the cutoff at -3 dB, where identical Butterworth biquads would lose 3 dB each.
+pub fn butterworth_q(stage: usize, num_stages: usize) -> f32 {
+    let order = 2 * num_stages;
+    let angle = std::f32::consts::PI * (2 * stage + 1) as f32 / (2 * order) as f32;
+    1.0 / (2.0 * angle.cos())
+}
+
+// A high-pass or low-pass filter made of up to `MAX_CASCADE_STAGES` chained biquads, each stage
+// adds another 12 dB/octave to the slope. The stages' Qs are staggered so the whole cascade is a
+// Butterworth filter. The stages switch to new coefficients immediately.
+#[derive(Clone)]
+pub struct CascadedFilter {
+    stages: [EQBand; MAX_CASCADE_STAGES],
+    // Only the first `num_stages` stages are processed
+    num_stages: usize,
+    freq: f32,
+    sample_rate: f32,
+}
+
+impl CascadedFilter {
+    // Create a single stage filter. Only the high-pass and low-pass band types can be cascaded.
+    pub fn new(band_type: BandType, freq: f32, sample_rate: f32) -> Result<Self, &'static str> {
+        if !matches!(band_type, BandType::HighPass | BandType::LowPass) {
+            return Err("Only high-pass and low-pass filters can be cascaded");
+        }
+
+        let mut filter = CascadedFilter {
+            stages: std::array::from_fn(|_| {
+                EQBand::new(band_type, freq, 0.0, std::f32::consts::FRAC_1_SQRT_2)
+            }),
+            num_stages: 1,
+            freq,
+            sample_rate,
+        };
+        filter.update_stages();
+
+        Ok(filter)
+    }
+
+    #[allow(dead_code)]
+    pub fn num_stages(&self) -> usize {
+        self.num_stages
+    }
+
+    // Change the number of chained biquads, for a slope of `12 * num_stages` dB/octave. Stages
+    // that become active start from a clean state.
+    pub fn set_num_stages(&mut self, num_stages: usize) -> Result<(), &'static str> {
+        if num_stages == 0 || num_stages > MAX_CASCADE_STAGES {
+            return Err("Number of stages out of range");
+        }
+        if num_stages == self.num_stages {
+            return Ok(());
+        }
+
+        for stage in self
+            .stages
+            .iter_mut()
+            .take(num_stages)
+            .skip(self.num_stages)
+        {
+            stage.reset();
+        }
+        self.num_stages = num_stages;
+        self.update_stages();
+        Ok(())
+    }
+
+    pub fn set_freq(&mut self, freq: f32) {
+        self.freq = freq;
+        self.update_stages();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_stages();
+    }
+
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let mut output = input;
+        for stage in self.stages.iter_mut().take(self.num_stages) {
+            output = stage.process(output);
+        }
+        output
+    }
+
+    // The combined magnitude response of all active stages at `freq`, in decibels
+    #[allow(dead_code)]
+    pub fn magnitude_db(&self, freq: f32) -> f32 {
+        self.stages
+            .iter()
+            .take(self.num_stages)
+            .map(|stage| stage.magnitude_db(freq))
+            .sum()
+    }
+
+    fn update_stages(&mut self) {
+        let num_stages = self.num_stages;
+        for (i, stage) in self.stages.iter_mut().take(num_stages).enumerate() {
+            stage.set_params(
+                self.freq,
+                0.0,
+                butterworth_q(i, num_stages),
+                self.sample_rate,
+            );
+        }
+    }
+}
+
+// A fourth order Linkwitz-Riley low-pass or high-pass filter, which is two identical second order
+// Butterworth biquads in a row. The low-pass and the high-pass at the same frequency are both 6 dB
+// down there and stay in phase with each other, so the two add up to an allpass.
+#[derive(Clone)]
+pub struct LinkwitzRiley {
+    stages: [EQBand; 2],
+    freq: f32,
+    sample_rate: f32,
+}
+
+impl LinkwitzRiley {
+    // Only the high-pass and low-pass band types can be used
+    pub fn new(band_type: BandType, freq: f32, sample_rate: f32) -> Result<Self, &'static str> {
+        if !matches!(band_type, BandType::HighPass | BandType::LowPass) {
+            return Err("Only high-pass and low-pass filters can be Linkwitz-Riley filters");
+        }
+
+        let mut filter = LinkwitzRiley {
+            stages: std::array::from_fn(|_| {
+                EQBand::new(band_type, freq, 0.0, std::f32::consts::FRAC_1_SQRT_2)
+            }),
+            freq,
+            sample_rate,
+        };
+        filter.update_stages();
+
+        Ok(filter)
+    }
+
+    pub fn set_freq(&mut self, freq: f32) {
+        self.freq = freq;
+        self.update_stages();
+    }
 
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_stages();
+    }
+
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let mut output = input;
+        for stage in &mut self.stages {
+            output = stage.process(output);
+        }
         output
     }
+
+    // The magnitude response at `freq`, in decibels
+    #[allow(dead_code)]
+    pub fn magnitude_db(&self, freq: f32) -> f32 {
+        self.stages
+            .iter()
+            .map(|stage| stage.magnitude_db(freq))
+            .sum()
+    }
+
+    fn update_stages(&mut self) {
+        for stage in &mut self.stages {
+            stage.set_params(
+                self.freq,
+                0.0,
+                std::f32::consts::FRAC_1_SQRT_2,
+                self.sample_rate,
+            );
+        }
+    }
+}
+
+// Splits a signal into a low, a mid and a high band with Linkwitz-Riley filters at two crossover
+// frequencies. The mid and high bands are split from the same high-passed signal, so between them
+// they get the phase shift of the split at the high crossover. The low band is split at the high
+// crossover as well and summed back together to get the same phase shift, so adding up all three
+// bands gives a flat magnitude response again.
+#[derive(Clone)]
+pub struct ThreeBandCrossover {
+    low_low_pass: LinkwitzRiley,
+    low_high_pass: LinkwitzRiley,
+    high_low_pass: LinkwitzRiley,
+    high_high_pass: LinkwitzRiley,
+    // The split at the high crossover the low band goes through to match the other bands' phase
+    low_phase_low_pass: LinkwitzRiley,
+    low_phase_high_pass: LinkwitzRiley,
+    low_freq: f32,
+    high_freq: f32,
+}
+
+impl ThreeBandCrossover {
+    pub fn new(low_freq: f32, high_freq: f32, sample_rate: f32) -> Self {
+        let low_pass = |freq| LinkwitzRiley::new(BandType::LowPass, freq, sample_rate).unwrap();
+        let high_pass = |freq| LinkwitzRiley::new(BandType::HighPass, freq, sample_rate).unwrap();
+
+        let low_freq = low_freq.min(high_freq);
+        ThreeBandCrossover {
+            low_low_pass: low_pass(low_freq),
+            low_high_pass: high_pass(low_freq),
+            high_low_pass: low_pass(high_freq),
+            high_high_pass: high_pass(high_freq),
+            low_phase_low_pass: low_pass(high_freq),
+            low_phase_high_pass: high_pass(high_freq),
+            low_freq,
+            high_freq,
+        }
+    }
+
+    // Move the crossovers. The low crossover is clamped so it never ends up above the high
+    // crossover. This gets called every block, so the filters are only updated when one of the
+    // frequencies actually changed.
+    pub fn set_frequencies(&mut self, low_freq: f32, high_freq: f32) {
+        let low_freq = low_freq.min(high_freq);
+        if low_freq == self.low_freq && high_freq == self.high_freq {
+            return;
+        }
+
+        self.low_freq = low_freq;
+        self.high_freq = high_freq;
+        self.low_low_pass.set_freq(low_freq);
+        self.low_high_pass.set_freq(low_freq);
+        for filter in self.high_crossover_filters() {
+            filter.set_freq(high_freq);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.low_low_pass.set_sample_rate(sample_rate);
+        self.low_high_pass.set_sample_rate(sample_rate);
+        for filter in self.high_crossover_filters() {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.low_low_pass.reset();
+        self.low_high_pass.reset();
+        for filter in self.high_crossover_filters() {
+            filter.reset();
+        }
+    }
+
+    // Split a sample into its low, mid and high bands
+    pub fn process(&mut self, input: f32) -> [f32; 3] {
+        let low = self.low_low_pass.process(input);
+        let low = self.low_phase_low_pass.process(low) + self.low_phase_high_pass.process(low);
+        let rest = self.low_high_pass.process(input);
+
+        [
+            low,
+            self.high_low_pass.process(rest),
+            self.high_high_pass.process(rest),
+        ]
+    }
+
+    fn high_crossover_filters(&mut self) -> [&mut LinkwitzRiley; 4] {
+        [
+            &mut self.high_low_pass,
+            &mut self.high_high_pass,
+            &mut self.low_phase_low_pass,
+            &mut self.low_phase_high_pass,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Runs a sine wave at `test_freq` through a single band and returns the gain in decibels
+    /// measured after the filter has settled.
+    fn measure_band_gain_db(band: &mut EQBand, test_freq: f32) -> f32 {
+        measure_band_gain_db_at(band, test_freq, SAMPLE_RATE)
+    }
+
+    /// The same as [`measure_band_gain_db()`], but for bands running at another sample rate.
+    fn measure_band_gain_db_at(band: &mut EQBand, test_freq: f32, sample_rate: f32) -> f32 {
+        measure_gain_db_at(|x| band.process(x), test_freq, sample_rate)
+    }
+
+    /// The same as [`measure_band_gain_db_at()`], but for any filter's `process` function.
+    fn measure_gain_db_at(
+        mut process: impl FnMut(f32) -> f32,
+        test_freq: f32,
+        sample_rate: f32,
+    ) -> f32 {
+        const SETTLE_SAMPLES: usize = 48000;
+        const MEASURE_SAMPLES: usize = 48000;
+
+        let mut input_energy = 0.0f64;
+        let mut output_energy = 0.0f64;
+        for i in 0..SETTLE_SAMPLES + MEASURE_SAMPLES {
+            let input = (i as f32 * test_freq * 2.0 * std::f32::consts::PI / sample_rate).sin();
+            let output = process(input);
+            if i >= SETTLE_SAMPLES {
+                input_energy += (input as f64).powi(2);
+                output_energy += (output as f64).powi(2);
+            }
+        }
+
+        10.0 * (output_energy / input_energy).log10() as f32
+    }
+
+    fn band(band_type: BandType, freq: f32, gain_db: f32, q: f32) -> EQBand {
+        let mut band = EQBand::new(band_type, freq, gain_db, q);
+        band.set_params(freq, gain_db, q, SAMPLE_RATE);
+        band
+    }
+
+    mod notch {
+        use super::*;
+
+        #[test]
+        fn attenuates_center() {
+            let mut notch = band(BandType::Notch, 1000.0, 0.0, 2.0);
+            assert!(measure_band_gain_db(&mut notch, 1000.0) < -40.0);
+        }
+
+        #[test]
+        fn passes_far_away() {
+            let mut notch = band(BandType::Notch, 1000.0, 0.0, 2.0);
+            assert!(measure_band_gain_db(&mut notch, 100.0).abs() < 0.5);
+            assert!(measure_band_gain_db(&mut notch, 10000.0).abs() < 0.5);
+        }
+    }
+
+    mod allpass {
+        use super::*;
+
+        #[test]
+        fn flat_magnitude() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Allpass, 1000.0, 0.0, 0.7).unwrap();
+
+            for test_freq in [50.0, 200.0, 800.0, 1000.0, 1250.0, 5000.0, 15000.0] {
+                let gain_db = measure_band_gain_db(&mut eq.bands[0], test_freq);
+                assert!(
+                    gain_db.abs() < 0.1,
+                    "{gain_db} dB at {test_freq} Hz exceeds the allowed deviation"
+                );
+            }
+        }
+    }
+
+    mod pass {
+        use super::*;
+
+        #[test]
+        fn high_pass_slope() {
+            let mut high_pass = band(
+                BandType::HighPass,
+                1000.0,
+                0.0,
+                std::f32::consts::FRAC_1_SQRT_2,
+            );
+            let one_octave_down = measure_band_gain_db(&mut high_pass, 125.0);
+            let two_octaves_down = measure_band_gain_db(&mut high_pass, 62.5);
+
+            approx::assert_relative_eq!(one_octave_down - two_octaves_down, 12.0, epsilon = 0.5);
+            assert!(measure_band_gain_db(&mut high_pass, 10000.0).abs() < 0.1);
+        }
+
+        #[test]
+        fn low_pass_slope() {
+            let mut low_pass = band(
+                BandType::LowPass,
+                500.0,
+                0.0,
+                std::f32::consts::FRAC_1_SQRT_2,
+            );
+            let one_octave_up = measure_band_gain_db(&mut low_pass, 2000.0);
+            let two_octaves_up = measure_band_gain_db(&mut low_pass, 4000.0);
+
+            approx::assert_relative_eq!(one_octave_up - two_octaves_up, 12.0, epsilon = 0.5);
+            assert!(measure_band_gain_db(&mut low_pass, 50.0).abs() < 0.1);
+        }
+    }
+
+    mod tilt {
+        use super::*;
+
+        #[test]
+        fn tilts_around_pivot() {
+            let mut tilt = band(BandType::Tilt, 1000.0, 6.0, 0.7);
+            approx::assert_relative_eq!(measure_band_gain_db(&mut tilt, 30.0), -3.0, epsilon = 0.2);
+            approx::assert_relative_eq!(
+                measure_band_gain_db(&mut tilt, 1000.0),
+                0.0,
+                epsilon = 0.2
+            );
+            approx::assert_relative_eq!(
+                measure_band_gain_db(&mut tilt, 18000.0),
+                3.0,
+                epsilon = 0.2
+            );
+        }
+
+        #[test]
+        fn negative_tilt() {
+            let mut tilt = band(BandType::Tilt, 1000.0, -6.0, 0.7);
+            approx::assert_relative_eq!(measure_band_gain_db(&mut tilt, 30.0), 3.0, epsilon = 0.2);
+            approx::assert_relative_eq!(
+                measure_band_gain_db(&mut tilt, 18000.0),
+                -3.0,
+                epsilon = 0.2
+            );
+        }
+    }
+
+    mod bypass {
+        use super::*;
+
+        #[test]
+        fn bypassed_band_passes_input() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Peak, 1000.0, 12.0, 1.0).unwrap();
+            eq.set_band_enabled(0, false).unwrap();
+
+            for i in 0..1000 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(eq.process(input), input);
+            }
+        }
+
+        #[test]
+        fn reenabling_keeps_state() {
+            let mut bypassed = band(BandType::Peak, 1000.0, 12.0, 1.0);
+            let mut reference = band(BandType::Peak, 1000.0, 12.0, 1.0);
+
+            bypassed.set_enabled(false);
+            for i in 0..1000 {
+                let input = (i as f32 * 0.1).sin();
+                bypassed.process(input);
+                reference.process(input);
+            }
+
+            bypassed.set_enabled(true);
+            for i in 1000..2000 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(bypassed.process(input), reference.process(input));
+            }
+        }
+    }
+
+    mod smoothing {
+        use super::*;
+
+        /// Jumps a peak band from 0 dB to +12 dB while a sine runs through it, and returns how
+        /// far the output deviates from that of an unchanged band during the first few samples
+        /// after the change.
+        fn deviation_after_gain_change(smoothing_samples: usize) -> [f32; 4] {
+            let mut changed = band(BandType::Peak, 1000.0, 0.0, 1.0);
+            let mut unchanged = band(BandType::Peak, 1000.0, 0.0, 1.0);
+            changed.set_smoothing_samples(smoothing_samples);
+
+            let input = |i: usize| (i as f32 * 0.13).sin() * 0.5;
+            for i in 0..1000 {
+                changed.process(input(i));
+                unchanged.process(input(i));
+            }
+
+            changed.set_params(1000.0, 12.0, 1.0, SAMPLE_RATE);
+            let mut deviation = [0.0; 4];
+            for (i, deviation) in (1000..).zip(&mut deviation) {
+                *deviation = (changed.process(input(i)) - unchanged.process(input(i))).abs();
+            }
+
+            deviation
+        }
+
+        #[test]
+        fn no_discontinuities() {
+            // Without smoothing the output immediately jumps to the new filter's output, with
+            // smoothing it should gradually move there instead
+            let unsmoothed = deviation_after_gain_change(0);
+            let smoothed = deviation_after_gain_change(64);
+            for (unsmoothed, smoothed) in unsmoothed.into_iter().zip(smoothed) {
+                assert!(smoothed < unsmoothed / 20.0);
+            }
+        }
+
+        #[test]
+        fn reaches_target() {
+            let mut smoothed = band(BandType::Peak, 1000.0, 0.0, 1.0);
+            smoothed.set_smoothing_samples(32);
+            smoothed.set_params(1000.0, 6.0, 1.0, SAMPLE_RATE);
+            let reference = band(BandType::Peak, 1000.0, 6.0, 1.0);
+
+            for _ in 0..32 {
+                smoothed.process(0.0);
+            }
+            assert_eq!(smoothed.coeffs.b0, reference.coeffs.b0);
+            assert_eq!(smoothed.coeffs.a2, reference.coeffs.a2);
+        }
+    }
+
+    mod direct_form {
+        use super::*;
+
+        /// A direct form I biquad with the same double precision math as the band, as a reference
+        /// for the transposed direct form II implementation.
+        struct DirectFormI {
+            coeffs: BiquadCoeffs,
+            state: [f64; 4],
+        }
+
+        impl DirectFormI {
+            fn new(coeffs: BiquadCoeffs) -> Self {
+                Self {
+                    coeffs,
+                    state: [0.0; 4],
+                }
+            }
+
+            fn process(&mut self, input: f32) -> f32 {
+                let [x1, x2, y1, y2] = self.state;
+                let input = input as f64;
+                let output = self.coeffs.b0 * input + self.coeffs.b1 * x1 + self.coeffs.b2 * x2
+                    - self.coeffs.a1 * y1
+                    - self.coeffs.a2 * y2;
+                self.state = [input, x1, output, y1];
+
+                output as f32
+            }
+        }
+
+        fn input(i: usize) -> f32 {
+            (i as f32 * 0.05).sin() * 0.5 + (i as f32 * 0.31).sin() * 0.25
+        }
+
+        #[test]
+        fn matches_direct_form_i() {
+            for band_type in [
+                BandType::Peak,
+                BandType::LowShelf,
+                BandType::HighShelf,
+                BandType::LowPass,
+                BandType::HighPass,
+                BandType::Notch,
+            ] {
+                let mut band = band(band_type, 300.0, 9.0, 2.0);
+                let mut reference = DirectFormI::new(band.coeffs);
+                for i in 0..48000 {
+                    let output = band.process(input(i));
+                    let expected = reference.process(input(i));
+                    approx::assert_relative_eq!(output, expected, epsilon = 1e-5);
+                }
+            }
+        }
+
+        /// With the transposed form the new feedback coefficients only reach the output through
+        /// the state, so on the sample where the coefficients jump only the `b0` term changes.
+        /// After that the band settles on the new filter's output.
+        #[test]
+        fn coefficient_step() {
+            const STEP: usize = 1000;
+
+            let mut stepped = band(BandType::Peak, 200.0, 12.0, 4.0);
+            let mut unchanged = band(BandType::Peak, 200.0, 12.0, 4.0);
+            let mut target = band(BandType::Peak, 4000.0, -12.0, 4.0);
+            for i in 0..STEP {
+                stepped.process(input(i));
+                unchanged.process(input(i));
+                target.process(input(i));
+            }
+
+            let old_b0 = stepped.coeffs.b0;
+            stepped.set_params(4000.0, -12.0, 4.0, SAMPLE_RATE);
+            let jump = stepped.process(input(STEP)) - unchanged.process(input(STEP));
+            target.process(input(STEP));
+            approx::assert_relative_eq!(
+                jump as f64,
+                (stepped.coeffs.b0 - old_b0) * input(STEP) as f64,
+                epsilon = 1e-6
+            );
+
+            for i in STEP + 1..STEP * 3 {
+                let deviation = (stepped.process(input(i)) - target.process(input(i))).abs();
+                if i > STEP * 2 {
+                    assert!(deviation < 1e-4, "{}: {}", i, deviation);
+                }
+            }
+        }
+    }
+
+    mod cascade {
+        use super::*;
+
+        fn high_pass(freq: f32, num_stages: usize) -> CascadedFilter {
+            let mut filter = CascadedFilter::new(BandType::HighPass, freq, SAMPLE_RATE).unwrap();
+            filter.set_num_stages(num_stages).unwrap();
+            filter
+        }
+
+        #[test]
+        fn butterworth_qs() {
+            approx::assert_relative_eq!(butterworth_q(0, 1), std::f32::consts::FRAC_1_SQRT_2);
+            approx::assert_relative_eq!(butterworth_q(0, 2), 0.5412, epsilon = 1e-4);
+            approx::assert_relative_eq!(butterworth_q(1, 2), 1.3066, epsilon = 1e-4);
+        }
+
+        #[test]
+        fn minus_3_db_at_cutoff() {
+            for num_stages in 1..=MAX_CASCADE_STAGES {
+                let mut filter = high_pass(200.0, num_stages);
+                let gain_db = measure_gain_db_at(|x| filter.process(x), 200.0, SAMPLE_RATE);
+                assert!((gain_db + 3.0).abs() < 0.1, "{num_stages}: {gain_db} dB");
+            }
+        }
+
+        /// Far below the cutoff every stage adds 12 dB of attenuation per octave, or more precisely
+        /// two times 6.02 dB for the two orders it adds.
+        #[test]
+        fn slope_per_stage() {
+            for num_stages in 1..=MAX_CASCADE_STAGES {
+                let filter = high_pass(1000.0, num_stages);
+                let slope = filter.magnitude_db(250.0) - filter.magnitude_db(125.0);
+                let expected = 2.0 * num_stages as f32 * 20.0 * 2.0f32.log10();
+                approx::assert_relative_eq!(slope, expected, epsilon = 0.05);
+            }
+        }
+
+        #[test]
+        fn two_stages_give_24_db_per_octave() {
+            let mut filter = high_pass(1000.0, 2);
+            let one_octave = measure_gain_db_at(|x| filter.process(x), 500.0, SAMPLE_RATE);
+            let two_octaves = measure_gain_db_at(|x| filter.process(x), 250.0, SAMPLE_RATE);
+            approx::assert_relative_eq!(one_octave - two_octaves, 24.0, epsilon = 0.5);
+
+            let mut filter = CascadedFilter::new(BandType::LowPass, 1000.0, SAMPLE_RATE).unwrap();
+            filter.set_num_stages(2).unwrap();
+            let one_octave = measure_gain_db_at(|x| filter.process(x), 2000.0, SAMPLE_RATE);
+            let two_octaves = measure_gain_db_at(|x| filter.process(x), 4000.0, SAMPLE_RATE);
+            approx::assert_relative_eq!(one_octave - two_octaves, 24.0, epsilon = 1.0);
+        }
+
+        #[test]
+        fn attenuates_bass() {
+            let mut filter = high_pass(200.0, 1);
+
+            // Over two octaves below the cutoff, so at least 24 dB down
+            let gain_db = measure_gain_db_at(|x| filter.process(x), 40.0, SAMPLE_RATE);
+            assert!(gain_db < -24.0, "gain = {gain_db} dB");
+        }
+
+        #[test]
+        fn passes_mids() {
+            for num_stages in 1..=MAX_CASCADE_STAGES {
+                let mut filter = high_pass(200.0, num_stages);
+                let gain_db = measure_gain_db_at(|x| filter.process(x), 2000.0, SAMPLE_RATE);
+                assert!(gain_db.abs() < 0.1, "{num_stages}: {gain_db} dB");
+            }
+        }
+
+        #[test]
+        fn invalid_configurations() {
+            assert!(CascadedFilter::new(BandType::Peak, 200.0, SAMPLE_RATE).is_err());
+            let mut filter = high_pass(200.0, 1);
+            assert!(filter.set_num_stages(0).is_err());
+            assert!(filter.set_num_stages(MAX_CASCADE_STAGES + 1).is_err());
+            assert_eq!(filter.num_stages(), 1);
+        }
+    }
+
+    mod prewarping {
+        use super::*;
+
+        #[test]
+        fn bandwidth_matches_between_sample_rates() {
+            let mut at_48k = EQBand::new(BandType::Peak, 10000.0, 12.0, 1.0);
+            at_48k.set_params(10000.0, 12.0, 1.0, 48000.0);
+            let mut at_192k = EQBand::new(BandType::Peak, 10000.0, 12.0, 1.0);
+            at_192k.set_params(10000.0, 12.0, 1.0, 192000.0);
+
+            // Without pre-warping the band is about 1-2 dB narrower at 48 kHz
+            for test_freq in [7000.0, 13000.0] {
+                let gain_48k = measure_band_gain_db_at(&mut at_48k, test_freq, 48000.0);
+                let gain_192k = measure_band_gain_db_at(&mut at_192k, test_freq, 192000.0);
+                approx::assert_relative_eq!(gain_48k, gain_192k, epsilon = 0.6);
+            }
+        }
+    }
+
+    mod magnitude_response {
+        use super::*;
+
+        #[test]
+        fn matches_measured_response() {
+            let mut peak = band(BandType::Peak, 1000.0, 9.0, 2.0);
+            for test_freq in [100.0, 700.0, 1000.0, 1500.0, 8000.0] {
+                let analytic = peak.magnitude_db(test_freq);
+                let measured = measure_band_gain_db(&mut peak, test_freq);
+                approx::assert_relative_eq!(analytic, measured, epsilon = 0.5);
+            }
+            approx::assert_relative_eq!(peak.magnitude_db(1000.0), 9.0, epsilon = 0.01);
+        }
+
+        #[test]
+        fn sums_bands() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Peak, 1000.0, 6.0, 1.0).unwrap();
+            eq.add_band(BandType::Peak, 1000.0, 3.0, 1.0).unwrap();
+            approx::assert_relative_eq!(eq.magnitude_db(1000.0), 9.0, epsilon = 0.01);
+
+            eq.set_band_enabled(1, false).unwrap();
+            approx::assert_relative_eq!(eq.magnitude_db(1000.0), 6.0, epsilon = 0.01);
+        }
+    }
+
+    mod phase_response {
+        use super::*;
+        use std::f32::consts::PI;
+
+        #[test]
+        fn allpass_rotates_phase() {
+            let allpass = band(BandType::Allpass, 1000.0, 0.0, 0.7);
+
+            // The phase is wrapped, so it needs to be unwrapped before checking whether it keeps
+            // decreasing
+            let mut previous_phase = allpass.phase_radians(10.0);
+            let mut unwrapped_phase = previous_phase;
+            for test_freq in (1..200).map(|i| 10.0 * 1.035f32.powi(i)) {
+                let phase = allpass.phase_radians(test_freq);
+                let mut delta = phase - previous_phase;
+                if delta > PI {
+                    delta -= 2.0 * PI;
+                }
+                assert!(delta <= 0.0, "The phase increased at {test_freq} Hz");
+
+                unwrapped_phase += delta;
+                previous_phase = phase;
+            }
+
+            approx::assert_relative_eq!(allpass.phase_radians(1000.0).abs(), PI, epsilon = 1e-3);
+            assert!(unwrapped_phase < -PI);
+        }
+
+        #[test]
+        fn sums_bands() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Allpass, 1000.0, 0.0, 0.7).unwrap();
+            eq.add_band(BandType::Allpass, 5000.0, 0.0, 0.7).unwrap();
+
+            let expected = eq.bands[0].phase_radians(300.0) + eq.bands[1].phase_radians(300.0);
+            approx::assert_relative_eq!(eq.phase_radians(300.0), expected, epsilon = 1e-4);
+        }
+    }
+
+    mod reset {
+        use super::*;
+
+        #[test]
+        fn clears_state() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::LowShelf, 100.0, 12.0, 0.7).unwrap();
+            eq.add_band(BandType::Peak, 1000.0, -6.0, 1.0).unwrap();
+
+            // A DC step leaves plenty of energy in the delay lines
+            for _ in 0..100 {
+                eq.process(1.0);
+            }
+            eq.reset();
+
+            for _ in 0..100 {
+                assert_eq!(eq.process(0.0), 0.0);
+            }
+        }
+    }
+
+    mod precision {
+        use super::*;
+
+        /// Runs a low-frequency test signal through `band` and a straightforward single precision
+        /// biquad using the same coefficients, and returns the RMS error of both compared to a
+        /// double precision reference.
+        fn rms_errors(band: &mut EQBand) -> (f64, f64) {
+            let coeffs = band.coeffs;
+            let mut single_state = [0.0f32; 4];
+            let mut double_state = [0.0f64; 4];
+
+            let mut band_error = 0.0f64;
+            let mut single_error = 0.0f64;
+            const NUM_SAMPLES: usize = 192000;
+            for i in 0..NUM_SAMPLES {
+                let input = (i as f32 * 0.0011).sin() * 0.5 + (i as f32 * 0.00037).sin() * 0.25;
+
+                let [x1, x2, y1, y2] = single_state;
+                let single =
+                    coeffs.b0 as f32 * input + coeffs.b1 as f32 * x1 + coeffs.b2 as f32 * x2
+                        - coeffs.a1 as f32 * y1
+                        - coeffs.a2 as f32 * y2;
+                single_state = [input, x1, single, y1];
+
+                let [x1, x2, y1, y2] = double_state;
+                let double = coeffs.b0 * input as f64 + coeffs.b1 * x1 + coeffs.b2 * x2
+                    - coeffs.a1 * y1
+                    - coeffs.a2 * y2;
+                double_state = [input as f64, x1, double, y1];
+
+                band_error += (band.process(input) as f64 - double).powi(2);
+                single_error += (single as f64 - double).powi(2);
+            }
+
+            (
+                (band_error / NUM_SAMPLES as f64).sqrt(),
+                (single_error / NUM_SAMPLES as f64).sqrt(),
+            )
+        }
+
+        #[test]
+        fn lower_noise_floor() {
+            let mut low_shelf = EQBand::new(BandType::LowShelf, 40.0, 12.0, 4.0);
+            low_shelf.set_params(40.0, 12.0, 4.0, 192000.0);
+
+            let (band_error, single_precision_error) = rms_errors(&mut low_shelf);
+            assert!(band_error * 10.0 < single_precision_error);
+        }
+    }
+
+    mod denormals {
+        use super::*;
+
+        #[test]
+        fn state_decays_to_zero() {
+            let mut peak = band(BandType::Peak, 100.0, 12.0, 8.0);
+            peak.process(1.0);
+            for _ in 0..SAMPLE_RATE as usize * 10 {
+                peak.process(0.0);
+            }
+
+            assert_eq!(peak.state.s1, 0.0);
+            assert_eq!(peak.state.s2, 0.0);
+            assert_eq!(peak.process(0.0), 0.0);
+        }
+    }
+
+    mod process_block {
+        use super::*;
+
+        #[test]
+        fn matches_per_sample_processing() {
+            let mut per_sample = ParametricEQ::new(SAMPLE_RATE);
+            per_sample
+                .add_band(BandType::LowShelf, 100.0, 6.0, 0.7)
+                .unwrap();
+            per_sample
+                .add_band(BandType::Peak, 1000.0, -3.0, 2.0)
+                .unwrap();
+            per_sample
+                .add_band(BandType::HighShelf, 8000.0, 4.0, 0.7)
+                .unwrap();
+            per_sample.set_smoothing_samples(16);
+            per_sample.set_band_params(1, 1200.0, 6.0, 1.0).unwrap();
+
+            let mut block = ParametricEQ::new(SAMPLE_RATE);
+            block.add_band(BandType::LowShelf, 100.0, 6.0, 0.7).unwrap();
+            block.add_band(BandType::Peak, 1000.0, -3.0, 2.0).unwrap();
+            block
+                .add_band(BandType::HighShelf, 8000.0, 4.0, 0.7)
+                .unwrap();
+            block.set_smoothing_samples(16);
+            block.set_band_params(1, 1200.0, 6.0, 1.0).unwrap();
+
+            let mut samples: Vec<f32> = (0..256).map(|i| (i as f32 * 0.37).sin()).collect();
+            let expected: Vec<f32> = samples.iter().map(|s| per_sample.process(*s)).collect();
+            block.process_block(&mut samples);
+
+            assert_eq!(samples, expected);
+        }
+    }
+
+    mod insert_band {
+        use super::*;
+
+        #[test]
+        fn insert_at_start() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Peak, 1000.0, 6.0, 1.0).unwrap();
+            eq.insert_band(0, BandType::Notch, 1000.0, 0.0, 1.0)
+                .unwrap();
+
+            // The notch now comes first, so bypassing the second band leaves only the notch
+            eq.set_band_enabled(1, false).unwrap();
+            assert!(eq.magnitude_db(1000.0) < -40.0);
+
+            eq.set_band_enabled(0, false).unwrap();
+            eq.set_band_enabled(1, true).unwrap();
+            approx::assert_relative_eq!(eq.magnitude_db(1000.0), 6.0, epsilon = 0.01);
+        }
+
+        #[test]
+        fn validates_index_and_limit() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            assert!(eq.insert_band(1, BandType::Peak, 1000.0, 0.0, 1.0).is_err());
+
+            for _ in 0..MAX_BANDS {
+                eq.insert_band(0, BandType::Peak, 1000.0, 0.0, 1.0).unwrap();
+            }
+            assert!(eq.insert_band(0, BandType::Peak, 1000.0, 0.0, 1.0).is_err());
+        }
+    }
+
+    mod clone {
+        use super::*;
+
+        #[test]
+        fn clone_produces_identical_output() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::LowShelf, 100.0, 6.0, 0.7).unwrap();
+            eq.add_band(BandType::Peak, 1000.0, -3.0, 2.0).unwrap();
+            for i in 0..100 {
+                eq.process((i as f32 * 0.1).sin());
+            }
+
+            // This should also copy over the filter state
+            let mut cloned = eq.clone();
+            for i in 100..1000 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(eq.process(input), cloned.process(input));
+            }
+        }
+    }
+
+    mod reference_coefficients {
+        use super::*;
+
+        /// The normalized `[b0, b1, b2, a1, a2]` coefficients from the RBJ Audio EQ Cookbook,
+        /// computed in double precision. Like `set_params()` this uses the bandwidth form of
+        /// alpha for the peak, notch and allpass bands.
+        fn rbj_reference(band_type: BandType, freq: f32, gain_db: f32, q: f32) -> [f64; 5] {
+            let (freq, gain_db, q) = (freq as f64, gain_db as f64, q as f64);
+            let a = 10.0f64.powf(gain_db / 40.0);
+            let omega = std::f64::consts::TAU * freq / SAMPLE_RATE as f64;
+            let (sin_omega, cos_omega) = omega.sin_cos();
+            let alpha = match band_type {
+                // The shelves' Q is their slope
+                BandType::LowShelf | BandType::HighShelf => {
+                    sin_omega / 2.0 * ((a + 1.0 / a) * (1.0 / q - 1.0) + 2.0).sqrt()
+                }
+                BandType::HighPass | BandType::LowPass => sin_omega / (2.0 * q),
+                BandType::Peak | BandType::Notch | BandType::Allpass | BandType::Tilt => {
+                    sin_omega * ((1.0 / (2.0 * q)).asinh() * omega / sin_omega).sinh()
+                }
+            };
+            let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+            let [b0, b1, b2, a0, a1, a2] = match band_type {
+                BandType::LowShelf => [
+                    a * ((a + 1.0) - (a - 1.0) * cos_omega + sqrt_a_alpha),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos_omega),
+                    a * ((a + 1.0) - (a - 1.0) * cos_omega - sqrt_a_alpha),
+                    (a + 1.0) + (a - 1.0) * cos_omega + sqrt_a_alpha,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos_omega),
+                    (a + 1.0) + (a - 1.0) * cos_omega - sqrt_a_alpha,
+                ],
+                BandType::HighShelf => [
+                    a * ((a + 1.0) + (a - 1.0) * cos_omega + sqrt_a_alpha),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_omega),
+                    a * ((a + 1.0) + (a - 1.0) * cos_omega - sqrt_a_alpha),
+                    (a + 1.0) - (a - 1.0) * cos_omega + sqrt_a_alpha,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos_omega),
+                    (a + 1.0) - (a - 1.0) * cos_omega - sqrt_a_alpha,
+                ],
+                BandType::Peak => [
+                    1.0 + alpha * a,
+                    -2.0 * cos_omega,
+                    1.0 - alpha * a,
+                    1.0 + alpha / a,
+                    -2.0 * cos_omega,
+                    1.0 - alpha / a,
+                ],
+                BandType::Notch => [
+                    1.0,
+                    -2.0 * cos_omega,
+                    1.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                ],
+                BandType::Allpass => [
+                    1.0 - alpha,
+                    -2.0 * cos_omega,
+                    1.0 + alpha,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                ],
+                BandType::LowPass => [
+                    (1.0 - cos_omega) / 2.0,
+                    1.0 - cos_omega,
+                    (1.0 - cos_omega) / 2.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                ],
+                BandType::HighPass => [
+                    (1.0 + cos_omega) / 2.0,
+                    -(1.0 + cos_omega),
+                    (1.0 + cos_omega) / 2.0,
+                    1.0 + alpha,
+                    -2.0 * cos_omega,
+                    1.0 - alpha,
+                ],
+                // The high shelf with the Q's alpha, cut by half its gain everywhere
+                BandType::Tilt => [
+                    a * ((a + 1.0) + (a - 1.0) * cos_omega + sqrt_a_alpha) / a,
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_omega) / a,
+                    a * ((a + 1.0) + (a - 1.0) * cos_omega - sqrt_a_alpha) / a,
+                    (a + 1.0) - (a - 1.0) * cos_omega + sqrt_a_alpha,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos_omega),
+                    (a + 1.0) - (a - 1.0) * cos_omega - sqrt_a_alpha,
+                ],
+            };
+
+            [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
+        }
+
+        #[test]
+        fn matches_rbj_cookbook() {
+            for band_type in [
+                BandType::Peak,
+                BandType::Notch,
+                BandType::Allpass,
+                BandType::LowPass,
+                BandType::HighPass,
+                BandType::Tilt,
+            ] {
+                for freq in [50.0, 440.0, 1000.0, 5000.0, 15000.0] {
+                    for (gain_db, q) in [(6.0, 0.7), (-9.0, 2.0), (12.0, 5.0)] {
+                        let band = band(band_type, freq, gain_db, q);
+                        let coeffs = band.target_coeffs;
+                        let actual = [coeffs.b0, coeffs.b1, coeffs.b2, coeffs.a1, coeffs.a2];
+                        let expected = rbj_reference(band_type, freq, gain_db, q);
+                        for (actual, expected) in actual.into_iter().zip(expected) {
+                            approx::assert_relative_eq!(
+                                actual,
+                                expected,
+                                epsilon = 1e-6,
+                                max_relative = 1e-6
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn shelves_match_rbj_cookbook() {
+            for band_type in [BandType::LowShelf, BandType::HighShelf] {
+                for freq in [50.0, 440.0, 1000.0, 5000.0, 15000.0] {
+                    for (gain_db, slope) in [(6.0, 1.0), (-9.0, 0.5), (18.0, 0.2)] {
+                        let band = band(band_type, freq, gain_db, slope);
+                        let coeffs = band.target_coeffs;
+                        let actual = [coeffs.b0, coeffs.b1, coeffs.b2, coeffs.a1, coeffs.a2];
+                        let expected = rbj_reference(band_type, freq, gain_db, slope);
+                        for (actual, expected) in actual.into_iter().zip(expected) {
+                            approx::assert_relative_eq!(
+                                actual,
+                                expected,
+                                epsilon = 1e-6,
+                                max_relative = 1e-6
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    mod shelf_slope {
+        use super::*;
+
+        /// How many decibels the shelf's response changes over the octave centered on its
+        /// frequency.
+        fn transition_db_per_octave(band: &EQBand, freq: f32) -> f32 {
+            (band.magnitude_db(freq * 2.0f32.sqrt()) - band.magnitude_db(freq / 2.0f32.sqrt()))
+                .abs()
+        }
+
+        #[test]
+        fn higher_slope_is_steeper() {
+            for band_type in [BandType::LowShelf, BandType::HighShelf] {
+                let mut prev_db_per_octave = 0.0;
+                for slope in [0.1, 0.25, 0.5, 1.0] {
+                    let band = band(band_type, 1000.0, 12.0, slope);
+                    let db_per_octave = transition_db_per_octave(&band, 1000.0);
+                    assert!(
+                        db_per_octave > prev_db_per_octave,
+                        "{band_type:?} at {slope}: {db_per_octave} <= {prev_db_per_octave}"
+                    );
+                    prev_db_per_octave = db_per_octave;
+
+                    // The slope only changes the transition, the shelf still reaches its gain
+                    // and sits halfway there at its frequency
+                    let (shelf_freq, flat_freq) = match band_type {
+                        BandType::LowShelf => (10.0, 20000.0),
+                        _ => (20000.0, 10.0),
+                    };
+                    approx::assert_relative_eq!(band.magnitude_db(shelf_freq), 12.0, epsilon = 0.3);
+                    approx::assert_relative_eq!(band.magnitude_db(flat_freq), 0.0, epsilon = 0.3);
+                    approx::assert_relative_eq!(band.magnitude_db(1000.0), 6.0, epsilon = 1e-3);
+                }
+            }
+        }
+
+        /// The width in octaves of the part of the shelf's transition where its response goes
+        /// from 10% to 90% of its gain.
+        fn transition_width_octaves(band: &EQBand, gain_db: f32) -> f32 {
+            let crossing = |fraction: f32| {
+                (0..2000)
+                    .map(|i| 10.0 * 2.0f32.powf(i as f32 / 200.0))
+                    .find(|&freq| {
+                        let reached = band.magnitude_db(freq) / gain_db;
+                        match band.band_type {
+                            BandType::LowShelf => reached < fraction,
+                            _ => reached > fraction,
+                        }
+                    })
+                    .unwrap()
+            };
+
+            (crossing(0.1) / crossing(0.9)).log2().abs()
+        }
+
+        /// A second order shelf gets a little wider at extreme gains, but the gain no longer
+        /// changes the shape beyond that.
+        #[test]
+        fn gain_keeps_the_transition_width() {
+            for band_type in [BandType::LowShelf, BandType::HighShelf] {
+                for slope in [0.5, 1.0] {
+                    let reference_width =
+                        transition_width_octaves(&band(band_type, 1000.0, 6.0, slope), 6.0);
+                    for gain_db in [-12.0, -6.0, -3.0, 3.0, 12.0] {
+                        let band = band(band_type, 1000.0, gain_db, slope);
+                        let width = transition_width_octaves(&band, gain_db);
+                        approx::assert_relative_eq!(width, reference_width, max_relative = 0.1);
+                        approx::assert_relative_eq!(
+                            band.magnitude_db(1000.0),
+                            gain_db / 2.0,
+                            epsilon = 1e-3
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn cuts_mirror_boosts() {
+            for band_type in [BandType::LowShelf, BandType::HighShelf] {
+                for gain_db in [3.0, 12.0, 24.0] {
+                    let boost = band(band_type, 500.0, gain_db, 0.7);
+                    let cut = band(band_type, 500.0, -gain_db, 0.7);
+                    for i in 0..100 {
+                        let freq = 10.0 * 2.0f32.powf(i as f32 / 10.0);
+                        approx::assert_relative_eq!(
+                            cut.magnitude_db(freq),
+                            -boost.magnitude_db(freq),
+                            epsilon = 1e-3
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn steepest_slope_doesnt_overshoot() {
+            let band = band(BandType::LowShelf, 1000.0, 12.0, 1.0);
+            for i in 0..200 {
+                let freq = 10.0 * 2.0f32.powf(i as f32 / 20.0);
+                let gain_db = band.magnitude_db(freq);
+                assert!(
+                    (-1e-3..=12.0 + 1e-3).contains(&gain_db),
+                    "{freq} Hz: {gain_db}"
+                );
+            }
+        }
+    }
+
+    mod invalid_params {
+        use super::*;
+
+        #[test]
+        fn stable_above_nyquist() {
+            for band_type in [
+                BandType::LowShelf,
+                BandType::HighShelf,
+                BandType::Peak,
+                BandType::Notch,
+                BandType::Allpass,
+                BandType::HighPass,
+                BandType::LowPass,
+                BandType::Tilt,
+            ] {
+                for step in 0..=40 {
+                    // Sweeps from a quarter of Nyquist up to twice Nyquist
+                    let freq = SAMPLE_RATE / 8.0 + step as f32 * SAMPLE_RATE * 0.875 / 40.0;
+                    let mut band = band(band_type, freq, 12.0, 5.0);
+                    for i in 0..4800 {
+                        let input = if i % 2 == 0 { 1.0 } else { -1.0 };
+                        let output = band.process(input);
+                        assert!(
+                            output.is_finite() && output.abs() < 20.0,
+                            "{:?} at {} Hz: {}",
+                            band_type,
+                            freq,
+                            output
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn zero_q_is_stable() {
+            for band_type in [
+                BandType::LowShelf,
+                BandType::HighShelf,
+                BandType::Peak,
+                BandType::Notch,
+                BandType::Allpass,
+                BandType::HighPass,
+                BandType::LowPass,
+                BandType::Tilt,
+            ] {
+                for q in [0.0, -1.0] {
+                    let mut band = band(band_type, 1000.0, 6.0, q);
+                    for i in 0..4800 {
+                        let output = band.process((i as f32 * 0.1).sin());
+                        assert!(
+                            output.is_finite() && output.abs() < 20.0,
+                            "{:?} with Q {}: {}",
+                            band_type,
+                            q,
+                            output
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn extreme_gain_is_limited() {
+            let band = band(BandType::Peak, 1000.0, 200.0, 1.0);
+            approx::assert_relative_eq!(band.magnitude_db(1000.0), MAX_GAIN_DB, epsilon = 0.01);
+        }
+
+        #[test]
+        fn nan_freq_passes_through() {
+            let mut band = band(BandType::Peak, f32::NAN, 6.0, 1.0);
+            for i in 0..100 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(band.process(input), input);
+            }
+        }
+    }
+
+    mod copy_params {
+        use super::*;
+
+        #[test]
+        fn matches_computed_params() {
+            let new_eq = || {
+                let mut eq = ParametricEQ::new(SAMPLE_RATE);
+                eq.add_band(BandType::LowShelf, 100.0, 0.0, 0.7).unwrap();
+                eq.add_band(BandType::Peak, 1000.0, 0.0, 1.0).unwrap();
+                eq
+            };
+            let mut computed = new_eq();
+            computed.set_smoothing_samples(16);
+            computed.set_band_params(0, 150.0, 6.0, 0.7).unwrap();
+            computed.set_band_params(1, 2000.0, -4.0, 2.0).unwrap();
+            let mut copied = new_eq();
+            copied.set_smoothing_samples(16);
+            copied.copy_params_from(&computed);
+
+            assert_eq!(copied.magnitude_db(1000.0), computed.magnitude_db(1000.0));
+            for i in 0..1000 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(copied.process(input), computed.process(input));
+            }
+        }
+    }
+
+    mod stability {
+        use super::*;
+
+        const NUM_CASES: usize = 20000;
+
+        /// A small xorshift generator, so the parameter combinations are random but the same on
+        /// every run.
+        struct Rng(u32);
+
+        impl Rng {
+            /// A random value in `[min, max)`.
+            fn next(&mut self, min: f32, max: f32) -> f32 {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 17;
+                self.0 ^= self.0 << 5;
+                min + (self.0 >> 8) as f32 / (1 << 24) as f32 * (max - min)
+            }
+        }
+
+        /// The magnitudes of the roots of `z^2 + a1 * z + a2`, the band's poles.
+        fn pole_magnitudes(coeffs: &BiquadCoeffs) -> [f64; 2] {
+            let (a1, a2) = (coeffs.a1, coeffs.a2);
+            let discriminant = a1 * a1 - 4.0 * a2;
+            if discriminant < 0.0 {
+                // A complex conjugate pair, both with a magnitude of `sqrt(a2)`
+                [a2.sqrt(); 2]
+            } else {
+                let root = discriminant.sqrt();
+                [((-a1 + root) / 2.0).abs(), ((-a1 - root) / 2.0).abs()]
+            }
+        }
+
+        #[test]
+        fn poles_inside_unit_circle() {
+            const BAND_TYPES: [BandType; 8] = [
+                BandType::LowShelf,
+                BandType::HighShelf,
+                BandType::Peak,
+                BandType::Notch,
+                BandType::Allpass,
+                BandType::HighPass,
+                BandType::LowPass,
+                BandType::Tilt,
+            ];
+            const SAMPLE_RATES: [f32; 4] = [44100.0, 48000.0, 96000.0, 192000.0];
+
+            let mut rng = Rng(0x1234_5678);
+            for _ in 0..NUM_CASES {
+                let band_type = BAND_TYPES[rng.next(0.0, BAND_TYPES.len() as f32) as usize];
+                let sample_rate = SAMPLE_RATES[rng.next(0.0, SAMPLE_RATES.len() as f32) as usize];
+                // The frequency is spread out logarithmically, since most of the bands sit low
+                let freq = 10.0 * (sample_rate / 2.0 / 10.0).powf(rng.next(0.0, 1.0));
+                let gain_db = rng.next(-24.0, 24.0);
+                let q = 0.1 * 100.0f32.powf(rng.next(0.0, 1.0));
+
+                let mut band = EQBand::new(band_type, freq, gain_db, q);
+                band.set_params(freq, gain_db, q, sample_rate);
+                for magnitude in pole_magnitudes(&band.target_coeffs) {
+                    assert!(
+                        magnitude < 1.0,
+                        "{:?} at {} Hz, {} dB, Q {}, {} Hz: |p| = {}",
+                        band_type,
+                        freq,
+                        gain_db,
+                        q,
+                        sample_rate,
+                        magnitude
+                    );
+                }
+            }
+        }
+    }
+
+    mod unchanged_params {
+        use super::*;
+
+        #[test]
+        fn setting_same_params_keeps_gliding() {
+            let mut reset = band(BandType::Peak, 1000.0, 0.0, 1.0);
+            reset.set_smoothing_samples(64);
+            reset.set_params(2000.0, 6.0, 1.0, SAMPLE_RATE);
+            let mut reference = reset.clone();
+
+            for i in 0..128 {
+                // Within the tolerance, so this shouldn't restart the glide
+                reset.set_params(2000.0 * (1.0 + 1e-7), 6.0, 1.0, SAMPLE_RATE);
+
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(reset.process(input), reference.process(input));
+            }
+        }
+
+        #[test]
+        fn new_band_computes_coefficients() {
+            // New bands already store these parameters and sample rate, but without any coefficients
+            let mut band = EQBand::new(BandType::Peak, 1000.0, 6.0, 1.0);
+            band.set_params(1000.0, 6.0, 1.0, 44100.0);
+            approx::assert_relative_eq!(band.magnitude_db(1000.0), 6.0, epsilon = 0.01);
+        }
+    }
+
+    mod active_bands {
+        use super::*;
+
+        #[test]
+        fn skips_inactive_bands() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::Peak, 1000.0, 6.0, 1.0).unwrap();
+            eq.add_band(BandType::Peak, 1000.0, 3.0, 1.0).unwrap();
+
+            eq.set_active_bands(1);
+            approx::assert_relative_eq!(eq.magnitude_db(1000.0), 6.0, epsilon = 0.01);
+            let mut reference = band(BandType::Peak, 1000.0, 6.0, 1.0);
+            for i in 0..100 {
+                let input = (i as f32 * 0.1).sin();
+                assert_eq!(eq.process(input), reference.process(input));
+            }
+
+            eq.set_active_bands(2);
+            approx::assert_relative_eq!(eq.magnitude_db(1000.0), 9.0, epsilon = 0.01);
+        }
+    }
+
+    mod broadband_gain {
+        use super::*;
+
+        #[test]
+        fn compensates_boosts() {
+            let mut eq = ParametricEQ::new(SAMPLE_RATE);
+            eq.add_band(BandType::LowShelf, 100.0, 12.0, 0.5).unwrap();
+            eq.add_band(BandType::Peak, 1000.0, 12.0, 1.0).unwrap();
+            eq.add_band(BandType::HighShelf, 10000.0, 12.0, 0.5)
+                .unwrap();
+            let makeup_gain = 10.0f32.powf(-eq.broadband_gain_db() / 20.0);
+
+            // A sum of equal amplitude sines spread out over the spectrum
+            let test_freqs = [40.0, 150.0, 400.0, 1000.0, 2500.0, 6000.0, 12000.0];
+            let input = |i: usize| {
+                test_freqs
+                    .iter()
+                    .map(|freq| (i as f32 * freq * 2.0 * std::f32::consts::PI / SAMPLE_RATE).sin())
+                    .sum::<f32>()
+            };
+
+            let mut input_energy = 0.0f64;
+            let mut output_energy = 0.0f64;
+            for i in 0..SAMPLE_RATE as usize * 2 {
+                let output = eq.process(input(i)) * makeup_gain;
+                if i >= SAMPLE_RATE as usize {
+                    input_energy += (input(i) as f64).powi(2);
+                    output_energy += (output as f64).powi(2);
+                }
+            }
+
+            let level_change_db = 10.0 * (output_energy / input_energy).log10();
+            assert!(level_change_db.abs() < 1.0);
+        }
+    }
+
+    mod bandwidth {
+        use super::*;
+
+        #[test]
+        fn one_octave_is_q_1_41() {
+            assert!((q_from_bandwidth_octaves(1.0) - std::f32::consts::SQRT_2).abs() < 1e-4);
+        }
+
+        #[test]
+        fn known_values() {
+            assert!((q_from_bandwidth_octaves(2.0) - 0.6667).abs() < 1e-3);
+            assert!((q_from_bandwidth_octaves(1.0 / 3.0) - 4.3185).abs() < 1e-3);
+        }
+
+        #[test]
+        fn round_trip() {
+            for bw in [0.1, 0.5, 1.0, 2.0, 3.0, 5.0] {
+                let q = q_from_bandwidth_octaves(bw);
+                assert!((bandwidth_octaves_from_q(q) - bw).abs() < 1e-4, "bw = {bw}");
+            }
+        }
+    }
+
+    mod crossover {
+        use super::*;
+
+        #[test]
+        fn linkwitz_riley_is_6_db_down_at_the_crossover() {
+            for band_type in [BandType::LowPass, BandType::HighPass] {
+                let filter = LinkwitzRiley::new(band_type, 1000.0, SAMPLE_RATE).unwrap();
+                approx::assert_relative_eq!(filter.magnitude_db(1000.0), -6.02, epsilon = 0.01);
+            }
+            assert!(LinkwitzRiley::new(BandType::Peak, 1000.0, SAMPLE_RATE).is_err());
+        }
+
+        /// Every band only passes its own part of the spectrum
+        #[test]
+        fn splits_into_bands() {
+            for (test_freq, loudest_band) in [(50.0, 0), (1000.0, 1), (15000.0, 2)] {
+                let gains_db: Vec<f32> = (0..3)
+                    .map(|band| {
+                        let mut crossover = ThreeBandCrossover::new(200.0, 4000.0, SAMPLE_RATE);
+                        measure_gain_db_at(|x| crossover.process(x)[band], test_freq, SAMPLE_RATE)
+                    })
+                    .collect();
+                for (band, gain_db) in gains_db.iter().enumerate() {
+                    if band == loudest_band {
+                        assert!(gain_db.abs() < 0.5, "{test_freq} Hz: {gains_db:?}");
+                    } else {
+                        assert!(*gain_db < -20.0, "{test_freq} Hz: {gains_db:?}");
+                    }
+                }
+            }
+        }
+
+        /// The bands add up to an allpass, so summing them reconstructs the input with a flat
+        /// magnitude response. This includes the crossover frequencies themselves, where two bands
+        /// are both 6 dB down.
+        #[test]
+        fn summed_bands_are_flat() {
+            for test_freq in [20.0, 100.0, 200.0, 600.0, 1000.0, 4000.0, 8000.0, 18000.0] {
+                let mut crossover = ThreeBandCrossover::new(200.0, 4000.0, SAMPLE_RATE);
+                let gain_db = measure_gain_db_at(
+                    |x| crossover.process(x).iter().sum(),
+                    test_freq,
+                    SAMPLE_RATE,
+                );
+                assert!(gain_db.abs() < 0.01, "{test_freq} Hz: {gain_db} dB");
+            }
+        }
+
+        #[test]
+        fn low_crossover_stays_below_high_crossover() {
+            let mut crossover = ThreeBandCrossover::new(200.0, 4000.0, SAMPLE_RATE);
+            crossover.set_frequencies(6000.0, 3000.0);
+            assert_eq!((crossover.low_freq, crossover.high_freq), (3000.0, 3000.0));
+            assert_eq!(crossover.low_low_pass.freq, 3000.0);
+
+            // The bands still add up to a flat response
+            let gain_db =
+                measure_gain_db_at(|x| crossover.process(x).iter().sum(), 3000.0, SAMPLE_RATE);
+            assert!(gain_db.abs() < 0.01, "{gain_db} dB");
+        }
+
+        #[test]
+        fn moving_the_crossovers() {
+            let mut crossover = ThreeBandCrossover::new(200.0, 4000.0, SAMPLE_RATE);
+            crossover.set_frequencies(2000.0, 8000.0);
+            let low_gain_db = measure_gain_db_at(|x| crossover.process(x)[0], 500.0, SAMPLE_RATE);
+            assert!(low_gain_db.abs() < 0.1, "{low_gain_db} dB");
+
+            crossover.reset();
+            let sum_gain_db =
+                measure_gain_db_at(|x| crossover.process(x).iter().sum(), 2000.0, SAMPLE_RATE);
+            assert!(sum_gain_db.abs() < 0.01, "{sum_gain_db} dB");
+        }
+    }
 }
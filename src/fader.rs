@@ -0,0 +1,215 @@
+/// Switches between settings that can't change in the middle of the signal without a click, like
+/// the oversampling factor. When the target changes, the output is faded out using the old value,
+/// the value is switched while the output is silent, and then the output is faded back in. The
+/// switch happens in [`update()`][Self::update()], which should be called at a point where changing
+/// the value is safe, like at the start of a block. Ending a block after
+/// [`samples_until_switch()`][Self::samples_until_switch()] samples lets the next block switch
+/// right away.
+#[derive(Debug)]
+pub struct SwitchFader<T> {
+    current: T,
+    target: T,
+    gain: f32,
+    step: f32,
+    /// The number of samples to stay silent for after switching before fading back in.
+    hold_samples_left: usize,
+}
+
+impl<T: Copy + PartialEq> SwitchFader<T> {
+    /// Create a fader set to `value` that fades out and back in over `fade_samples` samples each.
+    pub fn new(value: T, fade_samples: usize) -> Self {
+        Self {
+            current: value,
+            target: value,
+            gain: 1.0,
+            step: 1.0 / fade_samples.max(1) as f32,
+            hold_samples_left: 0,
+        }
+    }
+
+    /// The value that should currently be used for processing.
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// The value that's being faded to. This is the same as [`current()`][Self::current()] unless
+    /// a switch is in progress.
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Whether the output is currently being faded. If this is false the gain is always 1.
+    pub fn is_fading(&self) -> bool {
+        self.current != self.target || self.gain < 1.0
+    }
+
+    /// Set the value to fade to. If the old value has been fully faded out, then the current value
+    /// changes to the target. Returns `true` if that happened, so any state belonging to the new
+    /// value can be reset.
+    pub fn update(&mut self, target: T) -> bool {
+        self.target = target;
+        if self.current != self.target && self.gain <= 0.0 {
+            self.current = self.target;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The number of samples until the old value has been fully faded out, after which
+    /// [`update()`][Self::update()] switches to the new value. Returns `None` if the value isn't
+    /// being switched.
+    pub fn samples_until_switch(&self) -> Option<usize> {
+        (self.current != self.target).then(|| (self.gain / self.step).ceil() as usize)
+    }
+
+    /// Keep the output silent for another `num_samples` samples after switching before fading
+    /// back in. This can be used to hide the transient from filters that were reset after the
+    /// switch.
+    pub fn hold(&mut self, num_samples: usize) {
+        self.hold_samples_left = num_samples;
+    }
+
+    /// Get the gain for the next sample. This fades out while the current value differs from the
+    /// target, and stays at zero until the next [`update()`][Self::update()] switches values.
+    pub fn next_gain(&mut self) -> f32 {
+        if self.current != self.target {
+            self.gain = (self.gain - self.step).max(0.0);
+        } else if self.hold_samples_left > 0 {
+            self.hold_samples_left -= 1;
+        } else {
+            self.gain = (self.gain + self.step).min(1.0);
+        }
+
+        self.gain
+    }
+}
+
+/// Crossfades between two signals when a toggle like bypass changes. The amount moves linearly
+/// between 0 when the toggle is off and 1 when it's on.
+#[derive(Debug)]
+pub struct Crossfader {
+    amount: f32,
+    step: f32,
+}
+
+impl Crossfader {
+    /// Create a crossfader that starts at `enabled` and fades over `fade_samples` samples.
+    pub fn new(enabled: bool, fade_samples: usize) -> Self {
+        Self {
+            amount: if enabled { 1.0 } else { 0.0 },
+            step: 1.0 / fade_samples.max(1) as f32,
+        }
+    }
+
+    /// Jump to `enabled` without fading.
+    pub fn reset(&mut self, enabled: bool) {
+        self.amount = if enabled { 1.0 } else { 0.0 };
+    }
+
+    /// Whether the crossfader still needs to move to reach `enabled`.
+    pub fn is_fading(&self, enabled: bool) -> bool {
+        self.amount != if enabled { 1.0 } else { 0.0 }
+    }
+
+    /// Get the crossfade amount for the next sample while moving towards `enabled`.
+    pub fn next(&mut self, enabled: bool) -> f32 {
+        self.amount = if enabled {
+            (self.amount + self.step).min(1.0)
+        } else {
+            (self.amount - self.step).max(0.0)
+        };
+
+        self.amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_after_fading_out() {
+        let mut fader = SwitchFader::new(1, 4);
+        assert!(!fader.update(2));
+        assert_eq!(fader.current(), 1);
+
+        let gains: Vec<f32> = (0..4).map(|_| fader.next_gain()).collect();
+        assert_eq!(gains, [0.75, 0.5, 0.25, 0.0]);
+        assert_eq!(fader.current(), 1);
+
+        assert!(fader.update(2));
+        assert_eq!(fader.current(), 2);
+        let gains: Vec<f32> = (0..5).map(|_| fader.next_gain()).collect();
+        assert_eq!(gains, [0.25, 0.5, 0.75, 1.0, 1.0]);
+        assert!(!fader.is_fading());
+    }
+
+    #[test]
+    fn counts_down_to_the_switch() {
+        let mut fader = SwitchFader::new(1, 4);
+        assert_eq!(fader.samples_until_switch(), None);
+
+        fader.update(2);
+        assert_eq!(fader.samples_until_switch(), Some(4));
+        fader.next_gain();
+        assert_eq!(fader.samples_until_switch(), Some(3));
+        for _ in 0..3 {
+            fader.next_gain();
+        }
+        assert_eq!(fader.samples_until_switch(), Some(0));
+        assert!(fader.update(2));
+        assert_eq!(fader.samples_until_switch(), None);
+    }
+
+    #[test]
+    fn holds_before_fading_in() {
+        let mut fader = SwitchFader::new(1, 1);
+        fader.update(2);
+        fader.next_gain();
+        fader.update(2);
+        fader.hold(2);
+
+        let gains: Vec<f32> = (0..4).map(|_| fader.next_gain()).collect();
+        assert_eq!(gains, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn fades_back_in_when_target_reverts() {
+        let mut fader = SwitchFader::new(1, 4);
+        fader.update(2);
+        fader.next_gain();
+        fader.next_gain();
+
+        assert!(!fader.update(1));
+        assert_eq!(fader.next_gain(), 0.75);
+        assert_eq!(fader.next_gain(), 1.0);
+        assert!(!fader.is_fading());
+    }
+
+    mod crossfader {
+        use super::*;
+
+        #[test]
+        fn fades_in_and_out() {
+            let mut crossfader = Crossfader::new(false, 4);
+            assert!(!crossfader.is_fading(false));
+            assert!(crossfader.is_fading(true));
+
+            let amounts: Vec<f32> = (0..5).map(|_| crossfader.next(true)).collect();
+            assert_eq!(amounts, [0.25, 0.5, 0.75, 1.0, 1.0]);
+            assert!(!crossfader.is_fading(true));
+
+            let amounts: Vec<f32> = (0..2).map(|_| crossfader.next(false)).collect();
+            assert_eq!(amounts, [0.75, 0.5]);
+        }
+
+        #[test]
+        fn reset_jumps() {
+            let mut crossfader = Crossfader::new(false, 4);
+            crossfader.reset(true);
+            assert!(!crossfader.is_fading(true));
+            assert_eq!(crossfader.next(true), 1.0);
+        }
+    }
+}
@@ -1,33 +1,703 @@
+// The state and the coefficient are stored in double precision. At high (oversampled) sample rates
+// the coefficient gets very close to 1, and single precision leaves a residual DC offset.
 pub struct DCBlocker {
-    prev_input: f32,
-    prev_output: f32,
-    coeff: f32,
+    prev_input: f64,
+    prev_output: f64,
+    sample_rate: f32,
+    corner_freq: f32,
+    coeff: f64,
 }
 
 impl DCBlocker {
+    pub const DEFAULT_CORNER_FREQ: f32 = 20.0;
+
     pub fn new(sample_rate: f32) -> Self {
         DCBlocker {
             prev_input: 0.0,
             prev_output: 0.0,
-            coeff: Self::calculate_coefficient(sample_rate),
+            sample_rate,
+            corner_freq: Self::DEFAULT_CORNER_FREQ,
+            coeff: Self::calculate_coefficient(sample_rate, Self::DEFAULT_CORNER_FREQ),
         }
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
-        self.coeff = Self::calculate_coefficient(sample_rate);
+        self.sample_rate = sample_rate;
+        self.coeff = Self::calculate_coefficient(self.sample_rate, self.corner_freq);
+    }
+
+    pub fn set_corner_frequency(&mut self, corner_freq: f32) {
+        if corner_freq == self.corner_freq {
+            return;
+        }
+        self.corner_freq = corner_freq;
+        self.coeff = Self::calculate_coefficient(self.sample_rate, self.corner_freq);
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
     }
 
     pub fn process(&mut self, input: f32) -> f32 {
+        let input = input as f64;
         let output = input - self.prev_input + self.coeff * self.prev_output;
         self.prev_input = input;
         self.prev_output = output;
+        output as f32
+    }
+
+    fn calculate_coefficient(sample_rate: f32, corner_freq: f32) -> f64 {
+        // Calculate the pole's coefficient using the bilinear transform, prewarped so the -3 dB
+        // point lands on the corner frequency
+        let k = (std::f64::consts::PI * corner_freq as f64 / sample_rate as f64).tan();
+        (1.0 - k) / (1.0 + k)
+    }
+}
+
+// A resonant low-pass for taming the harmonics from the distortion, like the tone knob on a
+// distortion pedal. This is a trapezoidal state variable filter, so it stays stable while the
+// cutoff is being modulated. Even at 20 kHz a low-pass would still cut the top of the audible
+// range, so over the top octave the filter is crossfaded out until the fully open tone is flat.
+pub struct ToneFilter {
+    ic1eq: f32,
+    ic2eq: f32,
+    sample_rate: f32,
+    cutoff: f32,
+    resonance: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    // How much of the filtered signal is used, this is 0 when the tone is fully open
+    mix: f32,
+}
+
+impl ToneFilter {
+    // The cutoff at which the tone is fully open and the filter is faded out completely
+    pub const MAX_CUTOFF: f32 = 20000.0;
+
+    // The Q at zero and full resonance
+    const MIN_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    const MAX_Q: f32 = 10.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut filter = ToneFilter {
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+            sample_rate,
+            cutoff: Self::MAX_CUTOFF,
+            resonance: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            mix: 0.0,
+        };
+        filter.update_coefficients();
+
+        filter
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    // Sets the cutoff frequency in Hz and the resonance between 0 and 1
+    pub fn set_params(&mut self, cutoff: f32, resonance: f32) {
+        self.cutoff = cutoff;
+        self.resonance = resonance.clamp(0.0, 1.0);
+        self.update_coefficients();
+    }
+
+    pub fn reset(&mut self) {
+        self.ic1eq = 0.0;
+        self.ic2eq = 0.0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+
+        // The filter keeps running while it's faded out, so it doesn't start from stale state when
+        // the tone is closed again
+        input + (v2 - input) * self.mix
+    }
+
+    fn update_coefficients(&mut self) {
+        // Fully filtered an octave below the maximum cutoff, and not filtered at all at the maximum
+        self.mix = (Self::MAX_CUTOFF / self.cutoff).log2().clamp(0.0, 1.0);
+
+        // Keep the cutoff below Nyquist, the filter stops working past that
+        let cutoff = self.cutoff.min(self.sample_rate * 0.49);
+        let q = Self::MIN_Q * (Self::MAX_Q / Self::MIN_Q).powf(self.resonance);
+
+        let g = (std::f32::consts::PI * cutoff / self.sample_rate).tan();
+        let k = 1.0 / q;
+        self.a1 = 1.0 / (1.0 + g * (g + k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+}
+
+// The frequency in the middle of the emphasis shelves' transition bands
+const EMPHASIS_FREQ: f32 = 1000.0;
+
+// Calculates the `(b0, b1, a1)` coefficients for a first order high shelf with `gain_db` of gain
+// at high frequencies and unity gain at DC. This is the bilinear transform of
+// `G * (s + wz) / (s + G * wz)`, which has its zero inside of the unit circle so the inverse filter
+// is stable too.
+fn emphasis_coefficients(sample_rate: f32, gain_db: f32) -> (f32, f32, f32) {
+    let gain = 10.0f32.powf(gain_db / 20.0);
+    let w = 2.0 * std::f32::consts::PI * EMPHASIS_FREQ.min(sample_rate * 0.45);
+    let wz = w / gain.sqrt();
+    let wp = w * gain.sqrt();
+    let c = 2.0 * sample_rate;
+
+    let norm = 1.0 / (c + wp);
+    (
+        gain * (c + wz) * norm,
+        gain * (wz - c) * norm,
+        (wp - c) * norm,
+    )
+}
+
+// Boosts the highs going into the distortion so they get distorted harder. The inverse filter
+// with the same settings cuts them again after the distortion, undoing this exactly.
+pub struct Emphasis {
+    // Whether this is the de-emphasis filter. Its numerator and denominator are the emphasis
+    // filter's swapped around.
+    inverse: bool,
+    prev_input: f32,
+    prev_output: f32,
+    b0: f32,
+    b1: f32,
+    a1: f32,
+}
+
+impl Emphasis {
+    pub fn new(sample_rate: f32, gain_db: f32) -> Self {
+        Self::with_direction(sample_rate, gain_db, false)
+    }
+
+    // The de-emphasis filter that undoes `Emphasis::new()` with the same settings
+    pub fn new_inverse(sample_rate: f32, gain_db: f32) -> Self {
+        Self::with_direction(sample_rate, gain_db, true)
+    }
+
+    fn with_direction(sample_rate: f32, gain_db: f32, inverse: bool) -> Self {
+        let mut filter = Emphasis {
+            inverse,
+            prev_input: 0.0,
+            prev_output: 0.0,
+            b0: 1.0,
+            b1: 0.0,
+            a1: 0.0,
+        };
+        filter.set_params(sample_rate, gain_db);
+
+        filter
+    }
+
+    pub fn set_params(&mut self, sample_rate: f32, gain_db: f32) {
+        let (b0, b1, a1) = emphasis_coefficients(sample_rate, gain_db);
+        (self.b0, self.b1, self.a1) = if self.inverse {
+            (1.0 / b0, a1 / b0, b1 / b0)
+        } else {
+            (b0, b1, a1)
+        };
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.b1 * self.prev_input - self.a1 * self.prev_output;
+        self.prev_input = input;
+        self.prev_output = output;
         output
     }
+}
+
+// A first order allpass. It leaves the level alone and shifts the phase from 0 degrees at DC to
+// -180 degrees at Nyquist, passing -90 degrees at its break frequency.
+struct FirstOrderAllpass {
+    prev_input: f32,
+    prev_output: f32,
+    coeff: f32,
+}
 
-    fn calculate_coefficient(sample_rate: f32) -> f32 {
-        // Calculate the coefficient based on a corner frequency of 20 Hz
-        let corner_freq = 20.0;
-        let tau = 1.0 / (2.0 * std::f32::consts::PI * corner_freq);
-        (tau * sample_rate - 1.0) / (tau * sample_rate + 1.0)
+impl FirstOrderAllpass {
+    fn new() -> Self {
+        FirstOrderAllpass {
+            prev_input: 0.0,
+            prev_output: 0.0,
+            coeff: 0.0,
+        }
+    }
+
+    fn set_break_frequency(&mut self, sample_rate: f32, freq: f32) {
+        let k = (std::f32::consts::PI * freq.min(sample_rate * 0.45) / sample_rate).tan();
+        self.coeff = (k - 1.0) / (k + 1.0);
+    }
+
+    fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.coeff * input + self.prev_input - self.coeff * self.prev_output;
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+}
+
+// The break frequencies the left and the right channel's allpasses are spread out around
+const WIDENER_BREAK_FREQS: [f32; 3] = [200.0, 800.0, 3200.0];
+
+// Widens a stereo pair by running each channel through a short chain of allpasses with different
+// break frequencies. The level of each channel stays the same and only the phases drift apart.
+// The break frequencies alternate between being higher on the left and higher on the right, so
+// the phase differences of the stages partly cancel out and never get close to 180 degrees. That
+// keeps the mono sum intact, where scaling up the side signal cancels out completely in mono.
+pub struct Widener {
+    left: [FirstOrderAllpass; WIDENER_BREAK_FREQS.len()],
+    right: [FirstOrderAllpass; WIDENER_BREAK_FREQS.len()],
+    sample_rate: f32,
+    amount: f32,
+}
+
+impl Widener {
+    // How far the channels' break frequencies move away from `WIDENER_BREAK_FREQS` at full
+    // decorrelation, one channel's up and the other's down. At this spread the mono sum loses at
+    // most about 2 dB.
+    const MAX_SPREAD: f32 = 3.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut widener = Widener {
+            left: std::array::from_fn(|_| FirstOrderAllpass::new()),
+            right: std::array::from_fn(|_| FirstOrderAllpass::new()),
+            sample_rate,
+            amount: 0.0,
+        };
+        widener.update_coefficients();
+
+        widener
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    // Sets how far apart the channels are pushed, between 0 and 1. At 0 both channels go through
+    // the same allpasses and stay identical if they were identical to begin with.
+    pub fn set_amount(&mut self, amount: f32) {
+        let amount = amount.clamp(0.0, 1.0);
+        if amount == self.amount {
+            return;
+        }
+        self.amount = amount;
+        self.update_coefficients();
+    }
+
+    pub fn reset(&mut self) {
+        for allpass in self.left.iter_mut().chain(&mut self.right) {
+            allpass.reset();
+        }
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let left = self
+            .left
+            .iter_mut()
+            .fold(left, |sample, allpass| allpass.process(sample));
+        let right = self
+            .right
+            .iter_mut()
+            .fold(right, |sample, allpass| allpass.process(sample));
+        (left, right)
+    }
+
+    fn update_coefficients(&mut self) {
+        let spread = Self::MAX_SPREAD.powf(self.amount);
+        for (stage, freq) in WIDENER_BREAK_FREQS.iter().enumerate() {
+            let (left_freq, right_freq) = if stage % 2 == 0 {
+                (freq * spread, freq / spread)
+            } else {
+                (freq / spread, freq * spread)
+            };
+            self.left[stage].set_break_frequency(self.sample_rate, left_freq);
+            self.right[stage].set_break_frequency(self.sample_rate, right_freq);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Runs a sine wave at `test_freq` through a filter's `process` function and returns the gain
+    /// in decibels measured after the filter has settled.
+    fn measure_gain_db(mut process: impl FnMut(f32) -> f32, test_freq: f32) -> f32 {
+        const SETTLE_SAMPLES: usize = 96000;
+        const MEASURE_SAMPLES: usize = 96000;
+
+        let mut input_energy = 0.0f64;
+        let mut output_energy = 0.0f64;
+        for i in 0..SETTLE_SAMPLES + MEASURE_SAMPLES {
+            let input = (i as f32 * test_freq * 2.0 * std::f32::consts::PI / SAMPLE_RATE).sin();
+            let output = process(input);
+            if i >= SETTLE_SAMPLES {
+                input_energy += (input as f64).powi(2);
+                output_energy += (output as f64).powi(2);
+            }
+        }
+
+        10.0 * (output_energy / input_energy).log10() as f32
+    }
+
+    mod reset {
+        use super::*;
+
+        #[test]
+        fn clears_dc_burst() {
+            let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+            for _ in 0..100 {
+                dc_blocker.process(1.0);
+            }
+
+            dc_blocker.reset();
+            for _ in 0..100 {
+                assert_eq!(dc_blocker.process(0.0), 0.0);
+            }
+        }
+    }
+
+    mod precision {
+        use super::*;
+
+        const HIGH_SAMPLE_RATE: f32 = 192000.0;
+
+        // The DC blocker as it was before, with single precision state
+        struct SinglePrecisionDCBlocker {
+            prev_input: f32,
+            prev_output: f32,
+            coeff: f32,
+        }
+
+        impl SinglePrecisionDCBlocker {
+            fn process(&mut self, input: f32) -> f32 {
+                let output = input - self.prev_input + self.coeff * self.prev_output;
+                self.prev_input = input;
+                self.prev_output = output;
+                output
+            }
+        }
+
+        // Feeds ten seconds of a biased sine wave through `process` and returns the DC offset
+        // over the last second
+        fn residual_dc(mut process: impl FnMut(f32) -> f32) -> f64 {
+            let num_samples = HIGH_SAMPLE_RATE as usize * 10;
+            let measure_from = num_samples - HIGH_SAMPLE_RATE as usize;
+
+            let mut sum = 0.0f64;
+            for i in 0..num_samples {
+                let sine = (i as f64 * 1000.0 * 2.0 * std::f64::consts::PI
+                    / HIGH_SAMPLE_RATE as f64)
+                    .sin() as f32;
+                let output = process(0.5 + sine * 0.25);
+                if i >= measure_from {
+                    sum += output as f64;
+                }
+            }
+
+            sum / HIGH_SAMPLE_RATE as f64
+        }
+
+        #[test]
+        fn converges_closer_to_zero_than_f32() {
+            let mut dc_blocker = DCBlocker::new(HIGH_SAMPLE_RATE);
+            let mut single_precision = SinglePrecisionDCBlocker {
+                prev_input: 0.0,
+                prev_output: 0.0,
+                coeff: dc_blocker.coeff as f32,
+            };
+
+            let residual = residual_dc(|x| dc_blocker.process(x)).abs();
+            let single_precision_residual = residual_dc(|x| single_precision.process(x)).abs();
+            assert!(
+                residual < single_precision_residual,
+                "f64 = {residual}, f32 = {single_precision_residual}"
+            );
+            assert!(residual < 1e-6, "f64 = {residual}");
+        }
+    }
+
+    mod corner_frequency {
+        use super::*;
+
+        #[test]
+        fn minus_3_db_at_corner() {
+            for corner_freq in [5.0, 20.0, 50.0, 120.0] {
+                let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+                dc_blocker.set_corner_frequency(corner_freq);
+
+                let gain_db = measure_gain_db(|x| dc_blocker.process(x), corner_freq);
+                assert!(
+                    (gain_db + 3.0).abs() < 0.5,
+                    "corner = {corner_freq} Hz, gain = {gain_db} dB"
+                );
+            }
+        }
+
+        #[test]
+        fn higher_corner_cuts_more_bass() {
+            let mut low_corner = DCBlocker::new(SAMPLE_RATE);
+            low_corner.set_corner_frequency(10.0);
+            let mut high_corner = DCBlocker::new(SAMPLE_RATE);
+            high_corner.set_corner_frequency(100.0);
+
+            let low_corner_gain_db = measure_gain_db(|x| low_corner.process(x), 40.0);
+            let high_corner_gain_db = measure_gain_db(|x| high_corner.process(x), 40.0);
+            assert!(high_corner_gain_db < low_corner_gain_db - 3.0);
+        }
+
+        #[test]
+        fn defaults_to_20_hz() {
+            let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+            let gain_db = measure_gain_db(|x| dc_blocker.process(x), 20.0);
+            assert!((gain_db + 3.0).abs() < 0.5);
+        }
+
+        #[test]
+        fn survives_sample_rate_changes() {
+            let mut dc_blocker = DCBlocker::new(SAMPLE_RATE / 2.0);
+            dc_blocker.set_corner_frequency(50.0);
+            dc_blocker.set_sample_rate(SAMPLE_RATE);
+
+            let gain_db = measure_gain_db(|x| dc_blocker.process(x), 50.0);
+            assert!((gain_db + 3.0).abs() < 0.5);
+        }
+    }
+
+    mod tone_filter {
+        use super::*;
+        use crate::nonlinearity::hard_clip;
+
+        // Runs a hard clipped 1 kHz sine through the filter and returns the combined energy of its
+        // 5th and higher harmonics
+        fn high_harmonic_energy(cutoff: f32) -> f32 {
+            const PERIOD: usize = 48;
+
+            let mut filter = ToneFilter::new(SAMPLE_RATE);
+            filter.set_params(cutoff, 0.0);
+
+            // Let the filter settle before taking a single period
+            let mut output = [0.0; PERIOD];
+            for i in 0..PERIOD * 100 {
+                let phase = (i % PERIOD) as f32 / PERIOD as f32 * 2.0 * std::f32::consts::PI;
+                output[i % PERIOD] = filter.process(hard_clip(phase.sin() * 4.0, 1.0));
+            }
+
+            (5..PERIOD / 2)
+                .map(|harmonic| {
+                    let (mut re, mut im) = (0.0f32, 0.0f32);
+                    for (i, y) in output.iter().enumerate() {
+                        let phase =
+                            (i * harmonic) as f32 / PERIOD as f32 * 2.0 * std::f32::consts::PI;
+                        re += y * phase.cos();
+                        im += y * phase.sin();
+                    }
+                    re * re + im * im
+                })
+                .sum()
+        }
+
+        #[test]
+        fn lower_cutoff_attenuates_harmonics() {
+            let open = high_harmonic_energy(20000.0);
+            let half_closed = high_harmonic_energy(4000.0);
+            let closed = high_harmonic_energy(1500.0);
+
+            assert!(
+                half_closed < open * 0.5,
+                "open = {open}, half = {half_closed}"
+            );
+            assert!(
+                closed < half_closed * 0.5,
+                "half = {half_closed}, closed = {closed}"
+            );
+        }
+
+        // At the maximum cutoff the filter is faded out completely, so the top of the audible range
+        // isn't cut
+        #[test]
+        fn open_tone_is_flat() {
+            for test_freq in [16000.0, 18000.0, 20000.0] {
+                let mut filter = ToneFilter::new(SAMPLE_RATE);
+                filter.set_params(ToneFilter::MAX_CUTOFF, 0.0);
+
+                let gain_db = measure_gain_db(|x| filter.process(x), test_freq);
+                assert!(gain_db.abs() < 0.01, "{test_freq} Hz, gain = {gain_db} dB");
+            }
+        }
+
+        #[test]
+        fn passes_low_frequencies() {
+            let mut filter = ToneFilter::new(SAMPLE_RATE);
+            filter.set_params(5000.0, 0.0);
+
+            let gain_db = measure_gain_db(|x| filter.process(x), 100.0);
+            assert!(gain_db.abs() < 0.1, "gain = {gain_db} dB");
+        }
+
+        #[test]
+        fn resonance_boosts_the_cutoff() {
+            let mut filter = ToneFilter::new(SAMPLE_RATE);
+            filter.set_params(2000.0, 0.0);
+            let flat_gain_db = measure_gain_db(|x| filter.process(x), 2000.0);
+
+            let mut filter = ToneFilter::new(SAMPLE_RATE);
+            filter.set_params(2000.0, 1.0);
+            let resonant_gain_db = measure_gain_db(|x| filter.process(x), 2000.0);
+
+            assert!((flat_gain_db + 3.0).abs() < 0.5, "gain = {flat_gain_db} dB");
+            assert!(resonant_gain_db > 15.0, "gain = {resonant_gain_db} dB");
+        }
+    }
+
+    mod emphasis {
+        use super::*;
+
+        #[test]
+        fn boosts_highs() {
+            let mut emphasis = Emphasis::new(SAMPLE_RATE, 12.0);
+            let low_gain_db = measure_gain_db(|x| emphasis.process(x), 50.0);
+            let mut emphasis = Emphasis::new(SAMPLE_RATE, 12.0);
+            let high_gain_db = measure_gain_db(|x| emphasis.process(x), 15000.0);
+
+            assert!(low_gain_db.abs() < 0.5, "gain = {low_gain_db} dB");
+            assert!(
+                (high_gain_db - 12.0).abs() < 0.5,
+                "gain = {high_gain_db} dB"
+            );
+        }
+
+        #[test]
+        fn de_emphasis_cancels_emphasis() {
+            for gain_db in [0.0, 6.0, 18.0] {
+                let mut emphasis = Emphasis::new(SAMPLE_RATE, gain_db);
+                let mut de_emphasis = Emphasis::new_inverse(SAMPLE_RATE, gain_db);
+
+                // The combined impulse response should be a single impulse
+                for i in 0..1000 {
+                    let input = if i == 0 { 1.0 } else { 0.0 };
+                    let output = de_emphasis.process(emphasis.process(input));
+                    assert!(
+                        (output - input).abs() < 1e-5,
+                        "gain = {gain_db} dB, i = {i}"
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn flat_response_through_both() {
+            for test_freq in [50.0, 1000.0, 10000.0] {
+                let mut emphasis = Emphasis::new(SAMPLE_RATE, 12.0);
+                let mut de_emphasis = Emphasis::new_inverse(SAMPLE_RATE, 12.0);
+
+                let gain_db =
+                    measure_gain_db(|x| de_emphasis.process(emphasis.process(x)), test_freq);
+                assert!(gain_db.abs() < 0.01, "{test_freq} Hz, gain = {gain_db} dB");
+            }
+        }
+    }
+
+    mod widener {
+        use super::*;
+
+        const TEST_FREQS: [f32; 6] = [50.0, 200.0, 500.0, 1000.0, 3200.0, 12000.0];
+
+        // Feeds the same signal into both channels, like a mono source, and returns the gain of the
+        // mono sum and of the side signal in decibels
+        fn mono_and_side_gain_db(amount: f32, test_freq: f32) -> (f32, f32) {
+            let mut widener = Widener::new(SAMPLE_RATE);
+            widener.set_amount(amount);
+            let mono_gain_db = measure_gain_db(
+                |x| {
+                    let (left, right) = widener.process(x, x);
+                    (left + right) * 0.5
+                },
+                test_freq,
+            );
+
+            let mut widener = Widener::new(SAMPLE_RATE);
+            widener.set_amount(amount);
+            let side_gain_db = measure_gain_db(
+                |x| {
+                    let (left, right) = widener.process(x, x);
+                    (left - right) * 0.5
+                },
+                test_freq,
+            );
+
+            (mono_gain_db, side_gain_db)
+        }
+
+        #[test]
+        fn mono_sum_keeps_most_energy() {
+            for test_freq in TEST_FREQS {
+                let (mono_gain_db, _) = mono_and_side_gain_db(1.0, test_freq);
+                assert!(
+                    mono_gain_db > -3.0,
+                    "{test_freq} Hz, gain = {mono_gain_db} dB"
+                );
+            }
+        }
+
+        // The phase difference varies with the frequency, but there's a side signal everywhere
+        #[test]
+        fn decorrelates_a_mono_signal() {
+            for test_freq in TEST_FREQS {
+                let (_, side_gain_db) = mono_and_side_gain_db(1.0, test_freq);
+                assert!(
+                    side_gain_db > -15.0,
+                    "{test_freq} Hz, gain = {side_gain_db} dB"
+                );
+            }
+        }
+
+        #[test]
+        fn zero_amount_keeps_channels_identical() {
+            let mut widener = Widener::new(SAMPLE_RATE);
+            for i in 0..1000 {
+                let input = (i as f32 * 0.1).sin();
+                let (left, right) = widener.process(input, input);
+                assert_eq!(left, right);
+            }
+        }
+
+        #[test]
+        fn channels_keep_their_level() {
+            for test_freq in TEST_FREQS {
+                let mut widener = Widener::new(SAMPLE_RATE);
+                widener.set_amount(1.0);
+                let left_gain_db = measure_gain_db(|x| widener.process(x, 0.0).0, test_freq);
+                assert!(
+                    left_gain_db.abs() < 0.01,
+                    "{test_freq} Hz, gain = {left_gain_db} dB"
+                );
+            }
+        }
     }
 }
@@ -1,17 +1,74 @@
+use nih_plug::params::ParamMut;
 use nih_plug::prelude::*;
 use std::sync::Arc;
 
+mod analysis;
+mod delay;
+mod dynamics;
+#[cfg(feature = "gui")]
+mod editor;
 mod equalization;
+mod fader;
 mod filters;
-mod nonlinearity;
+mod meter;
+mod modulation;
+// The distortion curves are public so they can be tested and measured on their own
+pub mod nonlinearity;
 mod oversampling;
+mod presets;
+mod stereo;
 
-// Constants for oversampling
-const MAX_BLOCK_SIZE: usize = 32;
-const MAX_OVERSAMPLING_FACTOR: usize = 4;
+// Constants for oversampling. The block size is the host's maximum buffer size, this is only used
+// until the plugin has been initialized.
+const DEFAULT_MAX_BLOCK_SIZE: usize = 32;
+pub const MAX_OVERSAMPLING_FACTOR: usize = 4;
 const DEFAULT_OVERSAMPLING_FACTOR: usize = 1;
 const MAX_OVERSAMPLING_TIMES: usize = oversampling_factor_to_times(MAX_OVERSAMPLING_FACTOR);
-const MAX_OVERSAMPLED_BLOCK_SIZE: usize = MAX_BLOCK_SIZE * MAX_OVERSAMPLING_TIMES;
+// Changing the oversampling factor or quality fades the output out and back in over this many
+// samples each
+const OVERSAMPLING_FADE_SAMPLES: usize = 256;
+/// The length of the crossfade when toggling bypass, in samples.
+const BYPASS_FADE_SAMPLES: usize = 256;
+
+// The bit crusher is bypassed at this bit depth
+const MAX_BIT_DEPTH: f32 = 16.0;
+const MAX_DRIVE: f32 = 2.0;
+/// The attack and release times for the sidechain's envelope follower, in milliseconds.
+const SIDECHAIN_ATTACK_MS: f32 = 5.0;
+const SIDECHAIN_RELEASE_MS: f32 = 150.0;
+/// The attack and release times for the envelope followers that modulate the drive with the
+/// input's own level, in milliseconds.
+const DRIVE_ENV_ATTACK_MS: f32 = 1.0;
+const DRIVE_ENV_RELEASE_MS: f32 = 100.0;
+/// The attack and release times for the level detectors of the dynamic drive, in milliseconds.
+const DYNAMIC_DRIVE_ATTACK_MS: f32 = 5.0;
+const DYNAMIC_DRIVE_RELEASE_MS: f32 = 150.0;
+/// The drive's pre-gain is `10^(2 * drive)`, so one unit of drive is this many decibels.
+const DB_PER_DRIVE: f32 = 40.0;
+// The pre high-pass's lowest cutoff, where it has no audible effect
+const MIN_PRE_HPF_HZ: f32 = 10.0;
+/// The largest boost or cut of the main EQ's bands, in decibels.
+const MAX_EQ_BOOST_DB: f32 = 24.0;
+/// The largest boost or cut of the post EQ's bands, in decibels.
+const MAX_POST_EQ_BOOST_DB: f32 = 12.0;
+// The noise gate is bypassed at this threshold
+const MIN_GATE_THRESHOLD_DB: f32 = -100.0;
+/// The noise gate's fixed attack and hold times, in milliseconds.
+const GATE_ATTACK_MS: f32 = 1.0;
+const GATE_HOLD_MS: f32 = 20.0;
+/// The safety limiter keeps the output below this level.
+const SAFETY_LIMITER_CEILING_DB: f32 = -0.3;
+const SAFETY_LIMITER_RELEASE_MS: f32 = 100.0;
+/// At full depth the LFO moves the frequencies this many octaves up and down.
+const LFO_MAX_OCTAVES: f32 = 2.0;
+/// The LFO is advanced once every this many samples, regardless of the host's buffer size, so the
+/// frequencies it modulates don't move in large steps.
+const CONTROL_BLOCK_SIZE: usize = 64;
+/// The number of bands the multiband distortion splits the signal into.
+const NUM_DISTORTION_BANDS: usize = 3;
+/// The frequencies the multiband distortion splits the signal at by default, in Hz.
+const DEFAULT_CROSSOVER_LOW_HZ: f32 = 250.0;
+const DEFAULT_CROSSOVER_HIGH_HZ: f32 = 2500.0;
 
 /// A macro to load a param into the scratch buffer
 macro_rules! param_next_block {
@@ -26,28 +83,221 @@ macro_rules! param_next_block {
     }};
 }
 
-#[allow(dead_code)]
+/// The filter parameters that are only updated once per block. These are read from the smoothers
+/// once per block and then shared by all channels, so the smoothers don't advance once per
+/// channel and every channel gets the same values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlockFilterParams {
+    low_freq: f32,
+    mid_freq: f32,
+    high_freq: f32,
+    low_slope: f32,
+    mid_q: f32,
+    high_slope: f32,
+    tone_cutoff: f32,
+    tone_resonance: f32,
+    pre_hpf_hz: f32,
+    emphasis_db: f32,
+    post_low_freq: f32,
+    post_mid_freq: f32,
+    post_high_freq: f32,
+    post_low_slope: f32,
+    post_mid_q: f32,
+    post_high_slope: f32,
+    post_low_boost: f32,
+    post_mid_boost: f32,
+    post_high_boost: f32,
+    crossover_low: f32,
+    crossover_high: f32,
+    decorrelation: f32,
+}
+
+impl BlockFilterParams {
+    /// The parameters with the LFO's `destination` moved by `lfo_amount`, which includes the
+    /// LFO's depth. The drive isn't a filter parameter, so it's left alone here.
+    fn with_lfo(
+        mut self,
+        destination: Option<modulation::LfoDestination>,
+        lfo_amount: f32,
+        max_band_freq: f32,
+    ) -> Self {
+        let lfo_octaves = 2.0f32.powf(lfo_amount * LFO_MAX_OCTAVES);
+        match destination {
+            Some(modulation::LfoDestination::MidFreq) => {
+                self.mid_freq = (self.mid_freq * lfo_octaves).min(max_band_freq);
+            }
+            Some(modulation::LfoDestination::ToneCutoff) => {
+                self.tone_cutoff = (self.tone_cutoff * lfo_octaves).min(max_band_freq);
+            }
+            Some(modulation::LfoDestination::Drive) | None => (),
+        }
+
+        self
+    }
+}
+
+/// How the processed signal is combined with the dry signal, in parameter order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Routing {
+    /// Crossfade between the dry and the processed signal with the mix parameter.
+    Series,
+    /// Keep the dry signal at full level and add the processed signal on top of it with the
+    /// parallel blend parameter.
+    Parallel,
+}
+
+impl Routing {
+    const ALL: [Routing; 2] = [Routing::Series, Routing::Parallel];
+
+    fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Routing::Series => "Series",
+            Routing::Parallel => "Parallel",
+        }
+    }
+}
+
+/// The per-sample parameter values are stored here. The buffers for oversampled parameters can
+/// hold a full block at the maximum oversampling amount.
 struct ScratchBuffers {
-    gain: [f32; MAX_OVERSAMPLED_BLOCK_SIZE],
-    drive: [f32; MAX_OVERSAMPLED_BLOCK_SIZE],
+    input_trim: Vec<f32>,
+    gain: Vec<f32>,
+    drive: Vec<f32>,
+    clip_ceiling: Vec<f32>,
+    bias: Vec<f32>,
+    rectifier_mix: Vec<f32>,
+    drive_mix: Vec<f32>,
+    shaper_morph: Vec<f32>,
+    low_boost: Vec<f32>,
+    mid_boost: Vec<f32>,
+    high_boost: Vec<f32>,
+    mix: Vec<f32>,
+    parallel_blend: Vec<f32>,
+    width: Vec<f32>,
+    output_gain: Vec<f32>,
+    dry: Vec<f32>,
+    oversampling_fade: Vec<f32>,
+    bypass_fade: Vec<f32>,
+    wet_solo_fade: Vec<f32>,
+    sidechain_envelope: Vec<f32>,
+    /// The LFO's value for every sample of the block, including its depth.
+    lfo: Vec<f32>,
 }
 
-impl Default for ScratchBuffers {
-    fn default() -> Self {
+impl ScratchBuffers {
+    fn new(max_block_size: usize) -> Self {
+        let max_oversampled_block_size = max_block_size * MAX_OVERSAMPLING_TIMES;
+
         Self {
-            gain: [0.0; MAX_OVERSAMPLED_BLOCK_SIZE],
-            drive: [0.0; MAX_OVERSAMPLED_BLOCK_SIZE],
+            input_trim: vec![0.0; max_oversampled_block_size],
+            gain: vec![0.0; max_oversampled_block_size],
+            drive: vec![0.0; max_oversampled_block_size],
+            clip_ceiling: vec![0.0; max_oversampled_block_size],
+            bias: vec![0.0; max_oversampled_block_size],
+            rectifier_mix: vec![0.0; max_oversampled_block_size],
+            drive_mix: vec![0.0; max_oversampled_block_size],
+            shaper_morph: vec![0.0; max_oversampled_block_size],
+            low_boost: vec![0.0; max_oversampled_block_size],
+            mid_boost: vec![0.0; max_oversampled_block_size],
+            high_boost: vec![0.0; max_oversampled_block_size],
+            mix: vec![0.0; max_block_size],
+            parallel_blend: vec![0.0; max_block_size],
+            width: vec![0.0; max_block_size],
+            output_gain: vec![0.0; max_block_size],
+            dry: vec![0.0; max_block_size],
+            oversampling_fade: vec![0.0; max_block_size],
+            bypass_fade: vec![0.0; max_block_size],
+            wet_solo_fade: vec![0.0; max_block_size],
+            sidechain_envelope: vec![0.0; max_block_size],
+            lfo: vec![0.0; max_block_size],
         }
     }
 }
 
-struct Melter {
+pub struct Melter {
     params: Arc<MelterParams>,
-    oversamplers: Vec<oversampling::Lanczos3Oversampler>,
+    /// One oversampler per channel for every oversampling quality, indexed by
+    /// `OversamplingQuality::index()` and then by channel. These are all allocated up front so
+    /// switching between them doesn't allocate.
+    oversamplers: Vec<Vec<Box<dyn oversampling::Oversampler>>>,
+    /// The oversampling factor and quality currently in use. Changing these causes a click, so
+    /// the output is faded out before switching and faded back in afterwards.
+    oversampling_fader: fader::SwitchFader<(usize, oversampling::OversamplingQuality)>,
+    /// Whether the host is rendering offline, like when bouncing. The best oversampling settings
+    /// are used then, regardless of the parameters.
+    offline_rendering: bool,
+    /// Crossfades between the processed and the dry signal when toggling bypass.
+    bypass_fader: fader::Crossfader,
+    /// Crossfades between the mixed and the fully processed signal when toggling wet solo.
+    wet_solo_fader: fader::Crossfader,
     dc_blockers: Vec<filters::DCBlocker>,
+    tone_filters: Vec<filters::ToneFilter>,
+    pre_high_passes: Vec<equalization::CascadedFilter>,
+    emphasis_filters: Vec<filters::Emphasis>,
+    de_emphasis_filters: Vec<filters::Emphasis>,
+    cubic_adaas: Vec<nonlinearity::CubicADAA>,
+    slew_distortions: Vec<nonlinearity::SlewDistortion>,
+    /// Splits the signal into bands for the multiband distortion.
+    crossovers: Vec<equalization::ThreeBandCrossover>,
+    /// The stateful shapers for every band of the multiband distortion, so the bands don't share
+    /// their history with each other or with the single band shapers.
+    band_cubic_adaas: Vec<[nonlinearity::CubicADAA; NUM_DISTORTION_BANDS]>,
+    band_slew_distortions: Vec<[nonlinearity::SlewDistortion; NUM_DISTORTION_BANDS]>,
+    /// Whether the multiband distortion was used for the last block. The crossovers and the band
+    /// shapers start over from silence when it's turned on.
+    multiband_active: bool,
+    decimators: Vec<nonlinearity::Decimator>,
+    /// Delays the dry signal by the oversampling latency for the dry/wet mix.
+    dry_delays: Vec<delay::DelayLine>,
     parametric_eqs: Vec<equalization::ParametricEQ>,
-    scratch_buffers: Box<ScratchBuffers>,
+    /// The second EQ after the distortion, only used when both pre and post EQ are enabled.
+    post_eqs: Vec<equalization::ParametricEQ>,
+    /// Follows the level of the sidechain input, this is shared by all channels.
+    sidechain_follower: dynamics::EnvelopeFollower,
+    /// Follow the level going into each channel's distortion, these run at the oversampled rate.
+    drive_followers: Vec<dynamics::EnvelopeFollower>,
+    /// Add drive once the level going into each channel's distortion passes a threshold, these
+    /// also run at the oversampled rate.
+    dynamic_drives: Vec<dynamics::DynamicDrive>,
+    noise_gates: Vec<dynamics::NoiseGate>,
+    /// Keep the final output below the ceiling.
+    safety_limiters: Vec<dynamics::Limiter>,
+    /// Decorrelates the channels of the stereo layout.
+    widener: filters::Widener,
+    /// Fades the widener in when the decorrelation is turned up from zero, and out again when
+    /// it's turned back down, since switching the allpasses in or out at once would click.
+    widener_fader: fader::Crossfader,
+    lfo: modulation::Lfo,
+    /// The LFO's value since it was last advanced, and the number of samples until it's advanced
+    /// again. This carries over between blocks so the control rate doesn't depend on the block
+    /// size.
+    lfo_amount: f32,
+    lfo_samples_until_step: usize,
+    /// Publishes the output levels for the editor's meters.
+    output_meter: meter::OutputMeter,
+    /// Publishes the DC offset going into the DC blockers, which shows how asymmetric the
+    /// distortion is.
+    dc_meter: meter::DcMeter,
+    /// Collects the output for the editor's spectrum analyzer.
+    spectrum: Arc<analysis::SpectrumBuffer>,
+    scratch_buffers: ScratchBuffers,
+    /// The host's maximum buffer size. Buffers are processed in blocks of up to this size.
+    max_block_size: usize,
     sample_rate: f32,
+    /// The oversampled sample rate the filters were last set up for. Recomputing all of their
+    /// coefficients is only needed when this changes. `None` after the plugin is initialized.
+    filters_sample_rate: Option<f32>,
+    /// The latency of the oversampling settings used for the last processed buffer, in samples.
+    latency_samples: u32,
+    /// The preset selector's value during the last buffer. A preset is only recalled when the
+    /// selector changes, so the parameters can still be changed afterwards.
+    preset: i32,
+    /// The random seed parameter's value during the last buffer, for the same reason.
+    random_seed: i32,
 }
 
 impl Default for Melter {
@@ -55,25 +305,174 @@ impl Default for Melter {
         Self {
             params: Arc::new(MelterParams::default()),
             oversamplers: Vec::new(),
+            oversampling_fader: fader::SwitchFader::new(
+                (
+                    DEFAULT_OVERSAMPLING_FACTOR,
+                    oversampling::OversamplingQuality::Lanczos3,
+                ),
+                OVERSAMPLING_FADE_SAMPLES,
+            ),
+            offline_rendering: false,
+            bypass_fader: fader::Crossfader::new(false, BYPASS_FADE_SAMPLES),
+            wet_solo_fader: fader::Crossfader::new(false, BYPASS_FADE_SAMPLES),
             dc_blockers: Vec::new(),
+            tone_filters: Vec::new(),
+            pre_high_passes: Vec::new(),
+            emphasis_filters: Vec::new(),
+            de_emphasis_filters: Vec::new(),
+            cubic_adaas: Vec::new(),
+            slew_distortions: Vec::new(),
+            crossovers: Vec::new(),
+            band_cubic_adaas: Vec::new(),
+            band_slew_distortions: Vec::new(),
+            multiband_active: false,
+            decimators: Vec::new(),
+            dry_delays: Vec::new(),
             parametric_eqs: Vec::new(),
-            scratch_buffers: Box::default(),
+            post_eqs: Vec::new(),
+            sidechain_follower: dynamics::EnvelopeFollower::new(
+                44100.0,
+                SIDECHAIN_ATTACK_MS,
+                SIDECHAIN_RELEASE_MS,
+            ),
+            drive_followers: Vec::new(),
+            dynamic_drives: Vec::new(),
+            noise_gates: Vec::new(),
+            safety_limiters: Vec::new(),
+            widener: filters::Widener::new(44100.0),
+            widener_fader: fader::Crossfader::new(false, BYPASS_FADE_SAMPLES),
+            lfo: modulation::Lfo::new(44100.0),
+            lfo_amount: 0.0,
+            lfo_samples_until_step: 0,
+            output_meter: meter::OutputMeter::new(44100.0),
+            dc_meter: meter::DcMeter::new(),
+            spectrum: Arc::new(analysis::SpectrumBuffer::new(44100.0)),
+            scratch_buffers: ScratchBuffers::new(DEFAULT_MAX_BLOCK_SIZE),
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
             sample_rate: 44100.0,
+            filters_sample_rate: None,
+            latency_samples: 0,
+            preset: 0,
+            random_seed: 0,
         }
     }
 }
 
 #[derive(Params)]
 struct MelterParams {
+    // The editor's window size
+    #[cfg(feature = "gui")]
+    #[persist = "editor-state"]
+    editor_state: Arc<nih_plug_egui::EguiState>,
+
     // Pre-post equalization
     #[id = "pre_post_eq"]
     pub pre_post_eq: BoolParam,
+    #[id = "mid_side_eq"]
+    pub mid_side_eq: BoolParam,
 
-    // Distortion parameters
+    // Input stage, applied before the EQ and the distortion
+    #[id = "input_trim"]
+    pub input_trim: FloatParam,
+    #[id = "pre_hpf_hz"]
+    pub pre_hpf_hz: FloatParam,
+    // The number of biquads the pre high-pass chains together, each adds 12 dB/octave
+    #[id = "pre_hpf_slope"]
+    pub pre_hpf_slope: IntParam,
+    #[id = "gate_threshold"]
+    pub gate_threshold: FloatParam,
+    #[id = "gate_release"]
+    pub gate_release: FloatParam,
+
+    // Distortion parameters. The gain sets how hard the distortion is driven.
     #[id = "gain"]
     pub gain: FloatParam,
     #[id = "drive"]
     pub drive: FloatParam,
+    // How much the sidechain's level scales up the drive
+    #[id = "sidechain_drive"]
+    pub sidechain_drive: FloatParam,
+    // Added to the left channel's drive and subtracted from the right channel's drive, only used
+    // for stereo layouts
+    #[id = "drive_stereo_offset"]
+    pub drive_stereo_offset: FloatParam,
+    // How much the level going into the distortion adds to the drive
+    #[id = "drive_env_amount"]
+    pub drive_env_amount: FloatParam,
+    // The level above which the drive goes up like a compressor's gain reduction would, at the
+    // dynamic drive's ratio. A ratio of 1 turns this off.
+    #[id = "dynamic_drive_threshold"]
+    pub dynamic_drive_threshold: FloatParam,
+    #[id = "dynamic_drive_ratio"]
+    pub dynamic_drive_ratio: FloatParam,
+    #[id = "dynamic_drive_knee"]
+    pub dynamic_drive_knee: FloatParam,
+    // Turns the cubic distortion down as the drive goes up so the loudness stays about the same
+    #[id = "drive_compensation"]
+    pub drive_compensation: BoolParam,
+    // The distortion type is shape A, the morph crossfades from its output to shape B's output
+    #[id = "distortion_type"]
+    pub distortion_type: IntParam,
+    #[id = "distortion_type_b"]
+    pub distortion_type_b: IntParam,
+    #[id = "shaper_morph"]
+    pub shaper_morph: FloatParam,
+    #[id = "dc_corner_hz"]
+    pub dc_corner_hz: FloatParam,
+    #[id = "drive_mix"]
+    pub drive_mix: FloatParam,
+    #[id = "emphasis_db"]
+    pub emphasis_db: FloatParam,
+
+    // Tone filter after the distortion
+    #[id = "tone_cutoff"]
+    pub tone_cutoff: FloatParam,
+    #[id = "tone_resonance"]
+    pub tone_resonance: FloatParam,
+    #[id = "rectifier_mix"]
+    pub rectifier_mix: FloatParam,
+    #[id = "harmonic_order"]
+    pub harmonic_order: IntParam,
+    #[id = "slew_pos_rate"]
+    pub slew_pos_rate: FloatParam,
+    #[id = "slew_neg_rate"]
+    pub slew_neg_rate: FloatParam,
+    #[id = "clip_ceiling"]
+    pub clip_ceiling: FloatParam,
+    #[id = "bias"]
+    pub bias: FloatParam,
+
+    // Multiband distortion splits the signal into a low, a mid and a high band that are distorted
+    // separately and summed again. The band drives are added to the drive, and the band types take
+    // the place of the distortion type as every band's shape A.
+    #[id = "multiband"]
+    pub multiband: BoolParam,
+    #[id = "low_band_drive"]
+    pub low_band_drive: FloatParam,
+    #[id = "mid_band_drive"]
+    pub mid_band_drive: FloatParam,
+    #[id = "high_band_drive"]
+    pub high_band_drive: FloatParam,
+    #[id = "low_band_type"]
+    pub low_band_type: IntParam,
+    #[id = "mid_band_type"]
+    pub mid_band_type: IntParam,
+    #[id = "high_band_type"]
+    pub high_band_type: IntParam,
+    // The frequencies the bands are split at. Their ranges don't overlap, so the low crossover is
+    // always below the high crossover.
+    #[id = "crossover_low"]
+    pub crossover_low: FloatParam,
+    #[id = "crossover_high"]
+    pub crossover_high: FloatParam,
+
+    // Bit crusher
+    #[id = "bit_depth"]
+    pub bit_depth: FloatParam,
+    #[id = "bit_crush_oversampled"]
+    pub bit_crush_oversampled: BoolParam,
+    #[id = "downsample"]
+    pub downsample: FloatParam,
 
     // 3-band parametric EQ
     #[id = "low_boost"]
@@ -82,23 +481,564 @@ struct MelterParams {
     pub mid_boost: FloatParam,
     #[id = "high_boost"]
     pub high_boost: FloatParam,
+    #[id = "low_freq"]
+    pub low_freq: FloatParam,
+    #[id = "mid_freq"]
+    pub mid_freq: FloatParam,
+    #[id = "high_freq"]
+    pub high_freq: FloatParam,
+    #[id = "low_slope"]
+    pub low_slope: FloatParam,
+    #[id = "mid_q"]
+    pub mid_q: FloatParam,
+    #[id = "high_slope"]
+    pub high_slope: FloatParam,
+    #[id = "num_bands"]
+    pub num_bands: IntParam,
+    #[id = "eq_auto_gain"]
+    pub eq_auto_gain: BoolParam,
+
+    // A second 3-band EQ after the distortion. When both pre and post EQ are enabled the first EQ
+    // runs before the distortion and this one runs after it.
+    #[id = "pre_and_post_eq"]
+    pub pre_and_post_eq: BoolParam,
+    #[id = "post_low_boost"]
+    pub post_low_boost: FloatParam,
+    #[id = "post_mid_boost"]
+    pub post_mid_boost: FloatParam,
+    #[id = "post_high_boost"]
+    pub post_high_boost: FloatParam,
+    #[id = "post_low_freq"]
+    pub post_low_freq: FloatParam,
+    #[id = "post_mid_freq"]
+    pub post_mid_freq: FloatParam,
+    #[id = "post_high_freq"]
+    pub post_high_freq: FloatParam,
+    #[id = "post_low_slope"]
+    pub post_low_slope: FloatParam,
+    #[id = "post_mid_q"]
+    pub post_mid_q: FloatParam,
+    #[id = "post_high_slope"]
+    pub post_high_slope: FloatParam,
+
+    // LFO, modulates one of the destinations
+    #[id = "lfo_rate"]
+    pub lfo_rate: FloatParam,
+    // When enabled the LFO rate follows the host's tempo instead
+    #[id = "lfo_sync"]
+    pub lfo_sync: BoolParam,
+    #[id = "lfo_division"]
+    pub lfo_division: IntParam,
+    #[id = "lfo_depth"]
+    pub lfo_depth: FloatParam,
+    #[id = "lfo_waveform"]
+    pub lfo_waveform: IntParam,
+    #[id = "lfo_destination"]
+    pub lfo_destination: IntParam,
+
+    // Parallel blend between the input and the processed signal
+    #[id = "mix"]
+    pub mix: FloatParam,
+    // Series routing uses the mix, parallel routing adds the processed signal to the dry signal
+    // with the parallel blend instead
+    #[id = "routing"]
+    pub routing: IntParam,
+    #[id = "parallel_blend"]
+    pub parallel_blend: FloatParam,
+    // Temporarily outputs only the processed signal, regardless of the routing and the mix
+    #[id = "wet_solo"]
+    pub wet_solo: BoolParam,
+
+    // Stereo width of the processed signal, only used for stereo layouts
+    #[id = "width"]
+    pub width: FloatParam,
+    // Widens the stereo image by shifting the phases of the two channels apart, which unlike the
+    // width still sums to mono without cancelling out. Also only used for stereo layouts.
+    #[id = "decorrelation"]
+    pub decorrelation: FloatParam,
+
+    // Output trim, applied after all other processing
+    #[id = "output_gain"]
+    pub output_gain: FloatParam,
+    // Limits the final output to just below 0 dBFS
+    #[id = "safety_limit"]
+    pub safety_limit: BoolParam,
+
+    // Bypasses the processing while keeping the oversampling latency
+    #[id = "bypass"]
+    pub bypass: BoolParam,
 
     // Oversampling factor
     #[id = "oversampling_factor"]
     pub oversampling_factor: IntParam,
+    #[id = "oversampling_quality"]
+    pub oversampling_quality: IntParam,
+    // Picks the oversampling factor from the drive instead of the oversampling parameter
+    #[id = "auto_oversampling"]
+    pub auto_oversampling: BoolParam,
+
+    // Recalls one of the factory presets when it's changed
+    #[id = "preset"]
+    pub preset: IntParam,
+    // Randomizes the main parameters with this value as the seed when it's changed
+    #[id = "random_seed"]
+    pub random_seed: IntParam,
+}
+
+/// The factory preset selector. The first value doesn't correspond to a preset, so the parameters
+/// are left alone until a preset is picked.
+fn preset_param(default: i32) -> IntParam {
+    IntParam::new(
+        "Preset",
+        default,
+        IntRange::Linear {
+            min: 0,
+            max: presets::FACTORY_PRESETS.len() as i32,
+        },
+    )
+    .with_value_to_string(Arc::new(|value| match value {
+        0 => String::from("None"),
+        _ => presets::FACTORY_PRESETS[value as usize - 1]
+            .name
+            .to_string(),
+    }))
+}
+
+/// The seed for randomizing the main parameters. Like with the preset selector, the first value
+/// leaves the parameters alone.
+fn random_seed_param(default: i32) -> IntParam {
+    IntParam::new(
+        "Randomize",
+        default,
+        IntRange::Linear {
+            min: 0,
+            max: i32::MAX,
+        },
+    )
+    .with_value_to_string(Arc::new(|value| match value {
+        0 => String::from("Off"),
+        _ => value.to_string(),
+    }))
+}
+
+/// Set a parameter from the plugin itself instead of from the host or an editor. The smoother
+/// glides to the new value like it does for automation.
+fn set_param_value<P: ParamMut>(param: &P, value: P::Plain, sample_rate: f32) {
+    if param.set_plain_value(value) {
+        param.update_smoother(sample_rate, false);
+    }
+}
+
+/// The oversampling factor parameter, shown as the number of times the signal is oversampled.
+fn oversampling_factor_param(default: usize) -> IntParam {
+    IntParam::new(
+        "Oversampling",
+        default as i32,
+        IntRange::Linear {
+            min: 0,
+            max: MAX_OVERSAMPLING_FACTOR as i32,
+        },
+    )
+    .with_unit("x")
+    .with_value_to_string(Arc::new(|value| {
+        let oversampling_times = 2usize.pow(value as u32);
+        oversampling_times.to_string()
+    }))
+    .with_string_to_value(Arc::new(|string| {
+        let oversampling_times: usize = string.parse().ok()?;
+        Some((oversampling_times as f32).log2() as i32)
+    }))
+}
+
+/// The number of bands the main EQ processes. All of the EQ's bands are allocated up front, so
+/// this can go up to the EQ's maximum.
+fn num_bands_param(default: i32) -> IntParam {
+    IntParam::new(
+        "EQ Bands",
+        default,
+        IntRange::Linear {
+            min: 1,
+            max: equalization::MAX_BANDS as i32,
+        },
+    )
+}
+
+/// How far the drive has to move past one of the thresholds in [`auto_oversampling_factor()`]
+/// before the factor changes, as a fraction of the drive range covered by a single factor.
+/// Every change fades the output out and back in, so automating the drive around a threshold
+/// shouldn't keep switching back and forth.
+const AUTO_OVERSAMPLING_HYSTERESIS: f32 = 0.25;
+
+/// The oversampling factor used for `drive` when the factor is picked automatically. The harder
+/// the distortion is driven the more it aliases, so the factor goes up evenly with the drive, from
+/// no oversampling without any drive to the maximum factor at full drive. `current_factor` is kept
+/// for as long as the drive stays within the hysteresis around its range, except that turning the
+/// drive all the way down always turns the oversampling off.
+fn auto_oversampling_factor(drive: f32, current_factor: usize) -> usize {
+    if drive <= 0.0 {
+        return 0;
+    }
+
+    let drive_per_factor = MAX_DRIVE / MAX_OVERSAMPLING_FACTOR as f32;
+    let factor_for = |drive: f32| (drive.clamp(0.0, MAX_DRIVE) / drive_per_factor).ceil() as usize;
+    let hysteresis = AUTO_OVERSAMPLING_HYSTERESIS * drive_per_factor;
+
+    current_factor.clamp(
+        factor_for(drive - hysteresis),
+        factor_for(drive + hysteresis),
+    )
+}
+
+/// The drive added to one of the multiband distortion's bands.
+fn band_drive_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        0.0,
+        FloatRange::Linear {
+            min: -MAX_DRIVE / 2.0,
+            max: MAX_DRIVE / 2.0,
+        },
+    )
+    .with_value_to_string(formatters::v2s_f32_rounded(2))
+}
+
+/// The gain applied before the distortion, defaulting to `default_db` decibels.
+fn gain_param(default_db: f32) -> FloatParam {
+    FloatParam::new(
+        "Gain",
+        util::db_to_gain(default_db),
+        FloatRange::Skewed {
+            min: util::db_to_gain(0.0),
+            max: util::db_to_gain(30.0),
+            factor: FloatRange::gain_skew_factor(0.0, 30.0),
+        },
+    )
+    .with_smoother(SmoothingStyle::Logarithmic(50.0))
+    .with_unit(" dB")
+    .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+    .with_string_to_value(formatters::s2v_f32_gain_to_db())
+}
+
+/// The gain applied after everything else, defaulting to `default_db` decibels.
+fn output_gain_param(default_db: f32) -> FloatParam {
+    FloatParam::new(
+        "Output Gain",
+        util::db_to_gain(default_db),
+        FloatRange::Skewed {
+            min: util::db_to_gain(-24.0),
+            max: util::db_to_gain(24.0),
+            factor: FloatRange::gain_skew_factor(-24.0, 24.0),
+        },
+    )
+    .with_smoother(SmoothingStyle::Logarithmic(50.0))
+    .with_unit(" dB")
+    .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+    .with_string_to_value(formatters::s2v_f32_gain_to_db())
+}
+
+/// A parameter that selects a [`Routing`] by name.
+fn routing_param(default: Routing) -> IntParam {
+    IntParam::new(
+        "Routing",
+        Routing::ALL
+            .iter()
+            .position(|routing| *routing == default)
+            .unwrap() as i32,
+        IntRange::Linear {
+            min: 0,
+            max: Routing::ALL.len() as i32 - 1,
+        },
+    )
+    .with_value_to_string(Arc::new(|value| {
+        Routing::from_index(value).name().to_string()
+    }))
+    .with_string_to_value(Arc::new(|string| {
+        Routing::ALL
+            .iter()
+            .position(|routing| routing.name().eq_ignore_ascii_case(string.trim()))
+            .map(|index| index as i32)
+    }))
+}
+
+/// A parameter that selects one of the distortion types by name.
+fn distortion_type_param(name: &str, default: nonlinearity::DistortionType) -> IntParam {
+    IntParam::new(
+        name,
+        nonlinearity::DistortionType::ALL
+            .iter()
+            .position(|distortion_type| *distortion_type == default)
+            .unwrap() as i32,
+        IntRange::Linear {
+            min: 0,
+            max: nonlinearity::DistortionType::ALL.len() as i32 - 1,
+        },
+    )
+    .with_value_to_string(Arc::new(|value| {
+        nonlinearity::DistortionType::from_index(value)
+            .name()
+            .to_string()
+    }))
+    .with_string_to_value(Arc::new(|string| {
+        nonlinearity::DistortionType::ALL
+            .iter()
+            .position(|distortion_type| distortion_type.name().eq_ignore_ascii_case(string.trim()))
+            .map(|index| index as i32)
+    }))
+}
+
+/// Shows a Q value along with the bandwidth in octaves it corresponds to.
+fn v2s_q_with_octaves() -> Arc<dyn Fn(f32) -> String + Send + Sync> {
+    Arc::new(|value| {
+        let octaves = equalization::bandwidth_octaves_from_q(value);
+        format!("{value:.2} ({octaves:.2} oct)")
+    })
+}
+
+/// Parses either a Q value, or a bandwidth in octaves when followed by `oct`.
+#[allow(clippy::type_complexity)]
+fn s2v_q_or_octaves() -> Arc<dyn Fn(&str) -> Option<f32> + Send + Sync> {
+    Arc::new(|string| {
+        let string = string.trim();
+        match string.strip_suffix("oct") {
+            Some(octaves) => {
+                let octaves: f32 = octaves.trim().parse().ok()?;
+                Some(equalization::q_from_bandwidth_octaves(octaves))
+            }
+            None => string.split_whitespace().next()?.parse().ok(),
+        }
+    })
+}
+
+/// A boost for one of the EQ's bands, in decibels.
+fn eq_boost_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Linear {
+            min: -MAX_EQ_BOOST_DB,
+            max: MAX_EQ_BOOST_DB,
+        },
+    )
+    .with_smoother(SmoothingStyle::Linear(50.0))
+    .with_unit(" dB")
+}
+
+/// A boost for one of the post EQ's bands, in decibels.
+fn post_eq_boost_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Linear {
+            min: -MAX_POST_EQ_BOOST_DB,
+            max: MAX_POST_EQ_BOOST_DB,
+        },
+    )
+    .with_smoother(SmoothingStyle::Linear(50.0))
+    .with_unit(" dB")
+}
+
+/// A slope parameter for one of the EQs' shelves, see [`equalization::MAX_SHELF_SLOPE`].
+fn shelf_slope_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        equalization::MAX_SHELF_SLOPE,
+        FloatRange::Linear {
+            min: equalization::MIN_SHELF_SLOPE,
+            max: equalization::MAX_SHELF_SLOPE,
+        },
+    )
+    .with_smoother(SmoothingStyle::Linear(50.0))
+    .with_value_to_string(formatters::v2s_f32_rounded(2))
+}
+
+/// Set the parameters for the low, mid and high EQ bands. The boosts are passed separately since
+/// they're smoothed per sample while the other parameters are only updated once per block.
+fn set_eq_band_params(
+    eq: &mut equalization::ParametricEQ,
+    filter_params: &BlockFilterParams,
+    [low_boost, mid_boost, high_boost]: [f32; 3],
+) {
+    eq.set_band_params(
+        0,
+        filter_params.low_freq,
+        low_boost,
+        filter_params.low_slope,
+    )
+    .unwrap();
+    eq.set_band_params(1, filter_params.mid_freq, mid_boost, filter_params.mid_q)
+        .unwrap();
+    eq.set_band_params(
+        2,
+        filter_params.high_freq,
+        high_boost,
+        filter_params.high_slope,
+    )
+    .unwrap();
+}
+
+/// Set the parameters for the bands of the second EQ that runs after the distortion when both pre
+/// and post EQ are enabled. Unlike the first EQ's boosts, these are only updated once per block.
+fn set_post_eq_band_params(eq: &mut equalization::ParametricEQ, filter_params: &BlockFilterParams) {
+    eq.set_band_params(
+        0,
+        filter_params.post_low_freq,
+        filter_params.post_low_boost,
+        filter_params.post_low_slope,
+    )
+    .unwrap();
+    eq.set_band_params(
+        1,
+        filter_params.post_mid_freq,
+        filter_params.post_mid_boost,
+        filter_params.post_mid_q,
+    )
+    .unwrap();
+    eq.set_band_params(
+        2,
+        filter_params.post_high_freq,
+        filter_params.post_high_boost,
+        filter_params.post_high_slope,
+    )
+    .unwrap();
+}
+
+/// Create the main EQ with its low shelf, mid peak and high shelf bands.
+fn new_parametric_eq(sample_rate: f32) -> equalization::ParametricEQ {
+    let mut eq = equalization::ParametricEQ::new(sample_rate);
+
+    // Add the bands
+    eq.add_band(equalization::BandType::LowShelf, 100.0, 0.0, 1.0)
+        .unwrap();
+    eq.add_band(equalization::BandType::Peak, 1000.0, 0.0, 1.0)
+        .unwrap();
+    eq.add_band(equalization::BandType::HighShelf, 10000.0, 0.0, 1.0)
+        .unwrap();
+
+    // All bands are allocated up front so the number of active bands can change during
+    // processing. The bands past the first three are flat until they get their own parameters.
+    for _ in 3..equalization::MAX_BANDS {
+        eq.add_band(equalization::BandType::Peak, 1000.0, 0.0, 1.0)
+            .unwrap();
+    }
+
+    eq
+}
+
+/// Run a block of a channel through the safety limiter. Like the width, the limiter is faded out
+/// while bypassed so the bypassed signal stays untouched.
+fn apply_safety_limiter(channel: &mut [f32], limiter: &mut dynamics::Limiter, bypass_fade: &[f32]) {
+    for (sample, bypass) in channel.iter_mut().zip(bypass_fade) {
+        *sample = nonlinearity::dry_wet(limiter.process(*sample), *sample, *bypass);
+    }
+}
+
+impl MelterParams {
+    /// The output of the selected shapers for an input of `x` using the current parameter values,
+    /// morphed the same way as in the plugin. This is what the editor draws.
+    pub fn transfer_curve(&self, x: f32) -> f32 {
+        nonlinearity::dry_wet(
+            self.shape_curve(self.distortion_type.value(), x),
+            self.shape_curve(self.distortion_type_b.value(), x),
+            self.shaper_morph.value(),
+        )
+    }
+
+    /// The output of a single shaper for an input of `x`, using the current parameter values. The
+    /// slew limiter has no static transfer curve, so it's a straight line.
+    fn shape_curve(&self, distortion_type: i32, x: f32) -> f32 {
+        let drive = self.drive.value();
+        let clip_ceiling = self.clip_ceiling.value();
+        match nonlinearity::DistortionType::from_index(distortion_type) {
+            nonlinearity::DistortionType::Cubic => nonlinearity::cubic(x, drive, self.bias.value()),
+            nonlinearity::DistortionType::BridgeRectifier => nonlinearity::dry_wet(
+                x,
+                nonlinearity::bridge_rectifier(x),
+                self.rectifier_mix.value(),
+            ),
+            nonlinearity::DistortionType::Slew => x,
+            nonlinearity::DistortionType::Arctan => nonlinearity::atan_sat(x, drive),
+            nonlinearity::DistortionType::HardClip => nonlinearity::hard_clip(x, clip_ceiling),
+            nonlinearity::DistortionType::Wavefold => nonlinearity::wavefold(x, drive),
+            nonlinearity::DistortionType::Diode => nonlinearity::diode_clip(x, drive),
+            nonlinearity::DistortionType::AsymmetricDiode => {
+                nonlinearity::diode_clip_asymmetric(x, drive)
+            }
+            nonlinearity::DistortionType::Foldback => nonlinearity::foldback(x, clip_ceiling),
+            nonlinearity::DistortionType::Chebyshev => {
+                nonlinearity::chebyshev(x, self.harmonic_order.value() as u32)
+            }
+            nonlinearity::DistortionType::EvenHarmonics => nonlinearity::even_harmonics(x, drive),
+        }
+    }
+
+    /// Advance the smoothers for the per-block filter parameters past a block of `block_len`
+    /// samples. This should be called once per block, not once per channel. The EQ frequencies
+    /// are limited to `max_band_freq`.
+    fn next_block_filter_params(&self, block_len: usize, max_band_freq: f32) -> BlockFilterParams {
+        let steps = block_len as u32;
+        BlockFilterParams {
+            low_freq: self.low_freq.smoothed.next_step(steps).min(max_band_freq),
+            mid_freq: self.mid_freq.smoothed.next_step(steps).min(max_band_freq),
+            high_freq: self.high_freq.smoothed.next_step(steps).min(max_band_freq),
+            low_slope: self.low_slope.smoothed.next_step(steps),
+            mid_q: self.mid_q.smoothed.next_step(steps),
+            high_slope: self.high_slope.smoothed.next_step(steps),
+            tone_cutoff: self.tone_cutoff.smoothed.next_step(steps),
+            tone_resonance: self.tone_resonance.smoothed.next_step(steps),
+            pre_hpf_hz: self.pre_hpf_hz.smoothed.next_step(steps),
+            emphasis_db: self.emphasis_db.smoothed.next_step(steps),
+            post_low_freq: self
+                .post_low_freq
+                .smoothed
+                .next_step(steps)
+                .min(max_band_freq),
+            post_mid_freq: self
+                .post_mid_freq
+                .smoothed
+                .next_step(steps)
+                .min(max_band_freq),
+            post_high_freq: self
+                .post_high_freq
+                .smoothed
+                .next_step(steps)
+                .min(max_band_freq),
+            post_low_slope: self.post_low_slope.smoothed.next_step(steps),
+            post_mid_q: self.post_mid_q.smoothed.next_step(steps),
+            post_high_slope: self.post_high_slope.smoothed.next_step(steps),
+            post_low_boost: self.post_low_boost.smoothed.next_step(steps),
+            post_mid_boost: self.post_mid_boost.smoothed.next_step(steps),
+            post_high_boost: self.post_high_boost.smoothed.next_step(steps),
+            crossover_low: self
+                .crossover_low
+                .smoothed
+                .next_step(steps)
+                .min(max_band_freq),
+            crossover_high: self
+                .crossover_high
+                .smoothed
+                .next_step(steps)
+                .min(max_band_freq),
+            decorrelation: self.decorrelation.smoothed.next_step(steps),
+        }
+    }
 }
+
 impl Default for MelterParams {
     fn default() -> Self {
         Self {
+            #[cfg(feature = "gui")]
+            editor_state: editor::default_state(),
+
             pre_post_eq: BoolParam::new("Pre-Post EQ", false),
+            mid_side_eq: BoolParam::new("Mid/Side EQ", false),
 
-            gain: FloatParam::new(
-                "Gain",
+            input_trim: FloatParam::new(
+                "Input Trim",
                 util::db_to_gain(0.0),
                 FloatRange::Skewed {
-                    min: util::db_to_gain(0.0),
-                    max: util::db_to_gain(30.0),
-                    factor: FloatRange::gain_skew_factor(0.0, 30.0),
+                    min: util::db_to_gain(-24.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(-24.0, 24.0),
                 },
             )
             .with_smoother(SmoothingStyle::Logarithmic(50.0))
@@ -106,236 +1046,1998 @@ impl Default for MelterParams {
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
-            drive: FloatParam::new("Drive", 1.0, FloatRange::Linear { min: 0.0, max: 2.0 })
-                .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            gain: gain_param(0.0),
 
-            low_boost: FloatParam::new(
-                "Low Boost",
-                0.0,
+            // Keeps the low end out of the distortion
+            pre_hpf_hz: FloatParam::new(
+                "Pre High-Pass",
+                MIN_PRE_HPF_HZ,
+                FloatRange::Skewed {
+                    min: MIN_PRE_HPF_HZ,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            pre_hpf_slope: IntParam::new(
+                "Pre High-Pass Slope",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: equalization::MAX_CASCADE_STAGES as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| format!("{} dB/oct", (value + 1) * 12))),
+
+            gate_threshold: FloatParam::new(
+                "Gate Threshold",
+                MIN_GATE_THRESHOLD_DB,
                 FloatRange::Linear {
-                    min: -12.0,
-                    max: 12.0,
+                    min: MIN_GATE_THRESHOLD_DB,
+                    max: 0.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Linear(50.0))
-            .with_unit(" dB"),
+            .with_unit(" dB")
+            .with_value_to_string(Arc::new(|value| {
+                if value <= MIN_GATE_THRESHOLD_DB {
+                    String::from("Off")
+                } else {
+                    format!("{value:.1}")
+                }
+            })),
+            gate_release: FloatParam::new(
+                "Gate Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            drive: FloatParam::new(
+                "Drive",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: MAX_DRIVE,
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+
+            sidechain_drive: FloatParam::new(
+                "Sidechain Drive",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-            mid_boost: FloatParam::new(
-                "Mid Boost",
+            drive_stereo_offset: FloatParam::new(
+                "Drive Stereo Offset",
                 0.0,
                 FloatRange::Linear {
-                    min: -12.0,
-                    max: 12.0,
+                    min: -0.5,
+                    max: 0.5,
                 },
             )
-            .with_smoother(SmoothingStyle::Linear(50.0))
-            .with_unit(" dB"),
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
 
-            high_boost: FloatParam::new(
-                "High Boost",
+            drive_env_amount: FloatParam::new(
+                "Drive Envelope",
                 0.0,
                 FloatRange::Linear {
-                    min: -12.0,
-                    max: 12.0,
+                    min: 0.0,
+                    max: MAX_DRIVE,
                 },
             )
-            .with_smoother(SmoothingStyle::Linear(50.0))
-            .with_unit(" dB"),
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
 
-            oversampling_factor: IntParam::new(
-                "Oversampling",
-                DEFAULT_OVERSAMPLING_FACTOR as i32,
-                IntRange::Linear {
-                    min: 0,
-                    max: MAX_OVERSAMPLING_FACTOR as i32,
+            dynamic_drive_threshold: FloatParam::new(
+                "Dynamic Drive Threshold",
+                -20.0,
+                FloatRange::Linear {
+                    min: -60.0,
+                    max: 0.0,
                 },
             )
-            .with_unit("x")
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            dynamic_drive_ratio: FloatParam::new(
+                "Dynamic Drive Ratio",
+                1.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(":1")
             .with_value_to_string(Arc::new(|value| {
-                let oversampling_times = 2usize.pow(value as u32);
-                oversampling_times.to_string()
-            }))
-            .with_string_to_value(Arc::new(|string| {
-                let oversampling_times: usize = string.parse().ok()?;
-                Some((oversampling_times as f32).log2() as i32)
+                if value <= 1.0 {
+                    String::from("Off")
+                } else {
+                    format!("{value:.1}")
+                }
             })),
-        }
-    }
-}
+            dynamic_drive_knee: FloatParam::new(
+                "Dynamic Drive Knee",
+                6.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
-impl Plugin for Melter {
-    const NAME: &'static str = "Melter";
-    const VENDOR: &'static str = "SeedyROM (Zack Kollar)";
-    const URL: &'static str = env!("CARGO_PKG_HOMEPAGE");
-    const EMAIL: &'static str = "me@seedyrom.io";
+            drive_compensation: BoolParam::new("Drive Compensation", false),
 
-    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+            distortion_type: distortion_type_param(
+                "Distortion Type",
+                nonlinearity::DistortionType::Cubic,
+            ),
+            distortion_type_b: distortion_type_param(
+                "Distortion Type B",
+                nonlinearity::DistortionType::Arctan,
+            ),
+            // Crossfades from the first distortion type's output to the second one's
+            shaper_morph: FloatParam::new(
+                "Shape Morph",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
-        AudioIOLayout {
-            main_input_channels: NonZeroU32::new(2),
-            main_output_channels: NonZeroU32::new(2),
-            ..AudioIOLayout::const_default()
-        },
-        AudioIOLayout {
-            main_input_channels: NonZeroU32::new(1),
-            main_output_channels: NonZeroU32::new(1),
-            ..AudioIOLayout::const_default()
-        },
-    ];
+            // Blends the rectified signal with the unrectified signal, so the octave up character
+            // can be mixed in instead of replacing the signal
+            rectifier_mix: FloatParam::new(
+                "Rectifier Mix",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
-    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+            // The harmonic the Chebyshev shaper turns a full scale sine wave into
+            harmonic_order: IntParam::new("Harmonic", 2, IntRange::Linear { min: 1, max: 8 }),
 
-    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+            // The slew rates are in full scale units per second
+            slew_pos_rate: FloatParam::new(
+                "Slew Rise Rate",
+                nonlinearity::SlewDistortion::DEFAULT_RATE,
+                FloatRange::Skewed {
+                    min: 100.0,
+                    max: 100000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" /s")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
 
-    type SysExMessage = ();
-    type BackgroundTask = ();
+            slew_neg_rate: FloatParam::new(
+                "Slew Fall Rate",
+                nonlinearity::SlewDistortion::DEFAULT_RATE,
+                FloatRange::Skewed {
+                    min: 100.0,
+                    max: 100000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" /s")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
 
-    fn params(&self) -> Arc<dyn Params> {
-        self.params.clone()
-    }
+            // Raising the DC blocker's corner frequency also removes sub-bass created by asymmetric
+            // distortion
+            dc_corner_hz: FloatParam::new(
+                "DC Blocker Frequency",
+                filters::DCBlocker::DEFAULT_CORNER_FREQ,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 120.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
-    fn initialize(
-        &mut self,
-        audio_io_layout: &AudioIOLayout,
-        buffer_config: &BufferConfig,
-        context: &mut impl InitContext<Self>,
-    ) -> bool {
-        let sample_rate = buffer_config.sample_rate;
-        self.sample_rate = sample_rate;
+            // Blends the clean signal going into the nonlinearity with its output
+            drive_mix: FloatParam::new("Drive Mix", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
 
-        let num_channels = audio_io_layout
-            .main_output_channels
-            .expect("Plugin was initialized without any outputs")
-            .get() as usize;
+            // Boosts the highs before the distortion and cuts them by the same amount afterwards,
+            // so the distortion is brighter without changing the overall tone
+            emphasis_db: FloatParam::new(
+                "Emphasis",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 18.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB"),
 
-        self.parametric_eqs.resize_with(num_channels, || {
-            let mut eq = equalization::ParametricEQ::new(sample_rate);
+            tone_cutoff: FloatParam::new(
+                "Tone",
+                filters::ToneFilter::MAX_CUTOFF,
+                FloatRange::Skewed {
+                    min: 200.0,
+                    max: filters::ToneFilter::MAX_CUTOFF,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
 
-            // Add the bands
-            eq.add_band(equalization::BandType::LowShelf, 100.0, 0.0, 1.0)
-                .unwrap();
-            eq.add_band(equalization::BandType::Peak, 1000.0, 0.0, 1.0)
-                .unwrap();
-            eq.add_band(equalization::BandType::HighShelf, 10000.0, 0.0, 1.0)
-                .unwrap();
+            tone_resonance: FloatParam::new(
+                "Tone Resonance",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-            eq
-        });
+            clip_ceiling: FloatParam::new(
+                "Clip Ceiling",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-24.0),
+                    max: util::db_to_gain(0.0),
+                    factor: FloatRange::gain_skew_factor(-24.0, 0.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
-        self.oversamplers.resize_with(num_channels, || {
-            oversampling::Lanczos3Oversampler::new(MAX_BLOCK_SIZE, MAX_OVERSAMPLING_FACTOR)
-        });
+            // Offsets the cubic shaper for asymmetric clipping and even harmonics, the DC this
+            // introduces is removed again by the DC blocker
+            bias: FloatParam::new(
+                "Bias",
+                0.5,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
 
-        self.dc_blockers
-            .resize_with(num_channels, || filters::DCBlocker::new(sample_rate));
+            multiband: BoolParam::new("Multiband", false),
+            low_band_drive: band_drive_param("Low Band Drive"),
+            mid_band_drive: band_drive_param("Mid Band Drive"),
+            high_band_drive: band_drive_param("High Band Drive"),
+            low_band_type: distortion_type_param(
+                "Low Band Type",
+                nonlinearity::DistortionType::Cubic,
+            ),
+            mid_band_type: distortion_type_param(
+                "Mid Band Type",
+                nonlinearity::DistortionType::Cubic,
+            ),
+            high_band_type: distortion_type_param(
+                "High Band Type",
+                nonlinearity::DistortionType::Cubic,
+            ),
+            crossover_low: FloatParam::new(
+                "Low Crossover",
+                DEFAULT_CROSSOVER_LOW_HZ,
+                FloatRange::Skewed {
+                    min: 80.0,
+                    max: 800.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            crossover_high: FloatParam::new(
+                "High Crossover",
+                DEFAULT_CROSSOVER_HIGH_HZ,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 8000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
 
-        if let Some(oversampler) = self.oversamplers.first() {
-            context.set_latency_samples(
-                oversampler.latency(self.params.oversampling_factor.value() as usize),
-            );
-        }
+            // The crusher is bypassed at the maximum bit depth
+            bit_depth: FloatParam::new(
+                "Bit Depth",
+                MAX_BIT_DEPTH,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: MAX_BIT_DEPTH,
+                },
+            )
+            .with_unit(" bits")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            // Quantizing at the oversampled rate lets the oversampler filter out the aliasing, at
+            // the base rate the crusher keeps its gritty aliasing
+            bit_crush_oversampled: BoolParam::new("Crush Oversampled", true),
+            downsample: FloatParam::new(
+                "Downsample",
+                1.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 32.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit("x")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
 
-        true
+            low_boost: eq_boost_param("Low Boost", 0.0),
+
+            mid_boost: eq_boost_param("Mid Boost", 0.0),
+
+            high_boost: eq_boost_param("High Boost", 0.0),
+
+            low_freq: FloatParam::new(
+                "Low Frequency",
+                100.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            mid_freq: FloatParam::new(
+                "Mid Frequency",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 200.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            high_freq: FloatParam::new(
+                "High Frequency",
+                10000.0,
+                FloatRange::Skewed {
+                    min: 2000.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            low_slope: shelf_slope_param("Low Slope"),
+
+            // The mid band's Q also shows its bandwidth in octaves, and accepts either
+            mid_q: FloatParam::new(
+                "Mid Q",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(v2s_q_with_octaves())
+            .with_string_to_value(s2v_q_or_octaves()),
+
+            high_slope: shelf_slope_param("High Slope"),
+
+            num_bands: num_bands_param(3),
+
+            eq_auto_gain: BoolParam::new("EQ Auto Gain", false),
+
+            pre_and_post_eq: BoolParam::new("Pre and Post EQ", false),
+            post_low_boost: post_eq_boost_param("Post Low Boost", 0.0),
+            post_mid_boost: post_eq_boost_param("Post Mid Boost", 0.0),
+            post_high_boost: post_eq_boost_param("Post High Boost", 0.0),
+            post_low_freq: FloatParam::new(
+                "Post Low Frequency",
+                100.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            post_mid_freq: FloatParam::new(
+                "Post Mid Frequency",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 200.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            post_high_freq: FloatParam::new(
+                "Post High Frequency",
+                10000.0,
+                FloatRange::Skewed {
+                    min: 2000.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            post_low_slope: shelf_slope_param("Post Low Slope"),
+            post_mid_q: FloatParam::new(
+                "Post Mid Q",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(v2s_q_with_octaves())
+            .with_string_to_value(s2v_q_or_octaves()),
+            post_high_slope: shelf_slope_param("Post High Slope"),
+
+            lfo_rate: FloatParam::new(
+                "LFO Rate",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            lfo_sync: BoolParam::new("LFO Sync", false),
+            lfo_division: IntParam::new(
+                "LFO Division",
+                modulation::NoteDivision::ALL
+                    .iter()
+                    .position(|division| *division == modulation::NoteDivision::Quarter)
+                    .unwrap() as i32,
+                IntRange::Linear {
+                    min: 0,
+                    max: modulation::NoteDivision::ALL.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| {
+                modulation::NoteDivision::from_index(value)
+                    .name()
+                    .to_string()
+            }))
+            .with_string_to_value(Arc::new(|string| {
+                modulation::NoteDivision::ALL
+                    .iter()
+                    .position(|division| division.name().eq_ignore_ascii_case(string.trim()))
+                    .map(|index| index as i32)
+            })),
+            lfo_depth: FloatParam::new("LFO Depth", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            lfo_waveform: IntParam::new(
+                "LFO Waveform",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: modulation::LfoWaveform::ALL.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| {
+                modulation::LfoWaveform::from_index(value)
+                    .name()
+                    .to_string()
+            })),
+            lfo_destination: IntParam::new(
+                "LFO Destination",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: modulation::LfoDestination::ALL.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| {
+                modulation::LfoDestination::from_index(value)
+                    .name()
+                    .to_string()
+            })),
+
+            mix: FloatParam::new("Mix", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            routing: routing_param(Routing::Series),
+            parallel_blend: FloatParam::new(
+                "Parallel Blend",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_solo: BoolParam::new("Wet Solo", false),
+
+            width: FloatParam::new("Width", 1.0, FloatRange::Linear { min: 0.0, max: 2.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            decorrelation: FloatParam::new(
+                "Decorrelation",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            output_gain: output_gain_param(0.0),
+
+            safety_limit: BoolParam::new("Safety Limiter", true),
+
+            bypass: BoolParam::new("Bypass", false).make_bypass(),
+
+            oversampling_factor: oversampling_factor_param(DEFAULT_OVERSAMPLING_FACTOR),
+
+            oversampling_quality: IntParam::new(
+                "Oversampling Quality",
+                oversampling::OversamplingQuality::Lanczos3.index() as i32,
+                IntRange::Linear {
+                    min: 0,
+                    max: oversampling::OversamplingQuality::ALL.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(|value| {
+                oversampling::OversamplingQuality::from_index(value)
+                    .name()
+                    .to_string()
+            }))
+            .with_string_to_value(Arc::new(|string| {
+                oversampling::OversamplingQuality::ALL
+                    .iter()
+                    .position(|quality| quality.name().eq_ignore_ascii_case(string.trim()))
+                    .map(|index| index as i32)
+            })),
+
+            auto_oversampling: BoolParam::new("Auto Oversampling", false),
+
+            preset: preset_param(0),
+            random_seed: random_seed_param(0),
+        }
     }
+}
 
-    fn reset(&mut self) {
-        for oversampler in &mut self.oversamplers {
-            oversampler.reset();
+/// Stands in for the host while initializing the plugin without one.
+struct OfflineInitContext;
+
+impl InitContext<Melter> for OfflineInitContext {
+    fn plugin_api(&self) -> PluginApi {
+        PluginApi::Clap
+    }
+
+    fn execute(&self, _task: ()) {}
+
+    fn set_latency_samples(&self, _samples: u32) {}
+
+    fn set_current_voice_capacity(&self, _capacity: u32) {}
+}
+
+/// Wrap a set of channels in a [`Buffer`] so they can be processed in place.
+fn new_buffer(channels: &mut [Vec<f32>]) -> Buffer<'_> {
+    let num_samples = channels.first().map_or(0, |channel| channel.len());
+    let mut buffer = Buffer::default();
+    unsafe {
+        buffer.set_slices(num_samples, |output_slices| {
+            output_slices.clear();
+            output_slices.extend(channels.iter_mut().map(|channel| channel.as_mut_slice()));
+        });
+    }
+
+    buffer
+}
+
+/// Jump all smoothers to their parameter's current value. The host does this when the plugin is
+/// initialized, so instances created without one need to do it themselves. Otherwise the
+/// smoothers would all start at zero.
+fn reset_smoothers(params: &MelterParams) {
+    for param in [
+        &params.input_trim,
+        &params.pre_hpf_hz,
+        &params.gain,
+        &params.drive,
+        &params.dc_corner_hz,
+        &params.drive_mix,
+        &params.shaper_morph,
+        &params.emphasis_db,
+        &params.tone_cutoff,
+        &params.tone_resonance,
+        &params.rectifier_mix,
+        &params.slew_pos_rate,
+        &params.slew_neg_rate,
+        &params.clip_ceiling,
+        &params.bias,
+        &params.bit_depth,
+        &params.downsample,
+        &params.low_boost,
+        &params.mid_boost,
+        &params.high_boost,
+        &params.low_freq,
+        &params.mid_freq,
+        &params.high_freq,
+        &params.low_slope,
+        &params.mid_q,
+        &params.high_slope,
+        &params.post_low_boost,
+        &params.post_mid_boost,
+        &params.post_high_boost,
+        &params.post_low_freq,
+        &params.post_mid_freq,
+        &params.post_high_freq,
+        &params.post_low_slope,
+        &params.post_mid_q,
+        &params.post_high_slope,
+        &params.crossover_low,
+        &params.crossover_high,
+        &params.mix,
+        &params.parallel_blend,
+        &params.width,
+        &params.decorrelation,
+        &params.output_gain,
+    ] {
+        param.smoothed.reset(param.value());
+    }
+}
+
+impl Melter {
+    /// Create an instance that's initialized for `num_channels` channels without a host, using
+    /// the default parameters apart from the oversampling factor. This is used by the benchmarks
+    /// and isn't part of the plugin's interface.
+    #[doc(hidden)]
+    pub fn new_offline(
+        num_channels: u32,
+        sample_rate: f32,
+        max_buffer_size: u32,
+        oversampling_factor: usize,
+    ) -> Self {
+        let mut melter = Melter {
+            params: Arc::new(MelterParams {
+                oversampling_factor: oversampling_factor_param(oversampling_factor),
+                ..MelterParams::default()
+            }),
+            ..Melter::default()
+        };
+        reset_smoothers(&melter.params);
+        let audio_io_layout = AudioIOLayout {
+            main_input_channels: NonZeroU32::new(num_channels),
+            main_output_channels: NonZeroU32::new(num_channels),
+            ..AudioIOLayout::const_default()
+        };
+        let buffer_config = BufferConfig {
+            sample_rate,
+            min_buffer_size: None,
+            max_buffer_size,
+            process_mode: ProcessMode::Realtime,
+        };
+        assert!(melter.initialize(&audio_io_layout, &buffer_config, &mut OfflineInitContext));
+        melter.reset();
+
+        melter
+    }
+
+    /// Recall one of the factory presets without an editor, like the preset selector does.
+    /// Parameters that aren't part of the preset are left as they are.
+    pub fn load_preset(&mut self, index: usize) -> Result<(), &'static str> {
+        let preset = presets::FACTORY_PRESETS
+            .get(index)
+            .ok_or("Preset index out of range")?;
+        let sample_rate = self.sample_rate;
+        preset.for_each_value(
+            &self.params,
+            |param, value| set_param_value(param, value, sample_rate),
+            |param, value| set_param_value(param, value, sample_rate),
+        );
+
+        Ok(())
+    }
+
+    /// Set the main parameters to a random patch without an editor, like the random seed
+    /// parameter does. The same seed always gives the same patch.
+    pub fn randomize_params(&mut self, seed: u64) {
+        let sample_rate = self.sample_rate;
+        presets::RandomPatch::new(seed).for_each_value(
+            &self.params,
+            |param, value| set_param_value(param, value, sample_rate),
+            |param, value| set_param_value(param, value, sample_rate),
+        );
+    }
+
+    /// Run the plugin's processing on a set of channels in place, without a host or a sidechain
+    /// input. This isn't part of the plugin's interface.
+    #[doc(hidden)]
+    pub fn process_offline(&mut self, channels: &mut [Vec<f32>]) {
+        let mut buffer = new_buffer(channels);
+        let mut aux = AuxiliaryBuffers {
+            inputs: &mut [],
+            outputs: &mut [],
+        };
+        self.process_buffer(&mut buffer, &mut aux, None);
+    }
+
+    /// The latency added by the current oversampling settings in milliseconds. This is the same
+    /// latency that's reported to the host in samples.
+    pub fn latency_ms(&self) -> f32 {
+        self.latency_samples as f32 / self.sample_rate * 1000.0
+    }
+
+    /// Sweep the input from -1 to 1 in `points` evenly spaced steps through the current shaper
+    /// settings, returning `(input, output)` pairs. This uses the parameters' current values, so
+    /// it can be called at any time.
+    pub fn render_transfer_curve(&self, points: usize) -> Vec<(f32, f32)> {
+        let step = 2.0 / (points.max(2) - 1) as f32;
+        (0..points)
+            .map(|i| {
+                let x = i as f32 * step - 1.0;
+                (x, self.params.transfer_curve(x))
+            })
+            .collect()
+    }
+
+    /// The first `len` samples of the main EQ's impulse response at the plugin's sample rate with
+    /// the current parameter values, including the makeup gain when auto gain is enabled. This
+    /// uses its own EQ, so the plugin's filter states are left alone.
+    pub fn render_eq_impulse(&self, len: usize) -> Vec<f32> {
+        let max_band_freq = self.sample_rate * equalization::MAX_FREQ_RATIO;
+        let params = &self.params;
+        let mut eq = new_parametric_eq(self.sample_rate);
+        eq.set_active_bands(params.num_bands.value() as usize);
+        eq.set_band_params(
+            0,
+            params.low_freq.value().min(max_band_freq),
+            params.low_boost.value(),
+            params.low_slope.value(),
+        )
+        .unwrap();
+        eq.set_band_params(
+            1,
+            params.mid_freq.value().min(max_band_freq),
+            params.mid_boost.value(),
+            params.mid_q.value(),
+        )
+        .unwrap();
+        eq.set_band_params(
+            2,
+            params.high_freq.value().min(max_band_freq),
+            params.high_boost.value(),
+            params.high_slope.value(),
+        )
+        .unwrap();
+        let makeup_gain = if params.eq_auto_gain.value() {
+            util::db_to_gain(-eq.broadband_gain_db())
+        } else {
+            1.0
+        };
+
+        (0..len)
+            .map(|i| eq.process(if i == 0 { makeup_gain } else { 0.0 }))
+            .collect()
+    }
+
+    /// The oversampling factor and quality to process with. These come from the parameters,
+    /// except while rendering offline, where the CPU usage doesn't matter and the highest factor
+    /// with the linear phase Lanczos3 filters is always used. With auto oversampling the factor
+    /// follows the smoothed drive, so changes to it are faded like any other factor change.
+    fn target_oversampling(&self) -> (usize, oversampling::OversamplingQuality) {
+        if self.offline_rendering {
+            return (
+                MAX_OVERSAMPLING_FACTOR,
+                oversampling::OversamplingQuality::Lanczos3,
+            );
         }
+
+        let oversampling_factor = if self.params.auto_oversampling.value() {
+            auto_oversampling_factor(
+                self.params.drive.smoothed.previous_value(),
+                self.oversampling_fader.target().0,
+            )
+        } else {
+            self.params.oversampling_factor.value() as usize
+        };
+        (
+            oversampling_factor,
+            oversampling::OversamplingQuality::from_index(self.params.oversampling_quality.value()),
+        )
     }
 
-    fn process(
+    /// Switch to the target oversampling settings if the output has been faded out, and set up
+    /// everything that depends on them. This is called at the start of every block, so the switch
+    /// happens right after the fade-out. Returns the settings to process the block with.
+    fn update_oversampling(&mut self) -> (usize, oversampling::OversamplingQuality) {
+        // Changes to the oversampling factor and quality only take effect once the output has
+        // been faded out. The new oversamplers may contain stale state, so they're reset when that
+        // happens.
+        let target_oversampling = self.target_oversampling();
+        if self.oversampling_fader.update(target_oversampling) {
+            let (oversampling_factor, oversampling_quality) = self.oversampling_fader.current();
+            for oversampler in &mut self.oversamplers[oversampling_quality.index()] {
+                oversampler.reset();
+            }
+
+            // The output stays silent until the reset oversamplers' transient has passed
+            if let Some(oversampler) = self.oversamplers[oversampling_quality.index()].first() {
+                self.oversampling_fader
+                    .hold(oversampler.latency(oversampling_factor) as usize * 2);
+            }
+        }
+        let (oversampling_factor, oversampling_quality) = self.oversampling_fader.current();
+        let oversampling_times = oversampling_factor_to_times(oversampling_factor);
+
+        // The latency is reported to the host after processing, and the dry signal is delayed by
+        // the same amount
+        if let Some(oversampler) = self.oversamplers[oversampling_quality.index()].first() {
+            self.latency_samples = oversampler.latency(oversampling_factor);
+            for dry_delay in &mut self.dry_delays {
+                dry_delay.set_delay(self.latency_samples as usize);
+            }
+        }
+
+        // Set the sample rate of the filters running at the oversampled rate. This recomputes all
+        // of their coefficients, so it only happens when the oversampling factor or the host's
+        // sample rate actually changed.
+        let oversampled_sample_rate = self.sample_rate * oversampling_times as f32;
+        if self.filters_sample_rate != Some(oversampled_sample_rate) {
+            self.filters_sample_rate = Some(oversampled_sample_rate);
+            for eq in self.parametric_eqs.iter_mut().chain(&mut self.post_eqs) {
+                eq.set_sample_rate(oversampled_sample_rate);
+            }
+            for dc_blocker in &mut self.dc_blockers {
+                dc_blocker.set_sample_rate(oversampled_sample_rate);
+            }
+            for tone_filter in &mut self.tone_filters {
+                tone_filter.set_sample_rate(oversampled_sample_rate);
+            }
+            for pre_high_pass in &mut self.pre_high_passes {
+                pre_high_pass.set_sample_rate(oversampled_sample_rate);
+            }
+            for slew_distortion in &mut self.slew_distortions {
+                slew_distortion.set_sample_rate(oversampled_sample_rate);
+            }
+            for crossover in &mut self.crossovers {
+                crossover.set_sample_rate(oversampled_sample_rate);
+            }
+            for slew_distortion in self.band_slew_distortions.iter_mut().flatten() {
+                slew_distortion.set_sample_rate(oversampled_sample_rate);
+            }
+            for drive_follower in &mut self.drive_followers {
+                drive_follower.set_sample_rate(oversampled_sample_rate);
+            }
+            for dynamic_drive in &mut self.dynamic_drives {
+                dynamic_drive.set_sample_rate(oversampled_sample_rate);
+            }
+            for noise_gate in &mut self.noise_gates {
+                noise_gate.set_sample_rate(oversampled_sample_rate);
+            }
+        }
+
+        (oversampling_factor, oversampling_quality)
+    }
+
+    /// Process a buffer. This is everything `process()` does except for talking to the host, so it
+    /// can also be run without one. The latency for the current oversampling settings is stored
+    /// in `latency_samples`. `tempo` is the host's tempo in beats per minute if it reports one.
+    fn process_buffer(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
-        context: &mut impl ProcessContext<Self>,
-    ) -> ProcessStatus {
-        let oversampling_factor = self.params.oversampling_factor.value() as usize;
-        let oversampling_times = oversampling_factor_to_times(oversampling_factor);
+        aux: &mut AuxiliaryBuffers,
+        tempo: Option<f64>,
+    ) {
+        // Picking a different preset recalls it before the buffer is processed
+        let preset = self.params.preset.value();
+        if preset != self.preset {
+            self.preset = preset;
+            if preset > 0 {
+                self.load_preset(preset as usize - 1).unwrap();
+            }
+        }
+        let random_seed = self.params.random_seed.value();
+        if random_seed != self.random_seed {
+            self.random_seed = random_seed;
+            if random_seed > 0 {
+                self.randomize_params(random_seed as u64);
+            }
+        }
 
-        // If the oversampling factor parameter is changed then the host needs to know about the new
-        // latency
-        if let Some(oversampler) = self.oversamplers.first() {
-            context.set_latency_samples(oversampler.latency(oversampling_factor));
+        let dc_corner_hz = self.params.dc_corner_hz.value();
+        for dc_blocker in &mut self.dc_blockers {
+            dc_blocker.set_corner_frequency(dc_corner_hz);
+        }
+        let pre_hpf_stages = self.params.pre_hpf_slope.value() as usize + 1;
+        let gate_threshold = self.params.gate_threshold.value();
+        let gate_enabled = gate_threshold > MIN_GATE_THRESHOLD_DB;
+        let gate_release = self.params.gate_release.value();
+        for noise_gate in &mut self.noise_gates {
+            noise_gate.set_threshold(gate_threshold);
+            noise_gate.set_release(gate_release);
+        }
+        let dynamic_drive_enabled = self.params.dynamic_drive_ratio.value() > 1.0;
+        for dynamic_drive in &mut self.dynamic_drives {
+            dynamic_drive.set_params(
+                self.params.dynamic_drive_threshold.value(),
+                self.params.dynamic_drive_ratio.value(),
+                self.params.dynamic_drive_knee.value(),
+            );
         }
 
-        // Set the sample_rate of the EQs
-        for (eq, dc_blocker) in &mut self.parametric_eqs.iter_mut().zip(&mut self.dc_blockers) {
-            eq.set_sample_rate(self.sample_rate * oversampling_times as f32);
-            dc_blocker.set_sample_rate(self.sample_rate * oversampling_times as f32);
+        // In mid/side mode the first channel's EQ and distortion process the mid signal and the
+        // second channel's process the side signal. This only makes sense for stereo signals.
+        let num_channels = buffer.channels();
+        let mid_side = self.params.mid_side_eq.value() && num_channels == 2;
+        if mid_side {
+            if let [left, right] = buffer.as_slice() {
+                stereo::encode_mid_side(left, right);
+            }
         }
 
-        for (_, block) in buffer.iter_blocks(MAX_BLOCK_SIZE) {
-            let block_len = block.samples();
+        // The sidechain input is optional, without it the drive isn't modulated
+        let mut sidechain = aux.inputs.first_mut().map(|sidechain| sidechain.as_slice());
+        let sidechain_drive = self.params.sidechain_drive.value();
+        let drive_env_amount = self.params.drive_env_amount.value();
+
+        // A synced LFO falls back to the free running rate if the host doesn't report a tempo
+        let lfo_rate = match tempo {
+            Some(tempo) if self.params.lfo_sync.value() => {
+                modulation::NoteDivision::from_index(self.params.lfo_division.value())
+                    .rate_hz(tempo as f32)
+            }
+            _ => self.params.lfo_rate.value(),
+        };
+
+        let num_samples = buffer.samples();
+        let mut block_start = 0;
+        while block_start < num_samples {
+            let (oversampling_factor, oversampling_quality) = self.update_oversampling();
+            let oversampling_times = oversampling_factor_to_times(oversampling_factor);
+            let oversampled_sample_rate = self.sample_rate * oversampling_times as f32;
+            // The band frequencies need to stay below Nyquist, or the filters become unstable
+            let max_band_freq = oversampled_sample_rate * equalization::MAX_FREQ_RATIO;
+
+            // While fading out for a switch the block ends as soon as the output is silent, so the
+            // next block can switch right away
+            let block_len = (num_samples - block_start).min(self.max_block_size).min(
+                self.oversampling_fader
+                    .samples_until_switch()
+                    .unwrap_or(usize::MAX),
+            );
             let upsampled_block_len = block_len * oversampling_times;
 
             // Get the params for this block
             let pre_post_eq = self.params.pre_post_eq.value();
+            let pre_and_post_eq = self.params.pre_and_post_eq.value();
+            let distortion_type =
+                nonlinearity::DistortionType::from_index(self.params.distortion_type.value());
+            let distortion_type_b =
+                nonlinearity::DistortionType::from_index(self.params.distortion_type_b.value());
+            let num_bands = self.params.num_bands.value() as usize;
+            let eq_auto_gain = self.params.eq_auto_gain.value();
+            let bypass = self.params.bypass.value();
+            let safety_limit = self.params.safety_limit.value();
+
+            // The LFO is advanced every `CONTROL_BLOCK_SIZE` samples, independently of the
+            // block size. The modulation is added on top of the smoothed parameter values, so it
+            // doesn't interfere with the host's automation.
+            let lfo_destination = if self.params.lfo_depth.value() > 0.0 {
+                Some(modulation::LfoDestination::from_index(
+                    self.params.lfo_destination.value(),
+                ))
+            } else {
+                None
+            };
+            let lfo_waveform =
+                modulation::LfoWaveform::from_index(self.params.lfo_waveform.value());
+            let lfo_depth = self.params.lfo_depth.value();
+            for amount in &mut self.scratch_buffers.lfo[..block_len] {
+                if self.lfo_samples_until_step == 0 {
+                    self.lfo_amount =
+                        self.lfo
+                            .next_block(lfo_waveform, lfo_rate, CONTROL_BLOCK_SIZE)
+                            * lfo_depth;
+                    self.lfo_samples_until_step = CONTROL_BLOCK_SIZE;
+                }
+                self.lfo_samples_until_step -= 1;
+                *amount = self.lfo_amount;
+            }
+            let lfo = &self.scratch_buffers.lfo;
+            let lfo_moves_filters = matches!(
+                lfo_destination,
+                Some(modulation::LfoDestination::MidFreq | modulation::LfoDestination::ToneCutoff)
+            );
+
+            // The filters start the block at the LFO's current value, and are updated again at
+            // every step of the LFO within the block
+            let unmodulated_filter_params = self
+                .params
+                .next_block_filter_params(block_len, max_band_freq);
+            let filter_params =
+                unmodulated_filter_params.with_lfo(lfo_destination, lfo[0], max_band_freq);
+            let eq_boosts_smoothing = self.params.low_boost.smoothed.is_smoothing()
+                || self.params.mid_boost.smoothed.is_smoothing()
+                || self.params.high_boost.smoothed.is_smoothing();
+            let low_boost = param_next_block!(self, low_boost, upsampled_block_len);
+            let mid_boost = param_next_block!(self, mid_boost, upsampled_block_len);
+            let high_boost = param_next_block!(self, high_boost, upsampled_block_len);
+            let input_trim = param_next_block!(self, input_trim, upsampled_block_len);
             let gain = param_next_block!(self, gain, upsampled_block_len);
             let drive = param_next_block!(self, drive, upsampled_block_len);
 
-            // Apply the EQ params
-            for (channel_num, block_channel) in block.into_iter().enumerate() {
+            if lfo_destination == Some(modulation::LfoDestination::Drive) {
+                for (sample_idx, drive) in drive[..upsampled_block_len].iter_mut().enumerate() {
+                    let lfo_drive = lfo[sample_idx / oversampling_times] * MAX_DRIVE / 2.0;
+                    *drive = (*drive + lfo_drive).clamp(0.0, MAX_DRIVE);
+                }
+            }
+
+            // The sidechain's level scales the drive, so feeding it a kick makes the distortion
+            // pump along with it
+            if let Some(sidechain) = &mut sidechain {
+                let envelope = &mut self.scratch_buffers.sidechain_envelope;
+                for (sample_idx, envelope) in envelope[..block_len].iter_mut().enumerate() {
+                    let level = sidechain
+                        .iter()
+                        .map(|channel| channel[block_start + sample_idx].abs())
+                        .fold(0.0f32, f32::max);
+                    *envelope = self.sidechain_follower.process(level);
+                }
+
+                if sidechain_drive > 0.0 {
+                    for (sample_idx, drive) in drive[..upsampled_block_len].iter_mut().enumerate() {
+                        let envelope = envelope[sample_idx / oversampling_times];
+                        *drive = (*drive * (1.0 + sidechain_drive * envelope)).min(MAX_DRIVE);
+                    }
+                }
+            }
+
+            let drive_compensation = self.params.drive_compensation.value();
+            let drive_stereo_offset = if num_channels == 2 {
+                self.params.drive_stereo_offset.value()
+            } else {
+                0.0
+            };
+            let clip_ceiling = param_next_block!(self, clip_ceiling, upsampled_block_len);
+            let bias = param_next_block!(self, bias, upsampled_block_len);
+            let routing = Routing::from_index(self.params.routing.value());
+            let mix = param_next_block!(self, mix, block_len);
+            let parallel_blend = param_next_block!(self, parallel_blend, block_len);
+            let width = param_next_block!(self, width, block_len);
+            let output_gain = param_next_block!(self, output_gain, block_len);
+
+            // The fade gains are the same for every channel
+            let oversampling_fading = self.oversampling_fader.is_fading();
+            if oversampling_fading {
+                for gain in &mut self.scratch_buffers.oversampling_fade[..block_len] {
+                    *gain = self.oversampling_fader.next_gain();
+                }
+            }
+            let oversampling_fade = &self.scratch_buffers.oversampling_fade;
+
+            // Everything is still processed while bypassed so the smoothers and filters don't
+            // jump when bypass is turned off again, the processed output is just crossfaded
+            // with the dry signal
+            let bypass_fading = self.bypass_fader.is_fading(bypass);
+            if bypass_fading {
+                for amount in &mut self.scratch_buffers.bypass_fade[..block_len] {
+                    *amount = self.bypass_fader.next(bypass);
+                }
+            } else {
+                self.scratch_buffers.bypass_fade[..block_len].fill(if bypass { 1.0 } else { 0.0 });
+            }
+            let bypass_fade = &self.scratch_buffers.bypass_fade;
+
+            // Wet solo overrides the mix and the parallel blend without touching them, so the
+            // blend is back to where it was when solo is turned off again
+            let wet_solo = self.params.wet_solo.value();
+            if self.wet_solo_fader.is_fading(wet_solo) {
+                for amount in &mut self.scratch_buffers.wet_solo_fade[..block_len] {
+                    *amount = self.wet_solo_fader.next(wet_solo);
+                }
+            } else {
+                self.scratch_buffers.wet_solo_fade[..block_len].fill(if wet_solo {
+                    1.0
+                } else {
+                    0.0
+                });
+            }
+            let wet_solo_fade = &self.scratch_buffers.wet_solo_fade;
+            let rectifier_mix = param_next_block!(self, rectifier_mix, upsampled_block_len);
+            let drive_mix = param_next_block!(self, drive_mix, upsampled_block_len);
+            let shaper_morph = param_next_block!(self, shaper_morph, upsampled_block_len);
+            let bit_depth = self.params.bit_depth.value();
+            let bit_crush = bit_depth < MAX_BIT_DEPTH;
+            let bit_crush_oversampled = self.params.bit_crush_oversampled.value();
+            let downsample = self.params.downsample.value();
+            let slew_pos_rate = self.params.slew_pos_rate.value();
+            let harmonic_order = self.params.harmonic_order.value() as u32;
+            let slew_neg_rate = self.params.slew_neg_rate.value();
+            let multiband = self.params.multiband.value();
+            let band_drive_offsets = [
+                self.params.low_band_drive.value(),
+                self.params.mid_band_drive.value(),
+                self.params.high_band_drive.value(),
+            ];
+            let band_distortion_types = [
+                self.params.low_band_type.value(),
+                self.params.mid_band_type.value(),
+                self.params.high_band_type.value(),
+            ]
+            .map(nonlinearity::DistortionType::from_index);
+
+            // Set the EQ band params. Normally the coefficients glide to their new values over
+            // the course of this block. While the boosts are being automated the coefficients are
+            // instead updated for every sample so the boosts follow the automation exactly. Every
+            // channel uses the same parameters, so the per-block coefficients are only computed
+            // for the first channel's EQs and the other channels copy them.
+            let eq_smoothing_samples = if eq_boosts_smoothing {
+                0
+            } else if lfo_destination == Some(modulation::LfoDestination::MidFreq) {
+                // The mid band glides to every new step of the LFO
+                upsampled_block_len.min(CONTROL_BLOCK_SIZE * oversampling_times)
+            } else {
+                upsampled_block_len
+            };
+            for eq in self.parametric_eqs.iter_mut().chain(&mut self.post_eqs) {
+                eq.set_smoothing_samples(eq_smoothing_samples);
+            }
+            for eq in &mut self.parametric_eqs {
+                eq.set_active_bands(num_bands);
+            }
+            let mut eq_makeup_gain = 1.0;
+            if let Some((first_eq, other_eqs)) = self.parametric_eqs.split_first_mut() {
+                set_eq_band_params(
+                    first_eq,
+                    &filter_params,
+                    [low_boost[0], mid_boost[0], high_boost[0]],
+                );
+                for eq in other_eqs {
+                    eq.copy_params_from(first_eq);
+                }
+
+                // Compensates for the EQ's boosts and cuts so they don't change how hard the
+                // distortion is driven
+                if eq_auto_gain {
+                    eq_makeup_gain = util::db_to_gain(-first_eq.broadband_gain_db());
+                }
+            }
+            let mut post_eq_makeup_gain = 1.0;
+            if pre_and_post_eq {
+                if let Some((first_eq, other_eqs)) = self.post_eqs.split_first_mut() {
+                    set_post_eq_band_params(first_eq, &filter_params);
+                    for eq in other_eqs {
+                        eq.copy_params_from(first_eq);
+                    }
+
+                    if eq_auto_gain {
+                        post_eq_makeup_gain = util::db_to_gain(-first_eq.broadband_gain_db());
+                    }
+                }
+            }
+
+            // The other filters' parameters are also the same for every channel
+            for tone_filter in &mut self.tone_filters {
+                tone_filter.set_params(filter_params.tone_cutoff, filter_params.tone_resonance);
+            }
+            for pre_high_pass in &mut self.pre_high_passes {
+                pre_high_pass.set_freq(filter_params.pre_hpf_hz);
+                pre_high_pass.set_num_stages(pre_hpf_stages).unwrap();
+            }
+            for emphasis in &mut self.emphasis_filters {
+                emphasis.set_params(oversampled_sample_rate, filter_params.emphasis_db);
+            }
+            for de_emphasis in &mut self.de_emphasis_filters {
+                de_emphasis.set_params(oversampled_sample_rate, filter_params.emphasis_db);
+            }
+            for slew_distortion in self
+                .slew_distortions
+                .iter_mut()
+                .chain(self.band_slew_distortions.iter_mut().flatten())
+            {
+                slew_distortion.set_pos_rate(slew_pos_rate);
+                slew_distortion.set_neg_rate(slew_neg_rate);
+            }
+
+            for crossover in &mut self.crossovers {
+                crossover
+                    .set_frequencies(filter_params.crossover_low, filter_params.crossover_high);
+            }
+
+            // The crossovers and the band shapers start over from silence when the multiband
+            // distortion is turned on, instead of picking up where they were when it was last used
+            if multiband && !self.multiband_active {
+                for crossover in &mut self.crossovers {
+                    crossover.reset();
+                }
+                for cubic_adaa in self.band_cubic_adaas.iter_mut().flatten() {
+                    cubic_adaa.reset();
+                }
+                for slew_distortion in self.band_slew_distortions.iter_mut().flatten() {
+                    slew_distortion.reset();
+                }
+            }
+            self.multiband_active = multiband;
+
+            // The processed channels are kept around for the stereo width stage
+            let mut stereo_pair: [Option<&mut [f32]>; 2] = [None, None];
+
+            for (channel_num, channel) in buffer.as_slice().iter_mut().enumerate() {
+                let block_channel = &mut channel[block_start..block_start + block_len];
                 let eq = &mut self.parametric_eqs[channel_num];
-                let oversampler = &mut self.oversamplers[channel_num];
+                let post_eq = &mut self.post_eqs[channel_num];
+                let oversampler = &mut self.oversamplers[oversampling_quality.index()][channel_num];
                 let dc_blocker = &mut self.dc_blockers[channel_num];
+                let tone_filter = &mut self.tone_filters[channel_num];
+                let pre_high_pass = &mut self.pre_high_passes[channel_num];
+                let emphasis = &mut self.emphasis_filters[channel_num];
+                let de_emphasis = &mut self.de_emphasis_filters[channel_num];
+                let cubic_adaa = &mut self.cubic_adaas[channel_num];
+                let slew_distortion = &mut self.slew_distortions[channel_num];
+                let crossover = &mut self.crossovers[channel_num];
+                let band_cubic_adaas = &mut self.band_cubic_adaas[channel_num];
+                let band_slew_distortions = &mut self.band_slew_distortions[channel_num];
+                let decimator = &mut self.decimators[channel_num];
+                let drive_follower = &mut self.drive_followers[channel_num];
+                let dynamic_drive = &mut self.dynamic_drives[channel_num];
+                let noise_gate = &mut self.noise_gates[channel_num];
+                let dry_delay = &mut self.dry_delays[channel_num];
+
+                // Driving the two channels of a stereo pair slightly differently makes the
+                // distortion sound wider
+                let channel_drive_offset = match channel_num {
+                    0 => drive_stereo_offset,
+                    1 => -drive_stereo_offset,
+                    _ => 0.0,
+                };
 
-                // Set the EQ band params
-                let low_boost = self.params.low_boost.smoothed.next();
-                let mid_boost = self.params.mid_boost.smoothed.next();
-                let high_boost = self.params.high_boost.smoothed.next();
-                eq.set_band_params(0, 100.0, low_boost, 0.5).unwrap();
-                eq.set_band_params(1, 1000.0, mid_boost, 1.0).unwrap();
-                eq.set_band_params(2, 10000.0, high_boost, 0.5).unwrap();
+                // This only changes once per block, which is fine since it's a slow level
+                // correction. The multiband distortion's bands each get their own.
+                let compensation = |band_drive_offset: f32| {
+                    if drive_compensation {
+                        nonlinearity::cubic_drive_compensation(
+                            (drive[0] + channel_drive_offset + band_drive_offset)
+                                .clamp(0.0, MAX_DRIVE),
+                        )
+                    } else {
+                        1.0
+                    }
+                };
+                let band_drive_compensations = band_drive_offsets.map(compensation);
+                let drive_compensation = compensation(0.0);
+
+                // Keep a latency compensated copy of the input for the dry/wet mix. Non-finite
+                // input samples are left out, since they'd otherwise end up in the output even when
+                // fully wet.
+                let dry = &mut self.scratch_buffers.dry;
+                for (dry_sample, sample) in dry.iter_mut().zip(block_channel.iter()) {
+                    *dry_sample = dry_delay.process(if sample.is_finite() { *sample } else { 0.0 });
+                }
 
-                oversampler.process(block_channel, oversampling_factor, |upsampled| {
+                let dc_meter = &mut self.dc_meter;
+                let mut non_finite = false;
+                let mut lfo_filter_params = filter_params;
+                let mut process_upsampled = |upsampled: &mut [f32]| {
                     for (sample_idx, sample) in upsampled.iter_mut().enumerate() {
                         // Get the gain and drive for this sample
+                        let _input_trim = input_trim[sample_idx];
                         let _gain = gain[sample_idx];
-                        let _drive = drive[sample_idx];
+                        let mut _drive =
+                            (drive[sample_idx] + channel_drive_offset).clamp(0.0, MAX_DRIVE);
+                        let _clip_ceiling = clip_ceiling[sample_idx];
+                        let _bias = bias[sample_idx];
+                        let _rectifier_mix = rectifier_mix[sample_idx];
+                        let _drive_mix = drive_mix[sample_idx];
+                        let _shaper_morph = shaper_morph[sample_idx];
+
+                        // The LFO's value only changes at its steps, that's when the filters it
+                        // modulates are updated
+                        let lfo_idx = sample_idx / oversampling_times;
+                        if lfo_moves_filters
+                            && sample_idx % oversampling_times == 0
+                            && lfo_idx > 0
+                            && lfo[lfo_idx] != lfo[lfo_idx - 1]
+                        {
+                            lfo_filter_params = unmodulated_filter_params.with_lfo(
+                                lfo_destination,
+                                lfo[lfo_idx],
+                                max_band_freq,
+                            );
+                            tone_filter.set_params(
+                                lfo_filter_params.tone_cutoff,
+                                lfo_filter_params.tone_resonance,
+                            );
+                            if !eq_boosts_smoothing {
+                                set_eq_band_params(
+                                    eq,
+                                    &lfo_filter_params,
+                                    [low_boost[0], mid_boost[0], high_boost[0]],
+                                );
+                            }
+                        }
+
+                        // The coefficients for the automated boosts are computed as they're
+                        // needed, so they don't have to be stored for the whole block
+                        if eq_boosts_smoothing {
+                            set_eq_band_params(
+                                eq,
+                                &lfo_filter_params,
+                                [
+                                    low_boost[sample_idx],
+                                    mid_boost[sample_idx],
+                                    high_boost[sample_idx],
+                                ],
+                            );
+                        }
+
+                        // Input stage: trim the level and keep the low end out of the
+                        // distortion
+                        *sample *= _input_trim;
+                        *sample = pre_high_pass.process(*sample);
+
+                        // Gate the noise floor before the drive stage brings it up
+                        if gate_enabled {
+                            *sample = noise_gate.process(*sample);
+                        }
+
+                        // Apply pre EQ
+                        if pre_post_eq || pre_and_post_eq {
+                            *sample = eq.process(*sample) * eq_makeup_gain;
+                        }
 
-                        // Apply the gain
+                        // Drive stage: the gain drives the distortion, with the highs boosted
+                        // going in and cut again coming out
                         *sample *= _gain;
+                        *sample = emphasis.process(*sample);
+
+                        // Louder passages drive the distortion harder. The follower always runs
+                        // so it doesn't start from silence when the amount is turned up.
+                        let envelope = drive_follower.process(*sample);
+                        if drive_env_amount > 0.0 {
+                            _drive = (_drive + drive_env_amount * envelope).min(MAX_DRIVE);
+                        }
 
-                        // // Apply pre EQ
-                        if pre_post_eq {
-                            *sample = eq.process(*sample);
+                        // Past the threshold the drive also goes up with the level, like a
+                        // compressor feeding the distortion
+                        let dynamic_drive_db = dynamic_drive.process(*sample);
+                        if dynamic_drive_enabled {
+                            _drive = (_drive + dynamic_drive_db / DB_PER_DRIVE).min(MAX_DRIVE);
                         }
 
-                        // Apply the cubic non-linearity
-                        *sample = nonlinearity::cubic(*sample, _drive, 0.5);
+                        // Apply the selected non-linearities. The bridge rectifier, the hard
+                        // clipper, foldback, Chebyshev and the slew limiter have no drive, they're
+                        // driven by the gain alone. The clip ceiling is also used as the foldback
+                        // threshold.
+                        let shape = |distortion_type: nonlinearity::DistortionType,
+                                     input: f32,
+                                     drive: f32,
+                                     drive_compensation: f32,
+                                     cubic_adaa: &mut nonlinearity::CubicADAA,
+                                     slew_distortion: &mut nonlinearity::SlewDistortion| {
+                            let mut shape_with = |distortion_type| match distortion_type {
+                                nonlinearity::DistortionType::Cubic => {
+                                    cubic_adaa.process(input, drive, _bias) * drive_compensation
+                                }
+                                nonlinearity::DistortionType::BridgeRectifier => {
+                                    let rectified = nonlinearity::bridge_rectifier(input);
+                                    nonlinearity::dry_wet(input, rectified, _rectifier_mix)
+                                }
+                                nonlinearity::DistortionType::Slew => {
+                                    slew_distortion.process(input)
+                                }
+                                nonlinearity::DistortionType::Arctan => {
+                                    nonlinearity::atan_sat(input, drive)
+                                }
+                                nonlinearity::DistortionType::HardClip => {
+                                    nonlinearity::hard_clip(input, _clip_ceiling)
+                                }
+                                nonlinearity::DistortionType::Foldback => {
+                                    nonlinearity::foldback(input, _clip_ceiling)
+                                }
+                                nonlinearity::DistortionType::Chebyshev => {
+                                    nonlinearity::chebyshev(input, harmonic_order)
+                                }
+                                nonlinearity::DistortionType::Wavefold => {
+                                    nonlinearity::wavefold(input, drive)
+                                }
+                                nonlinearity::DistortionType::Diode => {
+                                    nonlinearity::diode_clip(input, drive)
+                                }
+                                nonlinearity::DistortionType::AsymmetricDiode => {
+                                    nonlinearity::diode_clip_asymmetric(input, drive)
+                                }
+                                nonlinearity::DistortionType::EvenHarmonics => {
+                                    nonlinearity::even_harmonics(input, drive)
+                                }
+                            };
+
+                            // Morph from shape A to shape B. Shape B is only computed while it's
+                            // audible, and when both shapes are the same their state is shared so
+                            // the shaper only runs once.
+                            let wet_a = shape_with(distortion_type);
+                            if _shaper_morph > 0.0 && distortion_type_b != distortion_type {
+                                nonlinearity::dry_wet(
+                                    wet_a,
+                                    shape_with(distortion_type_b),
+                                    _shaper_morph,
+                                )
+                            } else {
+                                wet_a
+                            }
+                        };
+
+                        // The multiband distortion distorts every band with its own shaper, drive
+                        // and shaper state, and sums the bands again afterwards
+                        let dry = *sample;
+                        let wet = if multiband {
+                            let mut wet = 0.0;
+                            for (band, input) in crossover.process(dry).into_iter().enumerate() {
+                                wet += shape(
+                                    band_distortion_types[band],
+                                    input,
+                                    (_drive + band_drive_offsets[band]).clamp(0.0, MAX_DRIVE),
+                                    band_drive_compensations[band],
+                                    &mut band_cubic_adaas[band],
+                                    &mut band_slew_distortions[band],
+                                );
+                            }
+                            wet
+                        } else {
+                            shape(
+                                distortion_type,
+                                dry,
+                                _drive,
+                                drive_compensation,
+                                cubic_adaa,
+                                slew_distortion,
+                            )
+                        };
+                        *sample = nonlinearity::dry_wet(dry, wet, _drive_mix);
+
+                        // Cut the boosted highs again by the same amount
+                        *sample = de_emphasis.process(*sample);
 
                         // Apply the DC blocker, using the this nice magic coefficient!
+                        dc_meter.add(*sample);
                         *sample = dc_blocker.process(*sample);
 
+                        // Apply the tone filter to tame the harmonics from the distortion
+                        *sample = tone_filter.process(*sample);
+
+                        if bit_crush && bit_crush_oversampled {
+                            *sample = nonlinearity::bit_crush(*sample, bit_depth);
+                        }
+
                         // // Apply post EQ
-                        if !pre_post_eq {
-                            *sample = eq.process(*sample);
+                        if pre_and_post_eq {
+                            *sample = post_eq.process(*sample) * post_eq_makeup_gain;
+                        } else if !pre_post_eq {
+                            *sample = eq.process(*sample) * eq_makeup_gain;
+                        }
+
+                        // A NaN or an infinity would get stuck in the filters' feedback and mute
+                        // the channel for good, so it's replaced with silence instead
+                        if !sample.is_finite() {
+                            *sample = 0.0;
+                            non_finite = true;
                         }
                     }
-                });
-            }
-        }
+                };
 
-        ProcessStatus::Normal
-    }
-}
+                // Without oversampling the block is processed directly, so this path never adds
+                // any latency or filtering
+                if oversampling_factor == 0 {
+                    process_upsampled(block_channel);
+                } else {
+                    oversampler.process(block_channel, oversampling_factor, &mut process_upsampled);
+                }
 
-impl ClapPlugin for Melter {
-    const CLAP_ID: &'static str = "com.seedyrom.melter";
-    const CLAP_DESCRIPTION: Option<&'static str> = Some("A distortion plugin for fun times!");
-    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
-    const CLAP_SUPPORT_URL: Option<&'static str> = None;
-    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect, ClapFeature::Stereo];
-}
+                // The bad sample may already be in the filters' state, so the channel starts over
+                // from silence
+                if non_finite {
+                    oversampler.reset();
+                    pre_high_pass.reset();
+                    noise_gate.reset();
+                    eq.reset();
+                    emphasis.reset();
+                    drive_follower.reset();
+                    dynamic_drive.reset();
+                    cubic_adaa.reset();
+                    slew_distortion.reset();
+                    crossover.reset();
+                    for cubic_adaa in band_cubic_adaas.iter_mut() {
+                        cubic_adaa.reset();
+                    }
+                    for slew_distortion in band_slew_distortions.iter_mut() {
+                        slew_distortion.reset();
+                    }
+                    de_emphasis.reset();
+                    dc_blocker.reset();
+                    tone_filter.reset();
+                    post_eq.reset();
+                }
 
-impl Vst3Plugin for Melter {
-    const VST3_CLASS_ID: [u8; 16] = *b"SeedyROMMelter!!";
-    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
-        &[Vst3SubCategory::Fx, Vst3SubCategory::Distortion];
-}
+                // The decimator and the base rate crusher come last, after the signal has been
+                // downsampled, so their aliasing isn't filtered out
+                for (sample_idx, sample) in block_channel.iter_mut().enumerate() {
+                    *sample = decimator.process(*sample, downsample);
+
+                    if bit_crush && !bit_crush_oversampled {
+                        *sample = nonlinearity::bit_crush(*sample, bit_depth);
+                    }
+
+                    // Output stage: blend with the dry signal and trim the level
+                    let blended = match routing {
+                        Routing::Series => {
+                            nonlinearity::dry_wet(dry[sample_idx], *sample, mix[sample_idx])
+                        }
+                        Routing::Parallel => dry[sample_idx] + *sample * parallel_blend[sample_idx],
+                    };
+                    *sample = nonlinearity::dry_wet(blended, *sample, wet_solo_fade[sample_idx]);
+                    *sample *= output_gain[sample_idx];
+
+                    if oversampling_fading {
+                        *sample *= oversampling_fade[sample_idx];
+                    }
+
+                    *sample =
+                        nonlinearity::dry_wet(*sample, dry[sample_idx], bypass_fade[sample_idx]);
+                }
+
+                // The stereo pair is only limited after the stereo width stage
+                if num_channels == 2 {
+                    stereo_pair[channel_num] = Some(block_channel);
+                } else if safety_limit {
+                    apply_safety_limiter(
+                        block_channel,
+                        &mut self.safety_limiters[channel_num],
+                        bypass_fade,
+                    );
+                }
+            }
+
+            // The width is faded back to 100% while bypassed so the bypassed signal stays
+            // untouched
+            if let [Some(left), Some(right)] = stereo_pair {
+                for (width, bypass) in width.iter_mut().zip(bypass_fade.iter()).take(block_len) {
+                    *width = nonlinearity::dry_wet(*width, 1.0, *bypass);
+                }
+
+                // In mid/side mode the channels are still encoded at this point
+                if mid_side {
+                    for (side, width) in right.iter_mut().zip(width.iter()) {
+                        *side *= width;
+                    }
+                } else {
+                    stereo::apply_width(left, right, width);
+                }
+
+                if mid_side {
+                    stereo::decode_mid_side(left, right);
+                }
+
+                // The widener is faded in and out, and like the width it's also faded out while
+                // bypassed. It only runs while it can be heard, and starts over from silence the
+                // next time it's faded in.
+                let widening = filter_params.decorrelation > 0.0;
+                if widening || self.widener_fader.is_fading(false) {
+                    self.widener.set_amount(filter_params.decorrelation);
+                    for ((l, r), bypass) in left.iter_mut().zip(right.iter_mut()).zip(bypass_fade) {
+                        let (wide_left, wide_right) = self.widener.process(*l, *r);
+                        let amount = self.widener_fader.next(widening) * (1.0 - bypass);
+                        *l = nonlinearity::dry_wet(*l, wide_left, amount);
+                        *r = nonlinearity::dry_wet(*r, wide_right, amount);
+                    }
+                } else {
+                    self.widener.reset();
+                }
+
+                // The limiter comes last so nothing after it can push the output over the ceiling
+                if safety_limit {
+                    for (channel, limiter) in
+                        [left, right].into_iter().zip(&mut self.safety_limiters)
+                    {
+                        apply_safety_limiter(channel, limiter, bypass_fade);
+                    }
+                }
+            }
+
+            block_start += block_len;
+        }
+
+        self.output_meter.update(buffer.as_slice());
+        self.dc_meter.publish();
+        self.spectrum.push(buffer.as_slice());
+    }
+}
+
+impl Plugin for Melter {
+    const NAME: &'static str = "Melter";
+    const VENDOR: &'static str = "SeedyROM (Zack Kollar)";
+    const URL: &'static str = env!("CARGO_PKG_HOMEPAGE");
+    const EMAIL: &'static str = "me@seedyrom.io";
+
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        // The mid/side and stereo width features are only used for the stereo layout
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(4),
+            main_output_channels: NonZeroU32::new(4),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                layout: Some("Quad"),
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(6),
+            main_output_channels: NonZeroU32::new(6),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                layout: Some("5.1"),
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    #[cfg(feature = "gui")]
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        editor::create(
+            self.params.clone(),
+            self.output_meter.peak(),
+            self.output_meter.rms(),
+            self.dc_meter.dc(),
+            self.spectrum.clone(),
+        )
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        let sample_rate = buffer_config.sample_rate;
+        self.sample_rate = sample_rate;
+        // Channels added below start out at the base rate, so all filters are set up again
+        self.filters_sample_rate = None;
+        self.output_meter.set_sample_rate(sample_rate);
+        self.spectrum.set_sample_rate(sample_rate);
+        self.sidechain_follower.set_sample_rate(sample_rate);
+        self.widener.set_sample_rate(sample_rate);
+        self.lfo.set_sample_rate(sample_rate);
+        // The selector's value from a restored state shouldn't recall its preset over the other
+        // restored parameters
+        self.preset = self.params.preset.value();
+        self.random_seed = self.params.random_seed.value();
+
+        // Processing happens in blocks as large as the host's buffers to keep the per-block
+        // overhead down
+        let max_block_size = (buffer_config.max_buffer_size as usize).max(1);
+        self.max_block_size = max_block_size;
+        self.scratch_buffers = ScratchBuffers::new(max_block_size);
+
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .expect("Plugin was initialized without any outputs")
+            .get() as usize;
+
+        self.parametric_eqs
+            .resize_with(num_channels, || new_parametric_eq(sample_rate));
+
+        self.post_eqs.resize_with(num_channels, || {
+            let mut eq = equalization::ParametricEQ::new(sample_rate);
+            eq.add_band(equalization::BandType::LowShelf, 100.0, 0.0, 1.0)
+                .unwrap();
+            eq.add_band(equalization::BandType::Peak, 1000.0, 0.0, 1.0)
+                .unwrap();
+            eq.add_band(equalization::BandType::HighShelf, 10000.0, 0.0, 1.0)
+                .unwrap();
+
+            eq
+        });
+
+        self.oversamplers = oversampling::OversamplingQuality::ALL
+            .iter()
+            .map(|quality| {
+                (0..num_channels)
+                    .map(|_| quality.create(max_block_size, MAX_OVERSAMPLING_FACTOR))
+                    .collect()
+            })
+            .collect();
+        self.offline_rendering = buffer_config.process_mode == ProcessMode::Offline;
+        let (oversampling_factor, oversampling_quality) = self.target_oversampling();
+        self.oversampling_fader = fader::SwitchFader::new(
+            (oversampling_factor, oversampling_quality),
+            OVERSAMPLING_FADE_SAMPLES,
+        );
+        self.bypass_fader.reset(self.params.bypass.value());
+        self.wet_solo_fader.reset(self.params.wet_solo.value());
+        self.widener_fader
+            .reset(self.params.decorrelation.value() > 0.0);
+
+        self.dc_blockers
+            .resize_with(num_channels, || filters::DCBlocker::new(sample_rate));
+
+        self.tone_filters
+            .resize_with(num_channels, || filters::ToneFilter::new(sample_rate));
+
+        self.pre_high_passes.resize_with(num_channels, || {
+            equalization::CascadedFilter::new(
+                equalization::BandType::HighPass,
+                MIN_PRE_HPF_HZ,
+                sample_rate,
+            )
+            .unwrap()
+        });
+
+        self.emphasis_filters
+            .resize_with(num_channels, || filters::Emphasis::new(sample_rate, 0.0));
+        self.de_emphasis_filters.resize_with(num_channels, || {
+            filters::Emphasis::new_inverse(sample_rate, 0.0)
+        });
+
+        self.cubic_adaas
+            .resize_with(num_channels, nonlinearity::CubicADAA::new);
+
+        self.slew_distortions.resize_with(num_channels, || {
+            nonlinearity::SlewDistortion::new(sample_rate)
+        });
+
+        self.crossovers.resize_with(num_channels, || {
+            equalization::ThreeBandCrossover::new(
+                DEFAULT_CROSSOVER_LOW_HZ,
+                DEFAULT_CROSSOVER_HIGH_HZ,
+                sample_rate,
+            )
+        });
+        self.band_cubic_adaas.resize_with(num_channels, || {
+            std::array::from_fn(|_| nonlinearity::CubicADAA::new())
+        });
+        self.band_slew_distortions.resize_with(num_channels, || {
+            std::array::from_fn(|_| nonlinearity::SlewDistortion::new(sample_rate))
+        });
+
+        self.decimators
+            .resize_with(num_channels, nonlinearity::Decimator::new);
+
+        self.drive_followers.resize_with(num_channels, || {
+            dynamics::EnvelopeFollower::new(sample_rate, DRIVE_ENV_ATTACK_MS, DRIVE_ENV_RELEASE_MS)
+        });
+        self.dynamic_drives.resize_with(num_channels, || {
+            dynamics::DynamicDrive::new(
+                sample_rate,
+                DYNAMIC_DRIVE_ATTACK_MS,
+                DYNAMIC_DRIVE_RELEASE_MS,
+            )
+        });
+
+        self.safety_limiters.resize_with(num_channels, || {
+            dynamics::Limiter::new(
+                sample_rate,
+                SAFETY_LIMITER_CEILING_DB,
+                SAFETY_LIMITER_RELEASE_MS,
+            )
+        });
+        for limiter in &mut self.safety_limiters {
+            limiter.set_sample_rate(sample_rate);
+        }
+
+        self.noise_gates.resize_with(num_channels, || {
+            dynamics::NoiseGate::new(
+                sample_rate,
+                MIN_GATE_THRESHOLD_DB,
+                GATE_ATTACK_MS,
+                self.params.gate_release.value(),
+                GATE_HOLD_MS,
+            )
+        });
+
+        // The dry signal needs to be delayed by up to the largest latency of any of the
+        // oversamplers
+        let max_latency = self
+            .oversamplers
+            .iter()
+            .filter_map(|oversamplers| oversamplers.first())
+            .map(|oversampler| oversampler.latency(MAX_OVERSAMPLING_FACTOR) as usize)
+            .max()
+            .unwrap_or(0);
+        self.dry_delays = (0..num_channels)
+            .map(|_| delay::DelayLine::new(max_latency))
+            .collect();
+
+        if let Some(oversampler) = self.oversamplers[oversampling_quality.index()].first() {
+            self.latency_samples = oversampler.latency(oversampling_factor);
+            context.set_latency_samples(self.latency_samples);
+        }
+
+        true
+    }
+
+    fn reset(&mut self) {
+        for oversampler in self.oversamplers.iter_mut().flatten() {
+            oversampler.reset();
+        }
+        for eq in self.parametric_eqs.iter_mut().chain(&mut self.post_eqs) {
+            eq.reset();
+        }
+        for dc_blocker in &mut self.dc_blockers {
+            dc_blocker.reset();
+        }
+        for tone_filter in &mut self.tone_filters {
+            tone_filter.reset();
+        }
+        for pre_high_pass in &mut self.pre_high_passes {
+            pre_high_pass.reset();
+        }
+        for emphasis in &mut self.emphasis_filters {
+            emphasis.reset();
+        }
+        for de_emphasis in &mut self.de_emphasis_filters {
+            de_emphasis.reset();
+        }
+        for cubic_adaa in &mut self.cubic_adaas {
+            cubic_adaa.reset();
+        }
+        for slew_distortion in &mut self.slew_distortions {
+            slew_distortion.reset();
+        }
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for cubic_adaa in self.band_cubic_adaas.iter_mut().flatten() {
+            cubic_adaa.reset();
+        }
+        for slew_distortion in self.band_slew_distortions.iter_mut().flatten() {
+            slew_distortion.reset();
+        }
+        for decimator in &mut self.decimators {
+            decimator.reset();
+        }
+        for dry_delay in &mut self.dry_delays {
+            dry_delay.reset();
+        }
+        self.sidechain_follower.reset();
+        self.lfo.reset();
+        self.lfo_samples_until_step = 0;
+        for drive_follower in &mut self.drive_followers {
+            drive_follower.reset();
+        }
+        for dynamic_drive in &mut self.dynamic_drives {
+            dynamic_drive.reset();
+        }
+        for noise_gate in &mut self.noise_gates {
+            noise_gate.reset();
+        }
+        for limiter in &mut self.safety_limiters {
+            limiter.reset();
+        }
+        self.widener.reset();
+        self.output_meter.reset();
+        self.dc_meter.reset();
+        self.spectrum.reset();
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        let tempo = context.transport().tempo;
+        self.process_buffer(buffer, aux, tempo);
+
+        // If the oversampling factor or quality parameters are changed then the host needs to know
+        // about the new latency
+        context.set_latency_samples(self.latency_samples);
+
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for Melter {
+    const CLAP_ID: &'static str = "com.seedyrom.melter";
+    const CLAP_DESCRIPTION: Option<&'static str> = Some("A distortion plugin for fun times!");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Stereo,
+        ClapFeature::Surround,
+    ];
+}
+
+impl Vst3Plugin for Melter {
+    const VST3_CLASS_ID: [u8; 16] = *b"SeedyROMMelter!!";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Fx, Vst3SubCategory::Distortion];
+}
 
 nih_export_clap!(Melter);
 nih_export_vst3!(Melter);
@@ -344,3 +3046,2255 @@ nih_export_vst3!(Melter);
 const fn oversampling_factor_to_times(factor: usize) -> usize {
     2usize.pow(factor as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a plugin instance that's been initialized for `num_channels` channels.
+    fn initialized_melter(num_channels: u32, sample_rate: f32, max_buffer_size: u32) -> Melter {
+        Melter::new_offline(
+            num_channels,
+            sample_rate,
+            max_buffer_size,
+            DEFAULT_OVERSAMPLING_FACTOR,
+        )
+    }
+
+    /// The same as [`Melter::process_offline()`], but with a sidechain input.
+    fn process_channels_with_sidechain(
+        melter: &mut Melter,
+        channels: &mut [Vec<f32>],
+        sidechain: &mut [Vec<f32>],
+    ) {
+        let mut buffer = new_buffer(channels);
+        let mut sidechain_buffers = [new_buffer(sidechain)];
+        let mut aux = AuxiliaryBuffers {
+            inputs: &mut sidechain_buffers,
+            outputs: &mut [],
+        };
+        melter.process_buffer(&mut buffer, &mut aux, None);
+    }
+
+    /// Parameters for clipping everything hard at the maximum gain, with the safety limiter
+    /// turned off so it doesn't hide what the clipper does.
+    fn hard_clipping_params() -> MelterParams {
+        MelterParams {
+            distortion_type: distortion_type_param(
+                "Distortion Type",
+                nonlinearity::DistortionType::HardClip,
+            ),
+            gain: gain_param(30.0),
+            safety_limit: BoolParam::new("Safety Limiter", false),
+            ..MelterParams::default()
+        }
+    }
+
+    /// The amplitude of a harmonic of a signal with the given period, measured over the last
+    /// `num_periods` periods.
+    fn harmonic_amplitude(
+        signal: &[f32],
+        period: usize,
+        num_periods: usize,
+        harmonic: usize,
+    ) -> f32 {
+        let window = &signal[signal.len() - period * num_periods..];
+        let (re, im) = window
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (i, sample)| {
+                let phase = std::f32::consts::TAU * (harmonic * i) as f32 / period as f32;
+                (re + sample * phase.cos(), im - sample * phase.sin())
+            });
+
+        2.0 * (re * re + im * im).sqrt() / window.len() as f32
+    }
+
+    /// The sum of the amplitudes of the 2nd to 5th harmonics relative to the fundamental,
+    /// measured over the last ten periods of the signal.
+    fn harmonic_content(signal: &[f32], period: usize) -> f32 {
+        let fundamental = harmonic_amplitude(signal, period, 10, 1);
+        (2..=5)
+            .map(|harmonic| harmonic_amplitude(signal, period, 10, harmonic))
+            .sum::<f32>()
+            / fundamental
+    }
+
+    mod scratch_buffers {
+        use super::*;
+
+        /// The scratch buffers and the oversamplers grow with the host's buffers, so a whole
+        /// buffer is processed as a single block.
+        #[test]
+        fn sized_from_host_buffer_size() {
+            const BUFFER_SIZE: usize = 8192;
+
+            let mut melter = initialized_melter(2, 48000.0, BUFFER_SIZE as u32);
+            assert_eq!(melter.max_block_size, BUFFER_SIZE);
+            assert_eq!(
+                melter.scratch_buffers.gain.len(),
+                BUFFER_SIZE * MAX_OVERSAMPLING_TIMES
+            );
+            assert_eq!(melter.scratch_buffers.mix.len(), BUFFER_SIZE);
+
+            let mut channels = vec![
+                (0..BUFFER_SIZE)
+                    .map(|i| (i as f32 * 0.01).sin() * 0.5)
+                    .collect::<Vec<f32>>();
+                2
+            ];
+            melter.process_offline(&mut channels);
+            assert!(channels.iter().flatten().all(|sample| sample.is_finite()));
+        }
+    }
+
+    mod channel_layouts {
+        use super::*;
+
+        #[test]
+        fn processes_every_layout() {
+            for layout in Melter::AUDIO_IO_LAYOUTS {
+                let num_channels = layout.main_output_channels.unwrap().get();
+                let mut melter = initialized_melter(num_channels, 48000.0, 256);
+                assert_eq!(melter.parametric_eqs.len(), num_channels as usize);
+                assert_eq!(melter.dc_blockers.len(), num_channels as usize);
+                assert_eq!(melter.dry_delays.len(), num_channels as usize);
+                for oversamplers in &melter.oversamplers {
+                    assert_eq!(oversamplers.len(), num_channels as usize);
+                }
+
+                let mut channels: Vec<Vec<f32>> = (0..num_channels)
+                    .map(|channel| {
+                        (0..1000)
+                            .map(|i| (i as f32 * 0.01 * (channel + 1) as f32).sin() * 0.5)
+                            .collect()
+                    })
+                    .collect();
+                melter.process_offline(&mut channels);
+
+                for channel in &channels {
+                    assert!(channel.iter().all(|sample| sample.is_finite()));
+                }
+            }
+        }
+
+        /// Mid/side processing and the width control only apply to stereo, so they shouldn't do
+        /// anything for a quad layout.
+        #[test]
+        fn stereo_features_are_ignored_for_quad() {
+            let input: Vec<Vec<f32>> = (0..4)
+                .map(|channel| {
+                    (0..1000)
+                        .map(|i| (i as f32 * 0.01 * (channel + 1) as f32).sin() * 0.5)
+                        .collect()
+                })
+                .collect();
+
+            let mut reference = initialized_melter(4, 48000.0, 256);
+            let mut reference_output = input.clone();
+            reference.process_offline(&mut reference_output);
+
+            let mut melter = initialized_melter(4, 48000.0, 256);
+            let params = MelterParams {
+                mid_side_eq: BoolParam::new("Mid/Side EQ", true),
+                width: FloatParam::new("Width", 0.0, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+            let mut output = input.clone();
+            melter.process_offline(&mut output);
+
+            assert_eq!(output, reference_output);
+        }
+    }
+
+    mod block_filter_params {
+        use super::*;
+
+        /// Ramps the low frequency and runs a stereo pair of EQs with the same input, the way
+        /// `process()` does. Both channels should always get the same gain, and the smoother
+        /// should advance by one step per sample in the block, however the block is processed.
+        #[test]
+        fn stereo_channels_get_identical_eq_gain() {
+            const SAMPLE_RATE: f32 = 44100.0;
+            const BLOCK_SIZE: usize = 32;
+            const NUM_BLOCKS: usize = 16;
+
+            let params = MelterParams::default();
+            reset_smoothers(&params);
+            params.low_freq.smoothed.set_target(SAMPLE_RATE, 400.0);
+            let steps_before = params.low_freq.smoothed.steps_left();
+
+            let mut eqs = [(); 2].map(|_| new_eq(SAMPLE_RATE));
+            let mut prev_low_freq = params.low_freq.smoothed.previous_value();
+            for block_idx in 0..NUM_BLOCKS {
+                let filter_params = params.next_block_filter_params(BLOCK_SIZE, SAMPLE_RATE * 0.45);
+                assert!(filter_params.low_freq > prev_low_freq);
+                prev_low_freq = filter_params.low_freq;
+
+                let mut outputs = [[0.0f32; BLOCK_SIZE]; 2];
+                for (eq, output) in eqs.iter_mut().zip(outputs.iter_mut()) {
+                    eq.set_smoothing_samples(BLOCK_SIZE);
+                    set_eq_band_params(eq, &filter_params, [6.0, 0.0, 0.0]);
+
+                    for (i, sample) in output.iter_mut().enumerate() {
+                        let t = (block_idx * BLOCK_SIZE + i) as f32;
+                        *sample = eq.process((t * 0.01 * std::f32::consts::TAU).sin());
+                    }
+                }
+
+                assert_eq!(outputs[0], outputs[1]);
+                assert_eq!(eqs[0].magnitude_db(100.0), eqs[1].magnitude_db(100.0));
+            }
+
+            assert_eq!(
+                params.low_freq.smoothed.steps_left(),
+                steps_before - (NUM_BLOCKS * BLOCK_SIZE) as i32
+            );
+        }
+    }
+
+    mod eq_bands {
+        use super::*;
+
+        fn set_num_bands(melter: &mut Melter, num_bands: i32) {
+            let params = MelterParams {
+                low_boost: eq_boost_param("Low Boost", 6.0),
+                num_bands: num_bands_param(num_bands),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+        }
+
+        fn sine() -> Vec<f32> {
+            (0..256).map(|i| (i as f32 * 0.05).sin() * 0.1).collect()
+        }
+
+        /// All bands are allocated up front, so the band count can go up to the maximum and back
+        /// down again while processing.
+        #[test]
+        fn switches_between_one_and_all_bands() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            assert!(melter.parametric_eqs[0]
+                .band_coeffs(equalization::MAX_BANDS - 1)
+                .is_ok());
+
+            for num_bands in [
+                1,
+                equalization::MAX_BANDS as i32,
+                1,
+                equalization::MAX_BANDS as i32,
+            ] {
+                set_num_bands(&mut melter, num_bands);
+                let mut channels = vec![sine()];
+                melter.process_offline(&mut channels);
+                assert!(channels[0].iter().all(|sample| sample.is_finite()));
+                assert!(channels[0].iter().any(|sample| sample.abs() > 0.05));
+            }
+        }
+
+        /// The bands past the first three don't have parameters of their own, so they're flat.
+        #[test]
+        fn extra_bands_are_flat() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            set_num_bands(&mut melter, equalization::MAX_BANDS as i32);
+            let mut reference_melter = initialized_melter(1, 48000.0, 256);
+            set_num_bands(&mut reference_melter, 3);
+
+            for _ in 0..4 {
+                let mut channels = vec![sine()];
+                let mut reference = vec![sine()];
+                melter.process_offline(&mut channels);
+                reference_melter.process_offline(&mut reference);
+                for (sample, reference) in channels[0].iter().zip(&reference[0]) {
+                    approx::assert_relative_eq!(*sample, *reference, epsilon = 1e-5);
+                }
+            }
+        }
+    }
+
+    mod preset_recall {
+        use super::*;
+
+        #[test]
+        fn load_preset_sets_params() {
+            for (index, preset) in presets::FACTORY_PRESETS.iter().enumerate() {
+                let mut melter = initialized_melter(2, 48000.0, 256);
+                melter.load_preset(index).unwrap();
+
+                let params = &melter.params;
+                approx::assert_relative_eq!(
+                    util::gain_to_db(params.gain.value()),
+                    preset.gain_db,
+                    epsilon = 1e-4
+                );
+                assert_eq!(params.drive.value(), preset.drive, "{}", preset.name);
+                assert_eq!(params.bias.value(), preset.bias, "{}", preset.name);
+                assert_eq!(
+                    params.low_boost.value(),
+                    preset.low_boost,
+                    "{}",
+                    preset.name
+                );
+                assert_eq!(
+                    params.mid_boost.value(),
+                    preset.mid_boost,
+                    "{}",
+                    preset.name
+                );
+                assert_eq!(
+                    params.high_boost.value(),
+                    preset.high_boost,
+                    "{}",
+                    preset.name
+                );
+                assert_eq!(
+                    params.oversampling_factor.value(),
+                    preset.oversampling_factor,
+                    "{}",
+                    preset.name
+                );
+            }
+
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            assert!(melter.load_preset(presets::FACTORY_PRESETS.len()).is_err());
+        }
+
+        /// Picking a preset with the selector recalls it on the next buffer. After that the
+        /// parameters can be changed again without the preset coming back.
+        #[test]
+        fn selector_recalls_preset_once() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                preset: preset_param(2),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(
+                melter.params.drive.value(),
+                presets::FACTORY_PRESETS[1].drive
+            );
+
+            set_param_value(&melter.params.drive, 0.1, 48000.0);
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(melter.params.drive.value(), 0.1);
+        }
+    }
+
+    mod randomize {
+        use super::*;
+
+        /// The main parameters as plain values, to compare two plugin instances.
+        fn main_param_values(params: &MelterParams) -> [f32; 7] {
+            [
+                params.gain.value(),
+                params.drive.value(),
+                params.bias.value(),
+                params.low_boost.value(),
+                params.mid_boost.value(),
+                params.high_boost.value(),
+                params.distortion_type.value() as f32,
+            ]
+        }
+
+        #[test]
+        fn same_seed_gives_same_params() {
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let mut other_melter = initialized_melter(2, 48000.0, 256);
+            melter.randomize_params(1234);
+            other_melter.randomize_params(1234);
+            assert_eq!(
+                main_param_values(&melter.params),
+                main_param_values(&other_melter.params)
+            );
+
+            other_melter.randomize_params(1235);
+            assert_ne!(
+                main_param_values(&melter.params),
+                main_param_values(&other_melter.params)
+            );
+        }
+
+        #[test]
+        fn sets_params_to_random_patch() {
+            for seed in 0..100 {
+                let mut melter = initialized_melter(2, 48000.0, 256);
+                melter.randomize_params(seed);
+
+                let patch = presets::RandomPatch::new(seed);
+                let params = &melter.params;
+                approx::assert_relative_eq!(
+                    util::gain_to_db(params.gain.value()),
+                    patch.gain_db,
+                    epsilon = 1e-4
+                );
+                assert_eq!(params.drive.value(), patch.drive);
+                assert_eq!(params.bias.value(), patch.bias);
+                assert_eq!(params.low_boost.value(), patch.low_boost);
+                assert_eq!(params.mid_boost.value(), patch.mid_boost);
+                assert_eq!(params.high_boost.value(), patch.high_boost);
+                assert_eq!(
+                    nonlinearity::DistortionType::ALL[params.distortion_type.value() as usize],
+                    patch.distortion_type
+                );
+            }
+        }
+
+        /// Changing the random seed parameter randomizes the parameters on the next buffer, once.
+        #[test]
+        fn seed_param_randomizes_once() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                random_seed: random_seed_param(42),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(
+                melter.params.drive.value(),
+                presets::RandomPatch::new(42).drive
+            );
+
+            set_param_value(&melter.params.drive, 0.1, 48000.0);
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(melter.params.drive.value(), 0.1);
+        }
+    }
+
+    mod pre_and_post_eq {
+        use super::*;
+
+        /// Runs a quiet 1 kHz sine through the plugin with the post EQ's mid band boosted, and
+        /// returns the output level in decibels.
+        fn level_with_post_mid_boost(pre_and_post_eq: bool, post_mid_boost: f32) -> f32 {
+            const PERIOD: usize = 48;
+
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let params = MelterParams {
+                pre_and_post_eq: BoolParam::new("Pre and Post EQ", pre_and_post_eq),
+                post_mid_boost: post_eq_boost_param("Post Mid Boost", post_mid_boost),
+                safety_limit: BoolParam::new("Safety Limiter", false),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let input: Vec<f32> = (0..PERIOD * 200)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.01)
+                .collect();
+            let mut channels = vec![input.clone(), input];
+            melter.process_offline(&mut channels);
+
+            // Both channels share the same EQ coefficients
+            assert_eq!(channels[0], channels[1]);
+
+            util::gain_to_db(harmonic_amplitude(&channels[0], PERIOD, 50, 1))
+        }
+
+        #[test]
+        fn post_eq_only_runs_when_enabled() {
+            let flat = level_with_post_mid_boost(true, 0.0);
+            let boosted = level_with_post_mid_boost(true, MAX_POST_EQ_BOOST_DB);
+            let disabled = level_with_post_mid_boost(false, MAX_POST_EQ_BOOST_DB);
+
+            approx::assert_relative_eq!(boosted - flat, MAX_POST_EQ_BOOST_DB, epsilon = 0.5);
+            approx::assert_relative_eq!(disabled, flat, epsilon = 0.01);
+        }
+    }
+
+    mod eq_boost_smoothing {
+        use super::*;
+
+        /// Ramps the mid boost quickly and recomputes the coefficients for every sample like
+        /// `process()` does while the boosts are being automated. The gain at the mid frequency
+        /// should change a little on every sample instead of jumping once per block.
+        #[test]
+        fn mid_boost_ramp_is_smooth() {
+            const SAMPLE_RATE: f32 = 44100.0;
+            const BLOCK_SIZE: usize = 32;
+            const NUM_BLOCKS: usize = 8;
+
+            let params = MelterParams::default();
+            reset_smoothers(&params);
+            params.mid_boost.smoothed.set_target(SAMPLE_RATE, 12.0);
+            let ramp_samples = params.mid_boost.smoothed.steps_left() as usize;
+            assert!(ramp_samples > BLOCK_SIZE * NUM_BLOCKS);
+
+            let mut eq = new_eq(SAMPLE_RATE);
+            eq.set_smoothing_samples(0);
+            let mut mid_boost = [0.0f32; BLOCK_SIZE];
+            let mut prev_gain_db = eq.magnitude_db(1000.0);
+            for _ in 0..NUM_BLOCKS {
+                let filter_params = params.next_block_filter_params(BLOCK_SIZE, SAMPLE_RATE * 0.45);
+                params
+                    .mid_boost
+                    .smoothed
+                    .next_block(&mut mid_boost, BLOCK_SIZE);
+
+                for boost in mid_boost {
+                    set_eq_band_params(&mut eq, &filter_params, [0.0, boost, 0.0]);
+                    let gain_db = eq.magnitude_db(filter_params.mid_freq);
+
+                    // The ramp covers 12 dB over `ramp_samples` samples
+                    let step = gain_db - prev_gain_db;
+                    assert!(step > 0.0);
+                    assert!(step < 2.0 * 12.0 / ramp_samples as f32, "{}", step);
+                    prev_gain_db = gain_db;
+                }
+            }
+        }
+
+        /// Every channel computes its per-sample coefficients from the same smoothed boosts, so
+        /// identical channels should still come out identical, and the same as a mono channel
+        /// processed on its own.
+        #[test]
+        fn stereo_channels_stay_in_sync() {
+            const SAMPLE_RATE: f32 = 48000.0;
+            const PERIOD: usize = 100;
+
+            let process = |num_channels: usize| {
+                let mut melter = initialized_melter(num_channels as u32, SAMPLE_RATE, 64);
+                let params = MelterParams {
+                    drive: FloatParam::new("Drive", 0.3, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                params.low_boost.smoothed.set_target(SAMPLE_RATE, -6.0);
+                params.mid_boost.smoothed.set_target(SAMPLE_RATE, 12.0);
+                params.high_boost.smoothed.set_target(SAMPLE_RATE, 6.0);
+                melter.params = Arc::new(params);
+
+                let input: Vec<f32> = (0..PERIOD * 50)
+                    .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.2)
+                    .collect();
+                let mut channels = vec![input; num_channels];
+                melter.process_offline(&mut channels);
+                assert!(!melter.params.mid_boost.smoothed.is_smoothing());
+
+                channels
+            };
+
+            let stereo = process(2);
+            assert_eq!(stereo[0], stereo[1]);
+            let mono = process(1);
+            for (stereo, mono) in stereo[0].iter().zip(&mono[0]) {
+                approx::assert_relative_eq!(*stereo, *mono, epsilon = 1e-6);
+            }
+        }
+    }
+
+    fn new_eq(sample_rate: f32) -> equalization::ParametricEQ {
+        let mut eq = equalization::ParametricEQ::new(sample_rate);
+        eq.add_band(equalization::BandType::LowShelf, 100.0, 0.0, 1.0)
+            .unwrap();
+        eq.add_band(equalization::BandType::Peak, 1000.0, 0.0, 1.0)
+            .unwrap();
+        eq.add_band(equalization::BandType::HighShelf, 10000.0, 0.0, 1.0)
+            .unwrap();
+        eq
+    }
+
+    mod sidechain {
+        use super::*;
+
+        #[test]
+        fn loud_sidechain_adds_harmonics() {
+            const PERIOD: usize = 100;
+            const NUM_SAMPLES: usize = 9600;
+
+            let process_with_sidechain_level = |level: f32| {
+                let mut melter = initialized_melter(1, 48000.0, 256);
+                let params = MelterParams {
+                    drive: FloatParam::new("Drive", 0.2, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                    sidechain_drive: FloatParam::new(
+                        "Sidechain Drive",
+                        1.0,
+                        FloatRange::Linear { min: 0.0, max: 1.0 },
+                    ),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                let mut channels = vec![(0..NUM_SAMPLES)
+                    .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.05)
+                    .collect::<Vec<f32>>()];
+                let mut sidechain = vec![vec![level; NUM_SAMPLES]];
+                process_channels_with_sidechain(&mut melter, &mut channels, &mut sidechain);
+
+                harmonic_content(&channels[0], PERIOD)
+            };
+
+            let quiet = process_with_sidechain_level(0.0);
+            let loud = process_with_sidechain_level(1.0);
+            assert!(loud > quiet * 2.0, "{} vs {}", loud, quiet);
+        }
+    }
+
+    mod drive_stereo_offset {
+        use super::*;
+
+        const PERIOD: usize = 100;
+
+        /// Processes the same sine on both channels of a stereo pair, or on a single mono
+        /// channel, and returns the 3rd harmonic's level relative to the fundamental for every
+        /// channel.
+        fn third_harmonic_per_channel(num_channels: usize, offset: f32) -> Vec<f32> {
+            let mut melter = initialized_melter(num_channels as u32, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new("Drive", 0.3, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                drive_stereo_offset: FloatParam::new(
+                    "Drive Stereo Offset",
+                    offset,
+                    FloatRange::Linear {
+                        min: -0.5,
+                        max: 0.5,
+                    },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let input: Vec<f32> = (0..PERIOD * 50)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.05)
+                .collect();
+            let mut channels = vec![input; num_channels];
+            melter.process_offline(&mut channels);
+
+            channels
+                .iter()
+                .map(|channel| {
+                    harmonic_amplitude(channel, PERIOD, 10, 3)
+                        / harmonic_amplitude(channel, PERIOD, 10, 1)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn offset_drives_channels_differently() {
+            let harmonics = third_harmonic_per_channel(2, 0.2);
+            assert!(
+                harmonics[0] > harmonics[1] * 2.0,
+                "{} vs {}",
+                harmonics[0],
+                harmonics[1]
+            );
+
+            let harmonics = third_harmonic_per_channel(2, 0.0);
+            assert_eq!(harmonics[0], harmonics[1]);
+        }
+
+        #[test]
+        fn ignored_for_mono() {
+            assert_eq!(
+                third_harmonic_per_channel(1, 0.2),
+                third_harmonic_per_channel(1, 0.0)
+            );
+        }
+    }
+
+    mod drive_envelope {
+        use super::*;
+
+        /// A quiet sine wave is interrupted by a short loud burst. Right after the burst the
+        /// envelope is still high so the quiet sine is distorted more, and once the envelope has
+        /// decayed it's back to how it was before the burst.
+        #[test]
+        fn loud_transient_temporarily_increases_drive() {
+            const PERIOD: usize = 100;
+            const NUM_SAMPLES: usize = 24000;
+            const BURST_START: usize = 4800;
+            const BURST_END: usize = BURST_START + PERIOD * 2;
+
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new("Drive", 0.2, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                drive_env_amount: FloatParam::new(
+                    "Drive Envelope",
+                    1.0,
+                    FloatRange::Linear { min: 0.0, max: 2.0 },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![(0..NUM_SAMPLES)
+                .map(|i| {
+                    let level = if (BURST_START..BURST_END).contains(&i) {
+                        1.0
+                    } else {
+                        0.05
+                    };
+                    (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * level
+                })
+                .collect::<Vec<f32>>()];
+            melter.process_offline(&mut channels);
+
+            let output = &channels[0];
+            let before = harmonic_content(&output[..BURST_START], PERIOD);
+            let after_burst = harmonic_content(&output[..BURST_END + PERIOD * 10], PERIOD);
+            let decayed = harmonic_content(output, PERIOD);
+            assert!(after_burst > before * 2.0, "{} vs {}", after_burst, before);
+            approx::assert_relative_eq!(decayed, before, max_relative = 0.2);
+        }
+    }
+
+    mod dynamic_drive {
+        use super::*;
+
+        const PERIOD: usize = 100;
+        const NUM_SAMPLES: usize = 9600;
+
+        /// The [`harmonic_content()`] of a sine wave with the given amplitude after running it
+        /// through the plugin with the given dynamic drive ratio.
+        fn distorted_harmonic_content(ratio: f32, amplitude: f32) -> f32 {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new("Drive", 0.2, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                dynamic_drive_threshold: FloatParam::new(
+                    "Dynamic Drive Threshold",
+                    -30.0,
+                    FloatRange::Linear {
+                        min: -60.0,
+                        max: 0.0,
+                    },
+                ),
+                dynamic_drive_ratio: FloatParam::new(
+                    "Dynamic Drive Ratio",
+                    ratio,
+                    FloatRange::Linear {
+                        min: 1.0,
+                        max: 20.0,
+                    },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![(0..NUM_SAMPLES)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * amplitude)
+                .collect::<Vec<f32>>()];
+            melter.process_offline(&mut channels);
+
+            harmonic_content(&channels[0], PERIOD)
+        }
+
+        /// A quiet signal below the threshold is distorted just as much as without dynamic drive,
+        /// while a loud one is distorted a lot more.
+        #[test]
+        fn loud_passages_distort_more() {
+            let quiet_off = distorted_harmonic_content(1.0, 0.01);
+            let quiet_on = distorted_harmonic_content(8.0, 0.01);
+            approx::assert_relative_eq!(quiet_on, quiet_off, max_relative = 1e-3);
+
+            let loud_off = distorted_harmonic_content(1.0, 0.1);
+            let loud_on = distorted_harmonic_content(8.0, 0.1);
+            assert!(loud_on > loud_off * 1.5, "{} vs {}", loud_on, loud_off);
+        }
+    }
+
+    mod shaper_morph {
+        use super::*;
+
+        /// Renders a driven sine wave with the two shapes and the morph amount.
+        fn render(
+            shape_a: nonlinearity::DistortionType,
+            shape_b: nonlinearity::DistortionType,
+            morph: f32,
+        ) -> Vec<f32> {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new("Drive", 0.5, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                distortion_type: distortion_type_param("Distortion Type", shape_a),
+                distortion_type_b: distortion_type_param("Distortion Type B", shape_b),
+                shaper_morph: FloatParam::new(
+                    "Shape Morph",
+                    morph,
+                    FloatRange::Linear { min: 0.0, max: 1.0 },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![(0..4800)
+                .map(|i| (std::f32::consts::TAU * i as f32 / 100.0).sin() * 0.5)
+                .collect::<Vec<f32>>()];
+            melter.process_offline(&mut channels);
+
+            channels.remove(0)
+        }
+
+        #[test]
+        fn endpoints_match_pure_shapes() {
+            use nonlinearity::DistortionType::{Arctan, Cubic, Foldback};
+
+            assert_eq!(render(Cubic, Arctan, 0.0), render(Cubic, Foldback, 0.0));
+            assert_eq!(render(Cubic, Arctan, 1.0), render(Arctan, Cubic, 0.0));
+        }
+
+        #[test]
+        fn halfway_is_between_the_shapes() {
+            use nonlinearity::DistortionType::{Cubic, Foldback};
+
+            let shape_a = render(Cubic, Foldback, 0.0);
+            let shape_b = render(Cubic, Foldback, 1.0);
+            let halfway = render(Cubic, Foldback, 0.5);
+            assert_ne!(shape_a, shape_b);
+            // The filters after the shapers are linear, so the morph carries over to the output
+            for ((a, b), halfway) in shape_a.iter().zip(&shape_b).zip(&halfway) {
+                approx::assert_relative_eq!(*halfway, (a + b) / 2.0, epsilon = 1e-4);
+            }
+        }
+    }
+
+    mod lfo {
+        use super::*;
+
+        const SAMPLE_RATE: f32 = 48000.0;
+        const BUFFER_SIZE: usize = 64;
+
+        /// Create a plugin instance with the LFO modulating the drive.
+        fn lfo_melter(params: MelterParams) -> Melter {
+            let mut melter = initialized_melter(1, SAMPLE_RATE, BUFFER_SIZE as u32);
+            let params = MelterParams {
+                lfo_depth: FloatParam::new(
+                    "LFO Depth",
+                    0.5,
+                    FloatRange::Linear { min: 0.0, max: 1.0 },
+                ),
+                ..params
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            melter
+        }
+
+        /// Processes one second in short buffers with the host reporting `tempo`, and returns
+        /// the modulated drive at the end of every buffer.
+        fn drive_over_one_second(melter: &mut Melter, tempo: Option<f64>) -> Vec<f32> {
+            let mut drive_values = Vec::new();
+            for _ in 0..SAMPLE_RATE as usize / BUFFER_SIZE {
+                let mut channels = vec![vec![0.0; BUFFER_SIZE]];
+                let mut buffer = new_buffer(&mut channels);
+                let mut aux = AuxiliaryBuffers {
+                    inputs: &mut [],
+                    outputs: &mut [],
+                };
+                melter.process_buffer(&mut buffer, &mut aux, tempo);
+                let upsampled_len =
+                    BUFFER_SIZE * oversampling_factor_to_times(DEFAULT_OVERSAMPLING_FACTOR);
+                drive_values.push(melter.scratch_buffers.drive[upsampled_len - 1]);
+            }
+
+            drive_values
+        }
+
+        /// How often the drive crosses its unmodulated value on the way up.
+        fn upward_crossings(drive_values: &[f32], base_drive: f32) -> usize {
+            drive_values
+                .windows(2)
+                .filter(|pair| pair[0] < base_drive && pair[1] >= base_drive)
+                .count()
+        }
+
+        fn lfo_rate_param(rate: f32) -> FloatParam {
+            FloatParam::new(
+                "LFO Rate",
+                rate,
+                FloatRange::Linear {
+                    min: 0.01,
+                    max: 20.0,
+                },
+            )
+        }
+
+        #[test]
+        fn drive_oscillates_at_lfo_rate() {
+            let mut melter = lfo_melter(MelterParams {
+                lfo_rate: lfo_rate_param(5.0),
+                ..MelterParams::default()
+            });
+            let base_drive = melter.params.drive.value();
+            let drive_values = drive_over_one_second(&mut melter, None);
+
+            let crossings = upward_crossings(&drive_values, base_drive);
+            assert!((4..=6).contains(&crossings), "{} crossings", crossings);
+
+            let max_drive = drive_values.iter().copied().fold(f32::MIN, f32::max);
+            let min_drive = drive_values.iter().copied().fold(f32::MAX, f32::min);
+            assert!(max_drive > base_drive + 0.25, "{}", max_drive);
+            assert!(min_drive < base_drive - 0.25, "{}", min_drive);
+        }
+
+        /// At 120 BPM an eighth note triplet lasts a sixth of a second, so the drive should go
+        /// through six cycles per second regardless of the free running rate.
+        #[test]
+        fn synced_period_matches_division() {
+            let division = modulation::NoteDivision::ALL
+                .iter()
+                .position(|division| *division == modulation::NoteDivision::EighthTriplet)
+                .unwrap() as i32;
+            let mut melter = lfo_melter(MelterParams {
+                lfo_rate: lfo_rate_param(1.0),
+                lfo_sync: BoolParam::new("LFO Sync", true),
+                lfo_division: IntParam::new(
+                    "LFO Division",
+                    division,
+                    IntRange::Linear { min: 0, max: 13 },
+                ),
+                ..MelterParams::default()
+            });
+            let base_drive = melter.params.drive.value();
+            let drive_values = drive_over_one_second(&mut melter, Some(120.0));
+
+            let crossings = upward_crossings(&drive_values, base_drive);
+            assert!((5..=7).contains(&crossings), "{} crossings", crossings);
+        }
+
+        #[test]
+        fn falls_back_to_free_rate_without_tempo() {
+            let mut melter = lfo_melter(MelterParams {
+                lfo_rate: lfo_rate_param(2.0),
+                lfo_sync: BoolParam::new("LFO Sync", true),
+                ..MelterParams::default()
+            });
+            let base_drive = melter.params.drive.value();
+            let drive_values = drive_over_one_second(&mut melter, None);
+
+            let crossings = upward_crossings(&drive_values, base_drive);
+            assert!((1..=3).contains(&crossings), "{} crossings", crossings);
+        }
+
+        /// The LFO is added on top of the smoothed drive, so the drive's smoother keeps
+        /// following the host's automation.
+        #[test]
+        fn leaves_drive_smoother_alone() {
+            let mut melter = lfo_melter(MelterParams {
+                lfo_rate: lfo_rate_param(5.0),
+                ..MelterParams::default()
+            });
+            drive_over_one_second(&mut melter, None);
+
+            assert!(!melter.params.drive.smoothed.is_smoothing());
+            assert_eq!(
+                melter.params.drive.smoothed.previous_value(),
+                melter.params.drive.value()
+            );
+        }
+
+        /// The LFO runs at a fixed control rate, so sweeping the filters in one large host
+        /// buffer gives the same output as sweeping them in short buffers.
+        #[test]
+        fn control_rate_doesnt_depend_on_buffer_size() {
+            const NUM_SAMPLES: usize = 8192;
+
+            let process = |destination: modulation::LfoDestination, host_buffer_size: usize| {
+                let mut melter = initialized_melter(1, SAMPLE_RATE, host_buffer_size as u32);
+                let params = MelterParams {
+                    // Sweeping the mid band's frequency is only audible with a boost
+                    mid_boost: eq_boost_param(
+                        "Mid Boost",
+                        if destination == modulation::LfoDestination::MidFreq {
+                            12.0
+                        } else {
+                            0.0
+                        },
+                    ),
+                    lfo_rate: lfo_rate_param(5.0),
+                    lfo_depth: FloatParam::new(
+                        "LFO Depth",
+                        1.0,
+                        FloatRange::Linear { min: 0.0, max: 1.0 },
+                    ),
+                    lfo_destination: IntParam::new(
+                        "LFO Destination",
+                        modulation::LfoDestination::ALL
+                            .iter()
+                            .position(|other| *other == destination)
+                            .unwrap() as i32,
+                        IntRange::Linear {
+                            min: 0,
+                            max: modulation::LfoDestination::ALL.len() as i32 - 1,
+                        },
+                    ),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                let mut output = Vec::new();
+                for buffer_start in (0..NUM_SAMPLES).step_by(host_buffer_size) {
+                    let mut channels = vec![(buffer_start..buffer_start + host_buffer_size)
+                        .map(|i| (i as f32 * 0.3).sin() * 0.5)
+                        .collect::<Vec<f32>>()];
+                    melter.process_offline(&mut channels);
+                    output.append(&mut channels[0]);
+                }
+
+                output
+            };
+
+            for destination in [
+                modulation::LfoDestination::ToneCutoff,
+                modulation::LfoDestination::MidFreq,
+            ] {
+                let short_buffers = process(destination, CONTROL_BLOCK_SIZE);
+                let long_buffer = process(destination, NUM_SAMPLES);
+                for (short, long) in short_buffers.iter().zip(&long_buffer) {
+                    approx::assert_relative_eq!(*short, *long, epsilon = 1e-5);
+                }
+            }
+        }
+    }
+
+    mod routing {
+        use super::*;
+
+        const LOUD_PERIOD: usize = 480;
+        const DETAIL_PERIOD: usize = 48;
+        const DETAIL_LEVEL: f32 = 0.01;
+
+        /// Runs a loud low sine through a heavily driven hard clipper, with and without a quiet
+        /// higher sine on top. Returns the level of the quiet sine in the difference between the
+        /// two outputs while the loud sine is near its peaks, relative to the quiet sine's own
+        /// level. Anything the clipper does to the quiet sine there is lost. The quiet sine is an
+        /// even harmonic of the loud sine, so the clipper's odd harmonics don't get in the way.
+        fn detail_preserved(routing: Routing) -> f32 {
+            let process = |detail_level: f32| {
+                let mut melter = initialized_melter(1, 48000.0, 256);
+                let params = MelterParams {
+                    routing: routing_param(routing),
+                    ..hard_clipping_params()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                let mut channels = vec![(0..NUM_SAMPLES)
+                    .map(|i| loud(i) + detail(i) * detail_level)
+                    .collect::<Vec<f32>>()];
+                melter.process_offline(&mut channels);
+
+                (channels.remove(0), melter.latency_samples as usize)
+            };
+
+            let (with_detail, latency) = process(1.0);
+            let (without_detail, _) = process(0.0);
+
+            // Skip the first periods while the filters settle
+            let (re, im, num_samples) = (LOUD_PERIOD * 4..NUM_SAMPLES)
+                .filter(|i| loud(i - latency).abs() > 0.3)
+                .fold((0.0, 0.0, 0), |(re, im, num_samples), i| {
+                    let difference = with_detail[i] - without_detail[i];
+                    let phase = std::f32::consts::TAU * (i - latency) as f32 / DETAIL_PERIOD as f32;
+                    (
+                        re + difference * phase.cos(),
+                        im + difference * phase.sin(),
+                        num_samples + 1,
+                    )
+                });
+
+            (re * re + im * im).sqrt() * 2.0 / num_samples as f32 / DETAIL_LEVEL
+        }
+
+        const NUM_SAMPLES: usize = LOUD_PERIOD * 20;
+
+        fn loud(i: usize) -> f32 {
+            (std::f32::consts::TAU * i as f32 / LOUD_PERIOD as f32).sin() * 0.5
+        }
+
+        fn detail(i: usize) -> f32 {
+            (std::f32::consts::TAU * i as f32 / DETAIL_PERIOD as f32).sin() * DETAIL_LEVEL
+        }
+
+        #[test]
+        fn parallel_preserves_low_level_detail() {
+            let series = detail_preserved(Routing::Series);
+            let parallel = detail_preserved(Routing::Parallel);
+
+            assert!(series < 0.1, "{}", series);
+            assert!((0.9..1.1).contains(&parallel), "{}", parallel);
+        }
+    }
+
+    mod safety_limiter {
+        use super::*;
+
+        /// Clips the signal hard and then boosts all EQ bands after the clipper, and returns the
+        /// peak output level.
+        fn hot_output_peak(safety_limit: bool) -> f32 {
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let params = MelterParams {
+                low_boost: eq_boost_param("Low Boost", 12.0),
+                mid_boost: eq_boost_param("Mid Boost", 12.0),
+                high_boost: eq_boost_param("High Boost", 12.0),
+                output_gain: output_gain_param(6.0),
+                safety_limit: BoolParam::new("Safety Limiter", safety_limit),
+                ..hard_clipping_params()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![
+                (0..9600)
+                    .map(|i| (i as f32 * 0.05).sin() * 0.8)
+                    .collect::<Vec<f32>>(),
+                (0..9600)
+                    .map(|i| (i as f32 * 0.013).sin())
+                    .collect::<Vec<f32>>(),
+            ];
+            melter.process_offline(&mut channels);
+
+            channels
+                .iter()
+                .flatten()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+        }
+
+        #[test]
+        fn output_stays_below_ceiling() {
+            // Without the limiter this goes well over 0 dBFS
+            assert!(hot_output_peak(false) > 1.0);
+
+            let peak = hot_output_peak(true);
+            assert!(
+                peak <= util::db_to_gain(SAFETY_LIMITER_CEILING_DB),
+                "{} dBFS",
+                util::gain_to_db(peak)
+            );
+        }
+
+        /// A bypassed plugin should output the latency compensated input, even when the input
+        /// goes over the limiter's ceiling.
+        #[test]
+        fn bypass_skips_the_limiter() {
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let params = MelterParams {
+                bypass: BoolParam::new("Bypass", true),
+                safety_limit: BoolParam::new("Safety Limiter", true),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let input: Vec<f32> = (0..9600).map(|i| (i as f32 * 0.05).sin() * 2.0).collect();
+            let mut channels = vec![input.clone(), input.clone()];
+            melter.process_offline(&mut channels);
+
+            let latency = melter.latency_samples as usize;
+            for channel in &channels {
+                for (i, sample) in channel
+                    .iter()
+                    .enumerate()
+                    .skip(BYPASS_FADE_SAMPLES + latency)
+                {
+                    assert_eq!(*sample, input[i - latency], "sample {i}");
+                }
+            }
+        }
+    }
+
+    mod bypass {
+        use super::*;
+
+        /// Once the bypass has faded in, the output should be exactly the input delayed by the
+        /// oversampling latency, the same as the processed signal would be.
+        #[test]
+        fn bypass_is_latency_compensated_input() {
+            const NUM_SAMPLES: usize = 2048;
+
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let latency = melter.latency_samples as usize;
+            assert!(latency > 0);
+
+            let input: Vec<f32> = (0..NUM_SAMPLES * 2)
+                .map(|i| (i as f32 * 0.05).sin() * 0.5)
+                .collect();
+            let mut processed = vec![input[..NUM_SAMPLES].to_vec()];
+            melter.process_offline(&mut processed);
+            assert_ne!(processed[0][latency..], input[..NUM_SAMPLES - latency]);
+
+            set_param_value(&melter.params.bypass, true, 48000.0);
+            let mut bypassed = vec![input[NUM_SAMPLES..].to_vec()];
+            melter.process_offline(&mut bypassed);
+
+            for (i, sample) in bypassed[0].iter().enumerate().skip(BYPASS_FADE_SAMPLES) {
+                assert_eq!(*sample, input[NUM_SAMPLES + i - latency], "sample {i}");
+            }
+        }
+    }
+
+    mod filter_sample_rate {
+        use super::*;
+
+        #[test]
+        fn only_updated_when_factor_changes() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let oversampled_sample_rate =
+                |factor: usize| 48000.0 * oversampling_factor_to_times(factor) as f32;
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(
+                melter.parametric_eqs[0].sample_rate(),
+                oversampled_sample_rate(DEFAULT_OVERSAMPLING_FACTOR)
+            );
+
+            // The coefficients aren't recomputed while the factor stays the same, so this sticks
+            melter.parametric_eqs[0].set_sample_rate(1000.0);
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(melter.parametric_eqs[0].sample_rate(), 1000.0);
+
+            let params = MelterParams {
+                oversampling_factor: oversampling_factor_param(MAX_OVERSAMPLING_FACTOR),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            // The new factor is only used once the output has been faded out
+            melter.process_offline(&mut [vec![0.0; OVERSAMPLING_FADE_SAMPLES]]);
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(
+                melter.parametric_eqs[0].sample_rate(),
+                oversampled_sample_rate(MAX_OVERSAMPLING_FACTOR)
+            );
+        }
+    }
+
+    mod offline_rendering {
+        use super::*;
+
+        /// Initializes a mono plugin in `process_mode` with oversampling turned off and the
+        /// minimum phase filters selected, and processes a buffer with it.
+        fn processed_melter(process_mode: ProcessMode) -> Melter {
+            let mut melter = Melter {
+                params: Arc::new(MelterParams {
+                    oversampling_factor: oversampling_factor_param(0),
+                    oversampling_quality: IntParam::new(
+                        "Oversampling Quality",
+                        oversampling::OversamplingQuality::MinimumPhase.index() as i32,
+                        IntRange::Linear {
+                            min: 0,
+                            max: oversampling::OversamplingQuality::ALL.len() as i32 - 1,
+                        },
+                    ),
+                    ..MelterParams::default()
+                }),
+                ..Melter::default()
+            };
+            reset_smoothers(&melter.params);
+            let audio_io_layout = AudioIOLayout {
+                main_input_channels: NonZeroU32::new(1),
+                main_output_channels: NonZeroU32::new(1),
+                ..AudioIOLayout::const_default()
+            };
+            let buffer_config = BufferConfig {
+                sample_rate: 48000.0,
+                min_buffer_size: None,
+                max_buffer_size: 256,
+                process_mode,
+            };
+            assert!(melter.initialize(&audio_io_layout, &buffer_config, &mut OfflineInitContext));
+            melter.reset();
+            melter.process_offline(&mut [vec![0.0; 256]]);
+
+            melter
+        }
+
+        #[test]
+        fn offline_uses_the_best_oversampling() {
+            let melter = processed_melter(ProcessMode::Offline);
+            assert_eq!(
+                melter.oversampling_fader.current(),
+                (
+                    MAX_OVERSAMPLING_FACTOR,
+                    oversampling::OversamplingQuality::Lanczos3
+                )
+            );
+            assert_eq!(
+                melter.parametric_eqs[0].sample_rate(),
+                48000.0 * MAX_OVERSAMPLING_TIMES as f32
+            );
+            assert_eq!(
+                melter.latency_samples,
+                oversampling::Lanczos3Oversampler::new(256, MAX_OVERSAMPLING_FACTOR)
+                    .latency(MAX_OVERSAMPLING_FACTOR)
+            );
+        }
+
+        #[test]
+        fn realtime_keeps_the_parameters() {
+            for process_mode in [ProcessMode::Realtime, ProcessMode::Buffered] {
+                let melter = processed_melter(process_mode);
+                assert_eq!(
+                    melter.oversampling_fader.current(),
+                    (0, oversampling::OversamplingQuality::MinimumPhase)
+                );
+                assert_eq!(melter.latency_samples, 0);
+            }
+        }
+    }
+
+    mod oversampling_switch {
+        use super::*;
+
+        /// The new factor is switched to as soon as the output has been faded out, even in the
+        /// middle of a buffer, so the output comes back without waiting for the next buffer.
+        #[test]
+        fn switches_within_one_buffer() {
+            const PERIOD: usize = 48;
+
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                oversampling_factor: oversampling_factor_param(2),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![(0..PERIOD * 200)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.1)
+                .collect::<Vec<f32>>()];
+            melter.process_offline(&mut channels);
+
+            assert_eq!(melter.oversampling_fader.current().0, 2);
+            assert!(!melter.oversampling_fader.is_fading());
+            let level = harmonic_amplitude(&channels[0], PERIOD, 100, 1);
+            assert!(level > 0.05, "{level}");
+        }
+
+        /// Switches the oversampling factor in the middle of a sine wave, and checks that the
+        /// output never jumps by more than the sine wave itself does before the switch. Without
+        /// any drive the output is still close to a sine wave, so a spike would stand out.
+        #[test]
+        fn no_spike_when_changing_oversampling_factor() {
+            const BLOCK_SIZE: usize = 32;
+            const NUM_BLOCKS: usize = 128;
+            // As a fraction of the sampling frequency
+            const FREQUENCY: f32 = 0.01;
+
+            let mut melter = initialized_melter(1, 48000.0, BLOCK_SIZE as u32);
+            set_param_value(&melter.params.drive, 0.0, 48000.0);
+            let mut output = Vec::with_capacity(BLOCK_SIZE * NUM_BLOCKS);
+            for block_idx in 0..NUM_BLOCKS {
+                // The factor changes halfway through
+                if block_idx == NUM_BLOCKS / 2 {
+                    set_param_value(
+                        &melter.params.oversampling_factor,
+                        MAX_OVERSAMPLING_FACTOR as i32,
+                        48000.0,
+                    );
+                }
+
+                let mut channels = vec![(0..BLOCK_SIZE)
+                    .map(|i| {
+                        let t = (block_idx * BLOCK_SIZE + i) as f32;
+                        (t * FREQUENCY * std::f32::consts::TAU).sin() * 0.1
+                    })
+                    .collect::<Vec<f32>>()];
+                melter.process_offline(&mut channels);
+                output.extend(channels.remove(0));
+            }
+            assert_eq!(
+                melter.oversampling_fader.current().0,
+                MAX_OVERSAMPLING_FACTOR
+            );
+            assert!(!melter.oversampling_fader.is_fading());
+
+            // The largest step once the filters have settled, plus what the fade itself adds
+            let switch_start = BLOCK_SIZE * NUM_BLOCKS / 2;
+            let steps = |range: std::ops::Range<usize>| {
+                output[range]
+                    .windows(2)
+                    .map(|pair| (pair[1] - pair[0]).abs())
+                    .collect::<Vec<f32>>()
+            };
+            let peak = output[switch_start / 2..switch_start]
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let max_step = steps(switch_start / 2..switch_start)
+                .into_iter()
+                .fold(0.0f32, f32::max)
+                + peak / OVERSAMPLING_FADE_SAMPLES as f32;
+            for (i, step) in steps(switch_start..output.len()).into_iter().enumerate() {
+                assert!(
+                    step <= max_step,
+                    "sample {}: {step} > {max_step}",
+                    switch_start + i
+                );
+            }
+        }
+    }
+
+    mod auto_oversampling {
+        use super::*;
+
+        #[test]
+        fn factor_rises_with_drive() {
+            let mut factor = 0;
+            let factors: Vec<usize> = (0..=100)
+                .map(|i| {
+                    factor = auto_oversampling_factor(i as f32 / 100.0 * MAX_DRIVE, factor);
+                    factor
+                })
+                .collect();
+            assert_eq!(factors[0], 0);
+            assert_eq!(factors[100], MAX_OVERSAMPLING_FACTOR);
+            assert!(factors.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            let mut factor = MAX_OVERSAMPLING_FACTOR;
+            for i in (0..=100).rev() {
+                factor = auto_oversampling_factor(i as f32 / 100.0 * MAX_DRIVE, factor);
+            }
+            assert_eq!(factor, 0);
+        }
+
+        /// Moves the drive back and forth across the threshold between the first two factors. The
+        /// factor should only change once the drive moves past the hysteresis, instead of fading
+        /// the output out and back in on every crossing.
+        #[test]
+        fn drive_around_a_threshold_doesnt_keep_switching() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new(
+                    "Drive",
+                    0.4,
+                    FloatRange::Linear {
+                        min: 0.0,
+                        max: MAX_DRIVE,
+                    },
+                )
+                .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+                oversampling_factor: oversampling_factor_param(0),
+                auto_oversampling: BoolParam::new("Auto Oversampling", true),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+            melter.process_offline(&mut [vec![0.0; OVERSAMPLING_FADE_SAMPLES]]);
+            melter.process_offline(&mut [vec![0.0; 256]]);
+            assert_eq!(melter.oversampling_fader.current().0, 1);
+
+            let threshold = MAX_DRIVE / MAX_OVERSAMPLING_FACTOR as f32;
+            for i in 0..20 {
+                let drive = if i % 2 == 0 {
+                    threshold * 1.1
+                } else {
+                    threshold * 0.9
+                };
+                melter.params.drive.smoothed.set_target(48000.0, drive);
+                for _ in 0..20 {
+                    melter.process_offline(&mut [vec![0.0; 256]]);
+                    assert!(!melter.oversampling_fader.is_fading());
+                }
+            }
+
+            // Moving further past the threshold does switch
+            melter
+                .params
+                .drive
+                .smoothed
+                .set_target(48000.0, threshold * 1.5);
+            for _ in 0..20 {
+                melter.process_offline(&mut [vec![0.0; 256]]);
+            }
+            assert_eq!(melter.oversampling_fader.target().0, 2);
+        }
+
+        /// Sweeps the drive through the plugin with the factor parameter set to no oversampling.
+        /// The factor in use and the reported latency should both rise along with the drive.
+        #[test]
+        fn plugin_follows_the_drive() {
+            let process_with_drive = |drive: f32| {
+                let mut melter = initialized_melter(1, 48000.0, 256);
+                let params = MelterParams {
+                    drive: FloatParam::new(
+                        "Drive",
+                        drive,
+                        FloatRange::Linear {
+                            min: 0.0,
+                            max: MAX_DRIVE,
+                        },
+                    ),
+                    oversampling_factor: oversampling_factor_param(0),
+                    auto_oversampling: BoolParam::new("Auto Oversampling", true),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                // The new factor is only used once the output has been faded out
+                melter.process_offline(&mut [vec![0.0; OVERSAMPLING_FADE_SAMPLES]]);
+                melter.process_offline(&mut [vec![0.0; 256]]);
+
+                (
+                    melter.oversampling_fader.current().0,
+                    melter.latency_samples,
+                )
+            };
+
+            let mut prev = process_with_drive(0.0);
+            assert_eq!(prev, (0, 0));
+            for i in 1..=8 {
+                let current = process_with_drive(i as f32 / 8.0 * MAX_DRIVE);
+                assert!(current.0 >= prev.0, "{:?} vs {:?}", current, prev);
+                assert!(current.1 >= prev.1, "{:?} vs {:?}", current, prev);
+                prev = current;
+            }
+            assert_eq!(prev.0, MAX_OVERSAMPLING_FACTOR);
+        }
+    }
+
+    mod multiband {
+        use super::*;
+
+        const NUM_PERIODS: usize = 10;
+
+        /// Parameters without any drive or bias, so the bands are only driven by their own drive.
+        fn undriven_params(multiband: bool) -> MelterParams {
+            MelterParams {
+                drive: FloatParam::new("Drive", 0.0, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                bias: FloatParam::new(
+                    "Bias",
+                    0.0,
+                    FloatRange::Linear {
+                        min: -1.0,
+                        max: 1.0,
+                    },
+                ),
+                multiband: BoolParam::new("Multiband", multiband),
+                ..MelterParams::default()
+            }
+        }
+
+        fn band_drive(drive: f32) -> FloatParam {
+            FloatParam::new(
+                "Band Drive",
+                drive,
+                FloatRange::Linear {
+                    min: -1.0,
+                    max: 1.0,
+                },
+            )
+        }
+
+        fn crossover(freq: f32) -> FloatParam {
+            FloatParam::new(
+                "Crossover",
+                freq,
+                FloatRange::Linear {
+                    min: 80.0,
+                    max: 8000.0,
+                },
+            )
+        }
+
+        /// Processes a sine with the given period through the plugin and returns the output.
+        fn process_sine(period: usize, amplitude: f32, params: MelterParams) -> Vec<f32> {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let mut channels = vec![(0..24000)
+                .map(|i| (std::f32::consts::TAU * i as f32 / period as f32).sin() * amplitude)
+                .collect::<Vec<f32>>()];
+            melter.process_offline(&mut channels);
+
+            channels.remove(0)
+        }
+
+        /// The 3rd harmonic's level relative to the fundamental.
+        fn third_harmonic(output: &[f32], period: usize) -> f32 {
+            harmonic_amplitude(output, period, NUM_PERIODS, 3)
+                / harmonic_amplitude(output, period, NUM_PERIODS, 1)
+        }
+
+        /// Without any drive the cubic is all but linear for a quiet signal, so the summed bands
+        /// should have the same level as the signal that wasn't split at all. That includes the
+        /// crossover frequencies, where two bands overlap.
+        #[test]
+        fn zero_drive_is_flat() {
+            // 100 Hz, around both crossovers, 1 kHz and 8 kHz
+            for period in [480, 192, 48, 19, 6] {
+                let level = |multiband| {
+                    let output = process_sine(period, 0.01, undriven_params(multiband));
+                    harmonic_amplitude(&output, period, NUM_PERIODS, 1)
+                };
+                let difference_db = util::gain_to_db(level(true) / level(false));
+                assert!(
+                    difference_db.abs() < 0.05,
+                    "period {period}: {difference_db} dB"
+                );
+            }
+        }
+
+        #[test]
+        fn band_drive_only_affects_its_band() {
+            const LOW_PERIOD: usize = 480;
+            const HIGH_PERIOD: usize = 10;
+
+            let process_with_low_drive = |period, low_drive| {
+                let params = MelterParams {
+                    low_band_drive: band_drive(low_drive),
+                    ..undriven_params(true)
+                };
+                process_sine(period, 0.2, params)
+            };
+            let clean = third_harmonic(&process_with_low_drive(LOW_PERIOD, 0.0), LOW_PERIOD);
+            let driven = third_harmonic(&process_with_low_drive(LOW_PERIOD, 1.0), LOW_PERIOD);
+            assert!(driven > clean * 10.0, "{driven} vs {clean}");
+
+            // The 4.8 kHz sine is far above the low band, so driving that band does nothing to it
+            let clean = process_with_low_drive(HIGH_PERIOD, 0.0);
+            let driven = process_with_low_drive(HIGH_PERIOD, 1.0);
+            let tail = clean.len() - HIGH_PERIOD * NUM_PERIODS;
+            for (clean, driven) in clean[tail..].iter().zip(&driven[tail..]) {
+                approx::assert_relative_eq!(*clean, *driven, epsilon = 1e-3);
+            }
+        }
+
+        /// A 500 Hz sine is in the low band with the low crossover above it and in the mid band
+        /// with the crossover below it, so only in the first case does the low band's drive
+        /// distort it.
+        #[test]
+        fn low_crossover_moves_the_split() {
+            const PERIOD: usize = 96;
+
+            let third_harmonic_with_crossover = |freq| {
+                let params = MelterParams {
+                    low_band_drive: band_drive(1.0),
+                    crossover_low: crossover(freq),
+                    ..undriven_params(true)
+                };
+                third_harmonic(&process_sine(PERIOD, 0.2, params), PERIOD)
+            };
+            let in_low_band = third_harmonic_with_crossover(800.0);
+            let in_mid_band = third_harmonic_with_crossover(80.0);
+            assert!(
+                in_low_band > in_mid_band * 10.0,
+                "{in_low_band} vs {in_mid_band}"
+            );
+        }
+
+        /// Gives the low and the high band different shapers and checks the harmonics of a sine in
+        /// each band. The even harmonics shaper adds a second harmonic and hardly any third, the
+        /// arctangent is odd symmetric and only adds the third. Swapping the shapers swaps the
+        /// harmonics. The high crossover is moved down so the mid band's shaper doesn't pick up the
+        /// high sine.
+        #[test]
+        fn bands_use_their_own_shapers() {
+            const LOW_PERIOD: usize = 480;
+            const HIGH_PERIOD: usize = 16;
+
+            let harmonics = |period, low_type, high_type| {
+                let params = MelterParams {
+                    drive: FloatParam::new("Drive", 0.5, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                    low_band_type: distortion_type_param("Low Band Type", low_type),
+                    high_band_type: distortion_type_param("High Band Type", high_type),
+                    crossover_high: crossover(1000.0),
+                    ..undriven_params(true)
+                };
+                let output = process_sine(period, 0.2, params);
+                let fundamental = harmonic_amplitude(&output, period, NUM_PERIODS, 1);
+                [2, 3].map(|harmonic| {
+                    harmonic_amplitude(&output, period, NUM_PERIODS, harmonic) / fundamental
+                })
+            };
+            let assert_even = |[second, third]: [f32; 2]| {
+                assert!(second > 0.05 && third < second / 10.0, "{second}, {third}")
+            };
+            let assert_odd = |[second, third]: [f32; 2]| {
+                assert!(third > 0.05 && second < third / 10.0, "{second}, {third}")
+            };
+
+            let even = nonlinearity::DistortionType::EvenHarmonics;
+            let odd = nonlinearity::DistortionType::Arctan;
+            assert_even(harmonics(LOW_PERIOD, even, odd));
+            assert_odd(harmonics(HIGH_PERIOD, even, odd));
+            assert_odd(harmonics(LOW_PERIOD, odd, even));
+            assert_even(harmonics(HIGH_PERIOD, odd, even));
+        }
+
+        /// The same for a 3 kHz sine and the high crossover.
+        #[test]
+        fn high_crossover_moves_the_split() {
+            const PERIOD: usize = 16;
+
+            let third_harmonic_with_crossover = |freq| {
+                let params = MelterParams {
+                    high_band_drive: band_drive(1.0),
+                    crossover_high: crossover(freq),
+                    ..undriven_params(true)
+                };
+                third_harmonic(&process_sine(PERIOD, 0.2, params), PERIOD)
+            };
+            let in_high_band = third_harmonic_with_crossover(1000.0);
+            let in_mid_band = third_harmonic_with_crossover(8000.0);
+            assert!(
+                in_high_band > in_mid_band * 10.0,
+                "{in_high_band} vs {in_mid_band}"
+            );
+        }
+    }
+
+    mod dc_meter {
+        use super::*;
+
+        /// The DC offset going into the DC blocker for a sine wave through the cubic shaper with
+        /// the given bias.
+        fn measured_dc(bias: f32) -> f32 {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                drive: FloatParam::new("Drive", 0.0, FloatRange::Linear { min: 0.0, max: 2.0 }),
+                bias: FloatParam::new(
+                    "Bias",
+                    bias,
+                    FloatRange::Linear {
+                        min: -1.0,
+                        max: 1.0,
+                    },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            // The second run measures ten whole periods after everything has settled
+            let dc = melter.dc_meter.dc();
+            for _ in 0..2 {
+                let mut channels = vec![(0..4800)
+                    .map(|i| (std::f32::consts::TAU * i as f32 / 480.0).sin() * 0.5)
+                    .collect::<Vec<f32>>()];
+                melter.process_offline(&mut channels);
+            }
+
+            dc.load(std::sync::atomic::Ordering::Relaxed)
+        }
+
+        /// The cubic curve is `x - x^3 / 3`, so the bias `b` added to a sine with amplitude `a`
+        /// results in a DC offset of `b - b^3 / 3 - b * a^2 / 2`.
+        #[test]
+        fn reports_offset_from_bias() {
+            const BIAS: f32 = 0.2;
+            const AMPLITUDE: f32 = 0.5;
+
+            let expected = BIAS - BIAS.powi(3) / 3.0 - BIAS * AMPLITUDE.powi(2) / 2.0;
+            approx::assert_relative_eq!(measured_dc(BIAS), expected, max_relative = 0.02);
+            approx::assert_relative_eq!(measured_dc(-BIAS), -expected, max_relative = 0.02);
+            assert!(measured_dc(0.0).abs() < 1e-4);
+        }
+    }
+
+    mod wet_solo {
+        use super::*;
+
+        fn sine() -> Vec<f32> {
+            (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect()
+        }
+
+        fn melter_with(mix: f32, routing: Routing, wet_solo: bool) -> Melter {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                mix: FloatParam::new("Mix", mix, FloatRange::Linear { min: 0.0, max: 1.0 }),
+                routing: routing_param(routing),
+                wet_solo: BoolParam::new("Wet Solo", wet_solo),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+            melter.wet_solo_fader.reset(wet_solo);
+
+            melter
+        }
+
+        fn render(mut melter: Melter) -> Vec<f32> {
+            let mut channels = vec![sine()];
+            melter.process_offline(&mut channels);
+            channels.remove(0)
+        }
+
+        #[test]
+        fn solo_is_fully_wet() {
+            let fully_wet = render(melter_with(1.0, Routing::Series, false));
+            assert_eq!(render(melter_with(0.25, Routing::Series, true)), fully_wet);
+            assert_eq!(
+                render(melter_with(0.25, Routing::Parallel, true)),
+                fully_wet
+            );
+        }
+
+        /// Turning solo off fades back to the mix, which was never changed.
+        #[test]
+        fn toggling_off_restores_mix() {
+            let mut never_soloed = melter_with(0.25, Routing::Series, false);
+            let mut soloed = melter_with(0.25, Routing::Series, true);
+            for melter in [&mut never_soloed, &mut soloed] {
+                let mut channels = vec![sine()];
+                melter.process_offline(&mut channels);
+            }
+
+            Arc::get_mut(&mut soloed.params).unwrap().wet_solo = BoolParam::new("Wet Solo", false);
+            let [expected, output] = [&mut never_soloed, &mut soloed].map(|melter| {
+                let mut channels = vec![sine()];
+                melter.process_offline(&mut channels);
+                channels.remove(0)
+            });
+
+            assert_eq!(soloed.params.mix.value(), 0.25);
+            assert_ne!(output[0], expected[0]);
+            for (output, expected) in output.iter().zip(&expected).skip(BYPASS_FADE_SAMPLES) {
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    mod latency {
+        use super::*;
+
+        /// With oversampling disabled and the mix turned all the way down, the output should be
+        /// exactly the input without any delay.
+        #[test]
+        fn no_latency_without_oversampling() {
+            let mut melter = Melter::new_offline(1, 48000.0, 256, 0);
+            let params = MelterParams {
+                mix: FloatParam::new("Mix", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+                oversampling_factor: oversampling_factor_param(0),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let input: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+            let mut channels = vec![input.clone()];
+            melter.process_offline(&mut channels);
+
+            assert_eq!(melter.latency_samples, 0);
+            assert_eq!(channels[0], input);
+        }
+
+        /// With oversampling and the mix turned all the way down, the output should be exactly
+        /// the input delayed by the reported latency.
+        #[test]
+        fn dry_mix_is_latency_compensated_input() {
+            let mut melter = initialized_melter(1, 48000.0, 256);
+            let params = MelterParams {
+                mix: FloatParam::new("Mix", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+                oversampling_factor: oversampling_factor_param(2),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+            // Switching from the default factor fades the output out and back in first
+            melter.process_offline(&mut [vec![0.0; OVERSAMPLING_FADE_SAMPLES * 4]]);
+            assert!(!melter.oversampling_fader.is_fading());
+
+            let latency = melter.latency_samples as usize;
+            assert!(latency > 0);
+            let input: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+            let mut channels = vec![input.clone()];
+            melter.process_offline(&mut channels);
+
+            for (i, sample) in channels[0].iter().enumerate().skip(latency) {
+                assert_eq!(*sample, input[i - latency], "sample {i}");
+            }
+        }
+
+        #[test]
+        fn latency_in_milliseconds() {
+            let melter = Melter::new_offline(1, 48000.0, 256, 2);
+            let latency_samples =
+                oversampling::Lanczos3Oversampler::new(256, MAX_OVERSAMPLING_FACTOR).latency(2);
+            assert!(latency_samples > 0);
+            approx::assert_relative_eq!(melter.latency_ms(), latency_samples as f32 / 48.0);
+
+            let melter = Melter::new_offline(1, 48000.0, 256, 0);
+            assert_eq!(melter.latency_ms(), 0.0);
+        }
+    }
+
+    mod non_finite {
+        use super::*;
+
+        fn sine(num_samples: usize) -> Vec<f32> {
+            (0..num_samples)
+                .map(|i| (i as f32 * 0.05).sin() * 0.5)
+                .collect()
+        }
+
+        fn rms(signal: &[f32]) -> f32 {
+            (signal.iter().map(|sample| sample * sample).sum::<f32>() / signal.len() as f32).sqrt()
+        }
+
+        #[test]
+        fn recovers_from_nan_input() {
+            const NUM_SAMPLES: usize = 4800;
+            const NAN_IDX: usize = 1000;
+
+            let mut reference_melter = initialized_melter(2, 48000.0, 256);
+            let mut reference = vec![sine(NUM_SAMPLES), sine(NUM_SAMPLES)];
+            reference_melter.process_offline(&mut reference);
+
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let mut channels = vec![sine(NUM_SAMPLES), sine(NUM_SAMPLES)];
+            channels[0][NAN_IDX] = f32::NAN;
+            channels[1][NAN_IDX] = f32::INFINITY;
+            melter.process_offline(&mut channels);
+
+            for (channel, reference) in channels.iter().zip(&reference) {
+                assert!(channel.iter().all(|sample| sample.is_finite()));
+
+                // Within a block of the bad sample the channel is producing sound again
+                let recovered = &channel[NAN_IDX + DEFAULT_MAX_BLOCK_SIZE * 2..NAN_IDX + 1000];
+                assert!(rms(recovered) > 0.1, "{}", rms(recovered));
+
+                let tail = NUM_SAMPLES - 1000..;
+                approx::assert_relative_eq!(
+                    rms(&channel[tail.clone()]),
+                    rms(&reference[tail]),
+                    max_relative = 0.05
+                );
+            }
+        }
+    }
+
+    mod tone {
+        use super::*;
+
+        /// The tone is fully open by default, so the top of the audible range comes out as loud as
+        /// the mids. The shapers are mixed out so the rest of the chain is linear, and the
+        /// oversampling is turned off since its filters have a passband ripple of their own.
+        #[test]
+        fn default_tone_is_flat_at_the_top() {
+            const AMPLITUDE: f32 = 0.01;
+
+            let level_db = |period: usize| {
+                let mut melter = initialized_melter(1, 96000.0, 256);
+                let params = MelterParams {
+                    drive_mix: FloatParam::new(
+                        "Drive Mix",
+                        0.0,
+                        FloatRange::Linear { min: 0.0, max: 1.0 },
+                    ),
+                    oversampling_factor: oversampling_factor_param(0),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+                melter.process_offline(&mut [vec![0.0; OVERSAMPLING_FADE_SAMPLES]]);
+
+                let mut channels = vec![(0..48000)
+                    .map(|i| (std::f32::consts::TAU * i as f32 / period as f32).sin() * AMPLITUDE)
+                    .collect::<Vec<f32>>()];
+                melter.process_offline(&mut channels);
+
+                util::gain_to_db(harmonic_amplitude(&channels[0], period, 100, 1) / AMPLITUDE)
+            };
+
+            // 1 kHz, and 16 kHz and 19.2 kHz at 96 kHz
+            let mids_db = level_db(96);
+            for period in [6, 5] {
+                let level_db = level_db(period);
+                assert!(
+                    (level_db - mids_db).abs() < 0.1,
+                    "period {period}: {level_db} dB vs {mids_db} dB"
+                );
+            }
+        }
+    }
+
+    mod output_gain {
+        use super::*;
+
+        #[test]
+        fn defaults_to_unity() {
+            let params = MelterParams::default();
+            assert_eq!(params.output_gain.default_plain_value(), 1.0);
+        }
+
+        /// The output gain comes after everything else, so changing it should change the level
+        /// coming out of the plugin by exactly that many decibels.
+        #[test]
+        fn applies_known_db_change() {
+            const PERIOD: usize = 48;
+
+            let level_db = |output_gain_db: f32| {
+                let mut melter = initialized_melter(1, 48000.0, 256);
+                let params = MelterParams {
+                    output_gain: output_gain_param(output_gain_db),
+                    safety_limit: BoolParam::new("Safety Limiter", false),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                let mut channels = vec![(0..PERIOD * 200)
+                    .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.5)
+                    .collect::<Vec<f32>>()];
+                melter.process_offline(&mut channels);
+
+                util::gain_to_db(harmonic_amplitude(&channels[0], PERIOD, 100, 1))
+            };
+
+            let unity_db = level_db(0.0);
+            assert!(unity_db.is_finite() && unity_db > -40.0, "{unity_db} dB");
+            for output_gain_db in [-6.0, 6.0] {
+                approx::assert_relative_eq!(
+                    level_db(output_gain_db) - unity_db,
+                    output_gain_db,
+                    epsilon = 1e-3
+                );
+            }
+        }
+    }
+
+    mod render {
+        use super::*;
+
+        #[test]
+        fn flat_eq_impulse_is_a_unit_sample() {
+            let melter = initialized_melter(1, 44100.0, 64);
+            let impulse = melter.render_eq_impulse(256);
+            assert_eq!(impulse.len(), 256);
+            assert_eq!(impulse[0], 1.0);
+            assert!(
+                impulse[1..].iter().all(|&sample| sample == 0.0),
+                "{impulse:?}"
+            );
+        }
+
+        /// The impulse response's sum is the EQ's gain at DC, which only the low shelf affects.
+        #[test]
+        fn eq_impulse_follows_the_low_shelf() {
+            let mut melter = initialized_melter(1, 44100.0, 64);
+            let params = MelterParams {
+                low_boost: eq_boost_param("Low Boost", 6.0),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let impulse = melter.render_eq_impulse(44100);
+            approx::assert_relative_eq!(
+                util::gain_to_db(impulse.iter().sum::<f32>()),
+                6.0,
+                epsilon = 0.01
+            );
+        }
+
+        #[test]
+        fn transfer_curve_matches_the_shaper() {
+            let melter = initialized_melter(1, 44100.0, 64);
+            let curve = melter.render_transfer_curve(65);
+            assert_eq!(curve.len(), 65);
+            assert_eq!(curve[0].0, -1.0);
+            assert_eq!(curve[32].0, 0.0);
+            assert_eq!(curve[64].0, 1.0);
+
+            let drive = melter.params.drive.value();
+            let bias = melter.params.bias.value();
+            for (x, y) in curve {
+                assert_eq!(y, nonlinearity::cubic(x, drive, bias), "{x}");
+            }
+        }
+    }
+
+    mod eq_gain_range {
+        use super::*;
+
+        /// The magnitude of an impulse response's spectrum at `freq`, in decibels.
+        fn response_db(impulse: &[f32], freq: f32, sample_rate: f32) -> f32 {
+            let omega = std::f32::consts::TAU * freq / sample_rate;
+            let (re, im) = impulse
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (n, sample)| {
+                    let phase = omega * n as f32;
+                    (re + sample * phase.cos(), im - sample * phase.sin())
+                });
+            util::gain_to_db((re * re + im * im).sqrt())
+        }
+
+        #[test]
+        fn boosts_go_up_to_24_db() {
+            let params = MelterParams::default();
+            for boost in [&params.low_boost, &params.mid_boost, &params.high_boost] {
+                assert_eq!(boost.preview_plain(0.0), -24.0);
+                assert_eq!(boost.preview_plain(1.0), 24.0);
+            }
+        }
+
+        #[test]
+        fn full_boost_is_accurate_and_stable() {
+            const SAMPLE_RATE: f32 = 44100.0;
+
+            for (gain_db, eq_auto_gain) in [(24.0, false), (-24.0, false), (24.0, true)] {
+                let mut melter = initialized_melter(1, SAMPLE_RATE, 64);
+                let params = MelterParams {
+                    mid_boost: eq_boost_param("Mid Boost", gain_db),
+                    eq_auto_gain: BoolParam::new("EQ Auto Gain", eq_auto_gain),
+                    ..MelterParams::default()
+                };
+                reset_smoothers(&params);
+                melter.params = Arc::new(params);
+
+                let impulse = melter.render_eq_impulse(8192);
+                assert!(impulse.iter().all(|sample| sample.is_finite()));
+                assert!(
+                    impulse[4096..].iter().all(|sample| sample.abs() < 1e-6),
+                    "{gain_db} dB doesn't decay"
+                );
+
+                let mid_freq = melter.params.mid_freq.value();
+                let center_db = response_db(&impulse, mid_freq, SAMPLE_RATE);
+                let far_db = response_db(&impulse, 20.0, SAMPLE_RATE);
+                if eq_auto_gain {
+                    // The makeup gain lowers everything, but the boost itself stays the same
+                    assert!(center_db < gain_db - 1.0, "{center_db}");
+                    approx::assert_relative_eq!(center_db - far_db, gain_db, epsilon = 0.1);
+                } else {
+                    approx::assert_relative_eq!(center_db, gain_db, epsilon = 0.01);
+                    approx::assert_relative_eq!(far_db, 0.0, epsilon = 0.1);
+                }
+            }
+        }
+    }
+
+    mod decorrelation {
+        use super::*;
+
+        const PERIOD: usize = 15;
+
+        /// Processes the same 3.2 kHz sine in both channels of the stereo layout and returns the
+        /// settled output channels.
+        fn process_mono_source(decorrelation: f32) -> (Vec<f32>, Vec<f32>) {
+            let mut melter = initialized_melter(2, 48000.0, 256);
+            let params = MelterParams {
+                decorrelation: FloatParam::new(
+                    "Decorrelation",
+                    decorrelation,
+                    FloatRange::Linear { min: 0.0, max: 1.0 },
+                ),
+                ..MelterParams::default()
+            };
+            reset_smoothers(&params);
+            melter.params = Arc::new(params);
+
+            let sine: Vec<f32> = (0..24000)
+                .map(|i| (std::f32::consts::TAU * i as f32 / PERIOD as f32).sin() * 0.5)
+                .collect();
+            let mut channels = vec![sine.clone(), sine];
+            melter.process_offline(&mut channels);
+
+            let right = channels.pop().unwrap().split_off(12000);
+            let left = channels.pop().unwrap().split_off(12000);
+            (left, right)
+        }
+
+        /// The largest jump between two consecutive samples in either channel.
+        fn max_step(channels: &[Vec<f32>]) -> f32 {
+            channels
+                .iter()
+                .flat_map(|channel| channel.windows(2).map(|pair| (pair[1] - pair[0]).abs()))
+                .fold(0.0, f32::max)
+        }
+
+        /// Automates the decorrelation from zero to a tiny amount and back while a low sine is
+        /// playing. The widener is faded in and out, so the output shouldn't jump any more than it
+        /// does without touching the decorrelation. The shapers are mixed out so the sine stays
+        /// smooth.
+        #[test]
+        fn automating_across_zero_doesnt_click() {
+            const BLOCK_SIZE: usize = 2400;
+
+            let process_with = |decorrelation: [f32; 3]| {
+                let mut melter = initialized_melter(2, 48000.0, BLOCK_SIZE as u32);
+                Arc::get_mut(&mut melter.params).unwrap().drive_mix =
+                    FloatParam::new("Drive Mix", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 });
+                let mut channels = vec![Vec::new(), Vec::new()];
+                let mut prev_decorrelation = 0.0;
+                for (block_idx, decorrelation) in decorrelation.into_iter().enumerate() {
+                    let params = Arc::get_mut(&mut melter.params).unwrap();
+                    params.decorrelation = FloatParam::new(
+                        "Decorrelation",
+                        decorrelation,
+                        FloatRange::Linear { min: 0.0, max: 1.0 },
+                    )
+                    .with_smoother(SmoothingStyle::Linear(50.0));
+                    params.decorrelation.smoothed.reset(prev_decorrelation);
+                    params
+                        .decorrelation
+                        .smoothed
+                        .set_target(48000.0, decorrelation);
+                    prev_decorrelation = decorrelation;
+
+                    let sine: Vec<f32> = (block_idx * BLOCK_SIZE..(block_idx + 1) * BLOCK_SIZE)
+                        .map(|i| (std::f32::consts::TAU * i as f32 / 480.0).sin() * 0.5)
+                        .collect();
+                    let mut block = vec![sine.clone(), sine];
+                    melter.process_offline(&mut block);
+                    for (channel, block) in channels.iter_mut().zip(block) {
+                        channel.extend(block);
+                    }
+                }
+
+                // The first block contains the latency and the filters settling
+                channels
+                    .into_iter()
+                    .map(|channel| channel[BLOCK_SIZE..].to_vec())
+                    .collect::<Vec<_>>()
+            };
+
+            let untouched = max_step(&process_with([0.0; 3]));
+            let automated = max_step(&process_with([0.0, 0.001, 0.0]));
+            assert!(automated < untouched * 1.5, "{automated} vs {untouched}");
+        }
+
+        fn mono_sum_energy(left: &[f32], right: &[f32]) -> f32 {
+            left.iter()
+                .zip(right)
+                .map(|(l, r)| ((l + r) * 0.5).powi(2))
+                .sum()
+        }
+
+        #[test]
+        fn zero_decorrelation_keeps_mono_sources_mono() {
+            let (left, right) = process_mono_source(0.0);
+            assert_eq!(left, right);
+        }
+
+        /// Unlike widening with the side signal, the decorrelated channels don't cancel out when
+        /// they're summed to mono.
+        #[test]
+        fn mono_sum_keeps_most_energy() {
+            let (left, right) = process_mono_source(0.0);
+            let mono_energy = mono_sum_energy(&left, &right);
+
+            let (left, right) = process_mono_source(1.0);
+            let side_energy: f32 = left
+                .iter()
+                .zip(&right)
+                .map(|(l, r)| ((l - r) * 0.5).powi(2))
+                .sum();
+            assert!(
+                side_energy > mono_energy * 0.1,
+                "{side_energy} vs {mono_energy}"
+            );
+
+            let decorrelated_energy = mono_sum_energy(&left, &right);
+            assert!(
+                decorrelated_energy > mono_energy * 0.5,
+                "{decorrelated_energy} vs {mono_energy}"
+            );
+        }
+    }
+}
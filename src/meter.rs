@@ -0,0 +1,219 @@
+use atomic_float::AtomicF32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// How long it takes for the peak level to fall to a quarter of its value after a peak, in
+/// milliseconds.
+const PEAK_DECAY_MS: f32 = 150.0;
+
+/// Measures the output level in `process()` and publishes it through atomics so an editor can
+/// display it. The levels are stored as linear gain values.
+#[derive(Debug)]
+pub struct OutputMeter {
+    peak: Arc<AtomicF32>,
+    rms: Arc<AtomicF32>,
+    /// The decaying peak level, this is also stored in `peak` after every block.
+    peak_hold: f32,
+    /// The per-sample weight of the previous peak level while it decays.
+    peak_decay_weight: f32,
+}
+
+impl OutputMeter {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut meter = Self {
+            peak: Arc::new(AtomicF32::new(0.0)),
+            rms: Arc::new(AtomicF32::new(0.0)),
+            peak_hold: 0.0,
+            peak_decay_weight: 0.0,
+        };
+        meter.set_sample_rate(sample_rate);
+
+        meter
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.peak_decay_weight = 0.25f32.powf((sample_rate * PEAK_DECAY_MS / 1000.0).recip());
+    }
+
+    /// The peak level with a decaying hold, shared with the editor.
+    #[allow(dead_code)]
+    pub fn peak(&self) -> Arc<AtomicF32> {
+        self.peak.clone()
+    }
+
+    /// The RMS level of the last processed block, shared with the editor.
+    #[allow(dead_code)]
+    pub fn rms(&self) -> Arc<AtomicF32> {
+        self.rms.clone()
+    }
+
+    pub fn reset(&mut self) {
+        self.peak_hold = 0.0;
+        self.peak.store(0.0, Ordering::Relaxed);
+        self.rms.store(0.0, Ordering::Relaxed);
+    }
+
+    /// Measure a block of output. The peak is taken over all channels, and the RMS level is
+    /// computed over the entire block.
+    pub fn update(&mut self, channels: &[&mut [f32]]) {
+        let num_samples = channels.first().map_or(0, |channel| channel.len());
+        if num_samples == 0 {
+            return;
+        }
+
+        let mut sum_squares = 0.0;
+        for sample_idx in 0..num_samples {
+            let mut amplitude = 0.0f32;
+            for channel in channels {
+                let sample = channel[sample_idx];
+                amplitude = amplitude.max(sample.abs());
+                sum_squares += sample * sample;
+            }
+
+            self.peak_hold = if amplitude > self.peak_hold {
+                amplitude
+            } else {
+                self.peak_hold * self.peak_decay_weight + amplitude * (1.0 - self.peak_decay_weight)
+            };
+        }
+
+        let rms = (sum_squares / (num_samples * channels.len()) as f32).sqrt();
+        self.peak.store(self.peak_hold, Ordering::Relaxed);
+        self.rms.store(rms, Ordering::Relaxed);
+    }
+}
+
+/// Measures the DC offset of a signal as the mean of the samples over a block, and publishes it
+/// through an atomic so an editor can display it. The samples are added one at a time while
+/// they're being processed, and the mean is published with [`publish()`][Self::publish()].
+#[derive(Debug)]
+pub struct DcMeter {
+    dc: Arc<AtomicF32>,
+    sum: f64,
+    num_samples: usize,
+}
+
+impl DcMeter {
+    pub fn new() -> Self {
+        Self {
+            dc: Arc::new(AtomicF32::new(0.0)),
+            sum: 0.0,
+            num_samples: 0,
+        }
+    }
+
+    /// The DC offset of the last published block, shared with the editor.
+    #[allow(dead_code)]
+    pub fn dc(&self) -> Arc<AtomicF32> {
+        self.dc.clone()
+    }
+
+    pub fn reset(&mut self) {
+        self.sum = 0.0;
+        self.num_samples = 0;
+        self.dc.store(0.0, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn add(&mut self, sample: f32) {
+        self.sum += sample as f64;
+        self.num_samples += 1;
+    }
+
+    /// Publish the mean of the samples added since the last call. Nothing changes if no samples
+    /// were added.
+    pub fn publish(&mut self) {
+        if self.num_samples > 0 {
+            self.dc.store(
+                (self.sum / self.num_samples as f64) as f32,
+                Ordering::Relaxed,
+            );
+            self.sum = 0.0;
+            self.num_samples = 0;
+        }
+    }
+}
+
+impl Default for DcMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    fn sine_block(amplitude: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (i as f32 * 0.01 * std::f32::consts::TAU).sin() * amplitude)
+            .collect()
+    }
+
+    #[test]
+    fn tracks_sine_amplitude() {
+        let mut meter = OutputMeter::new(SAMPLE_RATE);
+        let peak = meter.peak();
+        let rms = meter.rms();
+
+        let mut left = sine_block(0.5, 1000);
+        let mut right = left.clone();
+        meter.update(&[&mut left, &mut right]);
+
+        approx::assert_relative_eq!(peak.load(Ordering::Relaxed), 0.5, epsilon = 1e-3);
+        approx::assert_relative_eq!(
+            rms.load(Ordering::Relaxed),
+            0.5 / 2.0f32.sqrt(),
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn peak_decays_after_silence() {
+        let mut meter = OutputMeter::new(SAMPLE_RATE);
+        meter.update(&[&mut sine_block(1.0, 1000)]);
+
+        // The peak should fall to a quarter over the decay time
+        let decay_samples = (SAMPLE_RATE * PEAK_DECAY_MS / 1000.0) as usize;
+        meter.update(&[&mut vec![0.0; decay_samples]]);
+        approx::assert_relative_eq!(meter.peak().load(Ordering::Relaxed), 0.25, epsilon = 1e-2);
+        assert_eq!(meter.rms().load(Ordering::Relaxed), 0.0);
+    }
+
+    #[test]
+    fn reset_clears_levels() {
+        let mut meter = OutputMeter::new(SAMPLE_RATE);
+        meter.update(&[&mut sine_block(1.0, 100)]);
+        meter.reset();
+
+        assert_eq!(meter.peak().load(Ordering::Relaxed), 0.0);
+        assert_eq!(meter.rms().load(Ordering::Relaxed), 0.0);
+    }
+
+    mod dc_meter {
+        use super::*;
+
+        #[test]
+        fn measures_offset_of_biased_sine() {
+            let mut meter = DcMeter::new();
+            for sample in sine_block(0.5, 1000) {
+                meter.add(sample + 0.2);
+            }
+            meter.publish();
+
+            approx::assert_relative_eq!(meter.dc().load(Ordering::Relaxed), 0.2, epsilon = 1e-4);
+        }
+
+        #[test]
+        fn keeps_value_without_samples() {
+            let mut meter = DcMeter::new();
+            meter.add(-0.5);
+            meter.publish();
+            meter.publish();
+
+            assert_eq!(meter.dc().load(Ordering::Relaxed), -0.5);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::f32::consts::TAU;
+
+/// The shapes the LFO can take, in parameter order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LfoWaveform {
+    Sine,
+    Triangle,
+    Saw,
+}
+
+impl LfoWaveform {
+    pub const ALL: [LfoWaveform; 3] = [LfoWaveform::Sine, LfoWaveform::Triangle, LfoWaveform::Saw];
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LfoWaveform::Sine => "Sine",
+            LfoWaveform::Triangle => "Triangle",
+            LfoWaveform::Saw => "Saw",
+        }
+    }
+
+    /// The waveform's value in `[-1, 1]` at a phase in `[0, 1)`. All shapes start at zero and
+    /// rise from there.
+    pub fn value(self, phase: f32) -> f32 {
+        match self {
+            LfoWaveform::Sine => (phase * TAU).sin(),
+            LfoWaveform::Triangle => {
+                if phase < 0.25 {
+                    phase * 4.0
+                } else if phase < 0.75 {
+                    2.0 - phase * 4.0
+                } else {
+                    phase * 4.0 - 4.0
+                }
+            }
+            LfoWaveform::Saw => {
+                if phase < 0.5 {
+                    phase * 2.0
+                } else {
+                    phase * 2.0 - 2.0
+                }
+            }
+        }
+    }
+}
+
+/// The parameters the LFO can modulate, in parameter order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LfoDestination {
+    Drive,
+    MidFreq,
+    ToneCutoff,
+}
+
+impl LfoDestination {
+    pub const ALL: [LfoDestination; 3] = [
+        LfoDestination::Drive,
+        LfoDestination::MidFreq,
+        LfoDestination::ToneCutoff,
+    ];
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LfoDestination::Drive => "Drive",
+            LfoDestination::MidFreq => "Mid Frequency",
+            LfoDestination::ToneCutoff => "Tone",
+        }
+    }
+}
+
+/// The note lengths a tempo synced LFO cycle can take, in parameter order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteDivision {
+    Whole,
+    Half,
+    HalfDotted,
+    HalfTriplet,
+    Quarter,
+    QuarterDotted,
+    QuarterTriplet,
+    Eighth,
+    EighthDotted,
+    EighthTriplet,
+    Sixteenth,
+    SixteenthDotted,
+    SixteenthTriplet,
+    ThirtySecond,
+}
+
+impl NoteDivision {
+    pub const ALL: [NoteDivision; 14] = [
+        NoteDivision::Whole,
+        NoteDivision::Half,
+        NoteDivision::HalfDotted,
+        NoteDivision::HalfTriplet,
+        NoteDivision::Quarter,
+        NoteDivision::QuarterDotted,
+        NoteDivision::QuarterTriplet,
+        NoteDivision::Eighth,
+        NoteDivision::EighthDotted,
+        NoteDivision::EighthTriplet,
+        NoteDivision::Sixteenth,
+        NoteDivision::SixteenthDotted,
+        NoteDivision::SixteenthTriplet,
+        NoteDivision::ThirtySecond,
+    ];
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoteDivision::Whole => "1/1",
+            NoteDivision::Half => "1/2",
+            NoteDivision::HalfDotted => "1/2D",
+            NoteDivision::HalfTriplet => "1/2T",
+            NoteDivision::Quarter => "1/4",
+            NoteDivision::QuarterDotted => "1/4D",
+            NoteDivision::QuarterTriplet => "1/4T",
+            NoteDivision::Eighth => "1/8",
+            NoteDivision::EighthDotted => "1/8D",
+            NoteDivision::EighthTriplet => "1/8T",
+            NoteDivision::Sixteenth => "1/16",
+            NoteDivision::SixteenthDotted => "1/16D",
+            NoteDivision::SixteenthTriplet => "1/16T",
+            NoteDivision::ThirtySecond => "1/32",
+        }
+    }
+
+    /// The length of the note in quarter note beats.
+    pub fn beats(self) -> f32 {
+        match self {
+            NoteDivision::Whole => 4.0,
+            NoteDivision::Half => 2.0,
+            NoteDivision::HalfDotted => 3.0,
+            NoteDivision::HalfTriplet => 4.0 / 3.0,
+            NoteDivision::Quarter => 1.0,
+            NoteDivision::QuarterDotted => 1.5,
+            NoteDivision::QuarterTriplet => 2.0 / 3.0,
+            NoteDivision::Eighth => 0.5,
+            NoteDivision::EighthDotted => 0.75,
+            NoteDivision::EighthTriplet => 1.0 / 3.0,
+            NoteDivision::Sixteenth => 0.25,
+            NoteDivision::SixteenthDotted => 0.375,
+            NoteDivision::SixteenthTriplet => 1.0 / 6.0,
+            NoteDivision::ThirtySecond => 0.125,
+        }
+    }
+
+    /// The rate at which one LFO cycle lasts exactly this note at the given tempo.
+    pub fn rate_hz(self, tempo_bpm: f32) -> f32 {
+        tempo_bpm / 60.0 / self.beats()
+    }
+}
+
+/// A low frequency oscillator. This is meant to be advanced once per block, so it doesn't need to
+/// be any more precise than that.
+#[derive(Debug)]
+pub struct Lfo {
+    /// The position within the current cycle, in `[0, 1)`.
+    phase: f32,
+    sample_rate: f32,
+}
+
+impl Lfo {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            phase: 0.0,
+            sample_rate,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Get the LFO's value at the start of a block of `num_samples` samples, and then advance it
+    /// past that block.
+    pub fn next_block(&mut self, waveform: LfoWaveform, rate_hz: f32, num_samples: usize) -> f32 {
+        let value = waveform.value(self.phase);
+        self.phase = (self.phase + rate_hz * num_samples as f32 / self.sample_rate).fract();
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod waveforms {
+        use super::*;
+
+        #[test]
+        fn quarter_points() {
+            for waveform in [LfoWaveform::Sine, LfoWaveform::Triangle] {
+                approx::assert_relative_eq!(waveform.value(0.0), 0.0);
+                approx::assert_relative_eq!(waveform.value(0.25), 1.0);
+                approx::assert_relative_eq!(waveform.value(0.5), 0.0, epsilon = 1e-6);
+                approx::assert_relative_eq!(waveform.value(0.75), -1.0);
+            }
+
+            approx::assert_relative_eq!(LfoWaveform::Saw.value(0.0), 0.0);
+            approx::assert_relative_eq!(LfoWaveform::Saw.value(0.25), 0.5);
+            approx::assert_relative_eq!(LfoWaveform::Saw.value(0.5), -1.0);
+            approx::assert_relative_eq!(LfoWaveform::Saw.value(0.75), -0.5);
+        }
+
+        #[test]
+        fn stays_in_range() {
+            for waveform in LfoWaveform::ALL {
+                for step in 0..1000 {
+                    let value = waveform.value(step as f32 / 1000.0);
+                    assert!((-1.0..=1.0).contains(&value), "{:?}: {}", waveform, value);
+                }
+            }
+        }
+    }
+
+    mod note_division {
+        use super::*;
+
+        #[test]
+        fn rates_at_120_bpm() {
+            approx::assert_relative_eq!(NoteDivision::Whole.rate_hz(120.0), 0.5);
+            approx::assert_relative_eq!(NoteDivision::Quarter.rate_hz(120.0), 2.0);
+            approx::assert_relative_eq!(NoteDivision::QuarterDotted.rate_hz(120.0), 4.0 / 3.0);
+            approx::assert_relative_eq!(NoteDivision::EighthTriplet.rate_hz(120.0), 6.0);
+            approx::assert_relative_eq!(NoteDivision::ThirtySecond.rate_hz(120.0), 16.0);
+        }
+
+        #[test]
+        fn names_are_unique() {
+            for (i, a) in NoteDivision::ALL.iter().enumerate() {
+                for b in &NoteDivision::ALL[i + 1..] {
+                    assert_ne!(a.name(), b.name());
+                }
+            }
+        }
+    }
+
+    mod lfo {
+        use super::*;
+
+        #[test]
+        fn completes_a_cycle_at_the_rate() {
+            let mut lfo = Lfo::new(1000.0);
+            let values: Vec<f32> = (0..5)
+                .map(|_| lfo.next_block(LfoWaveform::Triangle, 2.0, 125))
+                .collect();
+
+            // Each block is a quarter of a cycle at 2 Hz
+            for (value, expected) in values.iter().zip([0.0, 1.0, 0.0, -1.0, 0.0]) {
+                approx::assert_relative_eq!(*value, expected, epsilon = 1e-5);
+            }
+        }
+
+        #[test]
+        fn reset_restarts_the_cycle() {
+            let mut lfo = Lfo::new(1000.0);
+            lfo.next_block(LfoWaveform::Sine, 1.0, 300);
+            lfo.reset();
+
+            assert_eq!(lfo.next_block(LfoWaveform::Sine, 1.0, 300), 0.0);
+        }
+    }
+}
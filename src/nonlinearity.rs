@@ -1,24 +1,36 @@
-#![allow(dead_code)]
-
 use std::f32::consts::PI;
 
 #[inline(always)]
 pub fn bridge_rectifier(input: f32) -> f32 {
-    input.abs().min(PI).sin()
+    // `PI` in f32 is slightly larger than π, so its sine is slightly negative
+    input.abs().min(PI).sin().max(0.0)
 }
 
 #[inline(always)]
-pub fn cubic(x: f32, drive: f32, offset: f32) -> f32 {
-    #[inline(always)]
-    fn clip(lo: f32, hi: f32, x: f32) -> f32 {
-        x.max(lo).min(hi)
-    }
+fn clip(lo: f32, hi: f32, x: f32) -> f32 {
+    x.max(lo).min(hi)
+}
 
-    #[inline(always)]
-    fn c3(x: f32) -> f32 {
-        x - x * x * x / 3.0
+#[inline(always)]
+fn c3(x: f32) -> f32 {
+    x - x * x * x / 3.0
+}
+
+// The antiderivative of `c3(clip(-1.0, 1.0, x))`
+#[inline(always)]
+fn c3_clipped_antiderivative(x: f32) -> f32 {
+    if x > 1.0 {
+        5.0 / 12.0 + 2.0 / 3.0 * (x - 1.0)
+    } else if x < -1.0 {
+        5.0 / 12.0 - 2.0 / 3.0 * (x + 1.0)
+    } else {
+        let x2 = x * x;
+        x2 / 2.0 - x2 * x2 / 12.0
     }
+}
 
+#[inline(always)]
+pub fn cubic(x: f32, drive: f32, offset: f32) -> f32 {
     // Calculate pregain
     let pregain = 10.0f32.powf(2.0 * drive);
 
@@ -32,3 +44,949 @@ pub fn cubic(x: f32, drive: f32, offset: f32) -> f32 {
     let postgain = 1.0f32.max(1.0 / pregain);
     result * postgain
 }
+
+// The number of samples `cubic_block()` shapes at once. Four lanes fit in a single SSE or NEON
+// register.
+const LANES: usize = 4;
+
+// Apply `cubic()` to a whole block of samples with a fixed drive and offset. The gains are only
+// computed once, and the samples are shaped `LANES` at a time in fixed size chunks the compiler can
+// vectorize. Every lane goes through the same operations in the same order as `cubic()`, so the
+// output is identical to calling it for every sample. The samples left over at the end of the
+// block use the scalar `cubic()`.
+pub fn cubic_block(samples: &mut [f32], drive: f32, offset: f32) {
+    let pregain = 10.0f32.powf(2.0 * drive);
+    let postgain = 1.0f32.max(1.0 / pregain);
+
+    let mut chunks = samples.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        let lanes: &mut [f32; LANES] = chunk.try_into().unwrap();
+        for lane in lanes {
+            *lane = c3(clip(-1.0, 1.0, *lane * pregain + offset)) * postgain;
+        }
+    }
+    for sample in chunks.into_remainder() {
+        *sample = cubic(*sample, drive, offset);
+    }
+}
+
+// The input RMS level the drive compensation is calibrated for, about -12 dBFS
+const DRIVE_COMPENSATION_REFERENCE_RMS: f32 = 0.25;
+
+// Estimates the output RMS of `cubic()` for a noise-like input with the given RMS level after the
+// pregain has been applied. This is linear for quiet signals and levels off as the clipped cubic
+// saturates, and it's within about 0.4 dB of the measured curve.
+#[inline(always)]
+fn cubic_output_rms_estimate(input_rms: f32) -> f32 {
+    const SATURATED_RMS: f32 = 0.65;
+
+    input_rms / (1.0 + (input_rms / SATURATED_RMS).powi(2)).sqrt()
+}
+
+// The gain that keeps the RMS level of `cubic()` roughly constant across the drive range. The
+// pregain makes the output louder until the cubic saturates, so this is the inverse of that
+// increase for a signal at the reference level. The postgain already handles negative drive
+// values, so those are left alone.
+#[inline(always)]
+pub fn cubic_drive_compensation(drive: f32) -> f32 {
+    let pregain = 10.0f32.powf(2.0 * drive.max(0.0));
+
+    cubic_output_rms_estimate(DRIVE_COMPENSATION_REFERENCE_RMS)
+        / cubic_output_rms_estimate(DRIVE_COMPENSATION_REFERENCE_RMS * pregain)
+}
+
+#[inline(always)]
+pub fn atan_sat(x: f32, drive: f32) -> f32 {
+    // Uses the same pregain curve as `cubic()`
+    let pregain = 10.0f32.powf(2.0 * drive);
+
+    // Normalize so unity input maps to unity output, this is a smooth curve without a hard knee so
+    // it aliases very little inside of the oversampler
+    let postgain = 1.0 / ((2.0 / PI) * pregain.atan());
+    (2.0 / PI) * (pregain * x).atan() * postgain
+}
+
+// Clamps the input to +/- `threshold`. The hard corners alias badly, so this relies on the
+// oversampler to keep the aliasing down and works best at oversampling factor 4 (16x).
+#[inline(always)]
+pub fn hard_clip(x: f32, threshold: f32) -> f32 {
+    x.clamp(-threshold, threshold)
+}
+
+// Reflects anything past +/- `threshold` back towards zero, repeatedly if needed. This is the same
+// as the classic `while x.abs() > t { x = x.signum() * 2.0 * t - x }` loop, but written as a
+// triangle wave so it takes the same amount of time for every input.
+#[inline(always)]
+pub fn foldback(x: f32, threshold: f32) -> f32 {
+    if threshold <= 0.0 {
+        return 0.0;
+    }
+
+    threshold - ((x + threshold).rem_euclid(4.0 * threshold) - 2.0 * threshold).abs()
+}
+
+// Evaluates the Chebyshev polynomial of the first kind `T_order(x)`. A full scale sine wave comes
+// out as just its `order`th harmonic, which makes this useful for adding specific harmonics. The
+// input is clamped to -1 to 1 since the polynomials grow very quickly outside of that range.
+#[inline(always)]
+pub fn chebyshev(x: f32, order: u32) -> f32 {
+    let x = clip(-1.0, 1.0, x);
+    if order == 0 {
+        return 1.0;
+    }
+
+    // T_(n+1)(x) = 2x * T_n(x) - T_(n-1)(x)
+    let (mut prev, mut current) = (1.0, x);
+    for _ in 1..order {
+        (prev, current) = (current, 2.0 * x * current - prev);
+    }
+
+    current
+}
+
+// A sine wavefolder, the signal folds back on itself more times the higher the drive. At minimum
+// drive unity input maps to unity output without any folding. Folding creates a lot of high
+// harmonics, so this needs to run inside the oversampler.
+#[inline(always)]
+pub fn wavefold(x: f32, drive: f32) -> f32 {
+    let folds = 1.0 + 4.0 * drive;
+    (folds * PI / 2.0 * x).sin()
+}
+
+// Quantizes the input in the -1 to 1 range to `2^bits` evenly spaced levels. Fractional bit depths
+// are allowed so the parameter can be swept smoothly.
+#[inline(always)]
+pub fn bit_crush(x: f32, bits: f32) -> f32 {
+    let step = 2.0 / (2.0f32.powf(bits) - 1.0);
+    let quantized = ((x.clamp(-1.0, 1.0) + 1.0) / step).round() * step - 1.0;
+    quantized.clamp(-1.0, 1.0)
+}
+
+// The same curve as `cubic()` with first order antiderivative anti-aliasing. Instead of evaluating
+// the curve at every sample, this takes the average of the curve between the previous and the
+// current sample using its antiderivative. That smooths out the clipping edges and greatly reduces
+// aliasing, at the cost of half a sample of delay.
+pub struct CubicADAA {
+    prev_input: f32,
+}
+
+impl CubicADAA {
+    // Below this input difference the antiderivative quotient becomes numerically unstable
+    const EPSILON: f32 = 1e-5;
+
+    pub fn new() -> Self {
+        CubicADAA { prev_input: 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, x: f32, drive: f32, offset: f32) -> f32 {
+        let pregain = 10.0f32.powf(2.0 * drive);
+        let postgain = 1.0f32.max(1.0 / pregain);
+
+        // The anti-aliasing is applied to the clipped cubic itself, so this works on the signal
+        // after the pregain and the offset have been applied
+        let input = x * pregain + offset;
+        let prev_input = self.prev_input;
+        self.prev_input = input;
+
+        let delta = input - prev_input;
+        let result = if delta.abs() < Self::EPSILON {
+            c3(clip(-1.0, 1.0, (input + prev_input) / 2.0))
+        } else {
+            (c3_clipped_antiderivative(input) - c3_clipped_antiderivative(prev_input)) / delta
+        };
+
+        result * postgain
+    }
+}
+
+impl Default for CubicADAA {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Crossfades between the clean and the shaped signal, a `mix` of 0 returns `dry` unchanged
+#[inline(always)]
+pub fn dry_wet(dry: f32, wet: f32, mix: f32) -> f32 {
+    dry * (1.0 - mix) + wet * mix
+}
+
+// A slew rate limiter, the output can only move towards the input by a fixed amount per sample.
+// This smears fast transients and high frequencies. The rising and falling rates are separate, so
+// the limiting can be made asymmetric. The rates are in units per second so the sound doesn't
+// depend on the sample rate.
+pub struct SlewDistortion {
+    prev_output: f32,
+    sample_rate: f32,
+    pos_rate: f32,
+    neg_rate: f32,
+    // The maximum rising and falling steps per sample
+    pos_step: f32,
+    neg_step: f32,
+}
+
+impl SlewDistortion {
+    pub const DEFAULT_RATE: f32 = 20000.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut slew = SlewDistortion {
+            prev_output: 0.0,
+            sample_rate,
+            pos_rate: Self::DEFAULT_RATE,
+            neg_rate: Self::DEFAULT_RATE,
+            pos_step: 0.0,
+            neg_step: 0.0,
+        };
+        slew.update_steps();
+
+        slew
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_steps();
+    }
+
+    // Sets the maximum rising rate in units per second
+    pub fn set_pos_rate(&mut self, rate: f32) {
+        self.pos_rate = rate;
+        self.update_steps();
+    }
+
+    // Sets the maximum falling rate in units per second
+    pub fn set_neg_rate(&mut self, rate: f32) {
+        self.neg_rate = rate;
+        self.update_steps();
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_output = 0.0;
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output =
+            self.prev_output + (input - self.prev_output).clamp(-self.neg_step, self.pos_step);
+        self.prev_output = output;
+        output
+    }
+
+    fn update_steps(&mut self) {
+        self.pos_step = self.pos_rate / self.sample_rate;
+        self.neg_step = self.neg_rate / self.sample_rate;
+    }
+}
+
+// A diode clipper with a matched pair of diodes, this has a soft exponential knee
+#[inline(always)]
+pub fn diode_clip(x: f32, drive: f32) -> f32 {
+    let pregain = 10.0f32.powf(2.0 * drive);
+    x.signum() * (1.0 - (-(x * pregain).abs()).exp())
+}
+
+// A diode clipper where the negative half clips at half the level of the positive half, like a
+// mismatched diode pair. The slope is the same on both sides of zero so there's no kink, but the
+// asymmetry adds even harmonics.
+#[inline(always)]
+pub fn diode_clip_asymmetric(x: f32, drive: f32) -> f32 {
+    let pregain = 10.0f32.powf(2.0 * drive);
+    let x = x * pregain;
+    if x >= 0.0 {
+        1.0 - (-x).exp()
+    } else {
+        -0.5 * (1.0 - (2.0 * x).exp())
+    }
+}
+
+// Adds an even function of the input to the input itself, so the signal passes through unchanged
+// and only even harmonics are added on top, like a single-ended tube stage. The added curve is
+// `s^2 / (1 + s^2)` of the driven input `s`, which starts out as a pure second harmonic and levels
+// off at half of full scale for loud signals. This pushes the signal's center up, so it relies on
+// the DC blocker after the distortion to remove the offset again.
+#[inline(always)]
+pub fn even_harmonics(x: f32, drive: f32) -> f32 {
+    let pregain = 10.0f32.powf(2.0 * drive);
+    let squared = (x * pregain).powi(2);
+    x + 0.5 * squared / (1.0 + squared)
+}
+
+// A sample rate reducer, every input sample is held for `factor` samples. Fractional factors are
+// spread out over time with a phase accumulator. This is meant to alias, so it should run at the
+// base sample rate rather than inside the oversampler.
+pub struct Decimator {
+    hold: f32,
+    phase: f32,
+}
+
+impl Decimator {
+    pub fn new() -> Self {
+        Decimator {
+            hold: 0.0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.hold = 0.0;
+        self.phase = 0.0;
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, x: f32, factor: f32) -> f32 {
+        if self.phase <= 0.0 {
+            self.hold = x;
+            self.phase += factor.max(1.0);
+        }
+        self.phase -= 1.0;
+
+        self.hold
+    }
+}
+
+impl Default for Decimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The distortion algorithms that can be selected with the distortion type parameter, in parameter
+// order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistortionType {
+    Cubic,
+    BridgeRectifier,
+    Slew,
+    Arctan,
+    HardClip,
+    Wavefold,
+    Diode,
+    AsymmetricDiode,
+    Foldback,
+    Chebyshev,
+    EvenHarmonics,
+}
+
+impl DistortionType {
+    pub const ALL: [DistortionType; 11] = [
+        DistortionType::Cubic,
+        DistortionType::BridgeRectifier,
+        DistortionType::Slew,
+        DistortionType::Arctan,
+        DistortionType::HardClip,
+        DistortionType::Wavefold,
+        DistortionType::Diode,
+        DistortionType::AsymmetricDiode,
+        DistortionType::Foldback,
+        DistortionType::Chebyshev,
+        DistortionType::EvenHarmonics,
+    ];
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DistortionType::Cubic => "Cubic",
+            DistortionType::BridgeRectifier => "Bridge Rectifier",
+            DistortionType::Slew => "Slew",
+            DistortionType::Arctan => "Arctan",
+            DistortionType::HardClip => "Hard Clip",
+            DistortionType::Wavefold => "Wavefold",
+            DistortionType::Diode => "Diode",
+            DistortionType::AsymmetricDiode => "Asymmetric Diode",
+            DistortionType::Foldback => "Foldback",
+            DistortionType::Chebyshev => "Chebyshev",
+            DistortionType::EvenHarmonics => "Even Harmonics",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the amplitude of the `harmonic`th harmonic of a full scale sine wave with a
+    // period of 64 samples after it has been run through `f`
+    fn harmonic_amplitude(f: impl Fn(f32) -> f32, harmonic: usize) -> f32 {
+        const PERIOD: usize = 64;
+
+        let (mut re, mut im) = (0.0f32, 0.0f32);
+        for i in 0..PERIOD {
+            let phase = i as f32 / PERIOD as f32 * 2.0 * PI;
+            let output = f(phase.sin());
+            re += output * (phase * harmonic as f32).cos();
+            im += output * (phase * harmonic as f32).sin();
+        }
+
+        (re * re + im * im).sqrt() * 2.0 / PERIOD as f32
+    }
+
+    mod bridge_rectifier {
+        use super::*;
+        use crate::filters::DCBlocker;
+
+        #[test]
+        fn output_is_non_negative() {
+            for i in -1000..=1000 {
+                assert!(bridge_rectifier(i as f32 / 100.0) >= 0.0);
+            }
+        }
+
+        #[test]
+        fn doubles_the_frequency() {
+            let fundamental = harmonic_amplitude(bridge_rectifier, 1);
+            let octave = harmonic_amplitude(bridge_rectifier, 2);
+            assert!(fundamental < 1e-4);
+            assert!(octave > 0.1);
+        }
+
+        #[test]
+        fn dc_is_removed_by_dc_blocker() {
+            const SAMPLE_RATE: f32 = 48000.0;
+
+            let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+            let mut sum = 0.0f64;
+            for i in 0..(SAMPLE_RATE as usize * 2) {
+                let input = (i as f32 * 100.0 * 2.0 * PI / SAMPLE_RATE).sin();
+                let output = dc_blocker.process(bridge_rectifier(input));
+                if i >= SAMPLE_RATE as usize {
+                    sum += output as f64;
+                }
+            }
+
+            assert!((sum / SAMPLE_RATE as f64).abs() < 1e-3);
+        }
+    }
+
+    mod cubic {
+        use super::*;
+        use crate::filters::DCBlocker;
+
+        #[test]
+        fn zero_bias_is_symmetric() {
+            for x in [0.01, 0.1, 0.3, 1.0] {
+                assert_eq!(cubic(x, 0.5, 0.0), -cubic(-x, 0.5, 0.0));
+            }
+        }
+
+        #[test]
+        fn bias_dc_is_removed_by_dc_blocker() {
+            const SAMPLE_RATE: f32 = 48000.0;
+
+            for bias in [-1.0, -0.5, 0.5, 1.0] {
+                let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+
+                // Run a second of a 100 Hz sine through the biased shaper and the DC blocker, and
+                // then measure the DC offset over the next second
+                let mut sum = 0.0f64;
+                let mut peak = 0.0f32;
+                for i in 0..(SAMPLE_RATE as usize * 2) {
+                    let input = (i as f32 * 100.0 * 2.0 * PI / SAMPLE_RATE).sin() * 0.5;
+                    let output = dc_blocker.process(cubic(input, 0.5, bias));
+                    assert!(output.is_finite());
+
+                    if i >= SAMPLE_RATE as usize {
+                        sum += output as f64;
+                        peak = peak.max(output.abs());
+                    }
+                }
+
+                let dc = sum / SAMPLE_RATE as f64;
+                assert!(dc.abs() < 1e-3, "bias = {bias}, dc = {dc}");
+                assert!(peak < 2.0, "bias = {bias}, peak = {peak}");
+            }
+        }
+    }
+
+    mod drive_compensation {
+        use super::*;
+
+        // Low passed white noise from a xorshift generator, scaled to the given RMS level
+        fn pink_noise(num_samples: usize, rms: f32) -> Vec<f32> {
+            let mut state = 0x2545_f491u32;
+            let mut lowpassed = 0.0;
+            let noise: Vec<f32> = (0..num_samples)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let white = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                    lowpassed = lowpassed * 0.98 + white * 0.1;
+                    lowpassed * 0.5 + white * 0.05
+                })
+                .collect();
+
+            let noise_rms = measure_rms(&noise);
+            noise.iter().map(|x| x / noise_rms * rms).collect()
+        }
+
+        fn measure_rms(samples: &[f32]) -> f32 {
+            (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+        }
+
+        #[test]
+        fn no_compensation_without_drive() {
+            approx::assert_relative_eq!(cubic_drive_compensation(0.0), 1.0);
+            approx::assert_relative_eq!(cubic_drive_compensation(-0.5), 1.0);
+        }
+
+        #[test]
+        fn keeps_rms_constant_across_drive() {
+            let input = pink_noise(20000, DRIVE_COMPENSATION_REFERENCE_RMS);
+            let reference_rms = measure_rms(
+                &input
+                    .iter()
+                    .map(|x| cubic(*x, 0.0, 0.0))
+                    .collect::<Vec<_>>(),
+            );
+
+            for step in 0..=20 {
+                let drive = step as f32 * 0.1;
+                let compensation = cubic_drive_compensation(drive);
+                let output: Vec<f32> = input
+                    .iter()
+                    .map(|x| cubic(*x, drive, 0.0) * compensation)
+                    .collect();
+
+                let difference_db = 20.0 * (measure_rms(&output) / reference_rms).log10();
+                assert!(
+                    difference_db.abs() < 1.0,
+                    "drive {}: {} dB",
+                    drive,
+                    difference_db
+                );
+            }
+        }
+    }
+
+    mod cubic_adaa {
+        use super::*;
+
+        const NUM_SAMPLES: usize = 2048;
+        // 187 periods in `NUM_SAMPLES`, so the sine's harmonics all land exactly on DFT bins
+        const SINE_BIN: usize = 187;
+
+        // Returns the energy in all DFT bins that aren't the sine's harmonics below Nyquist,
+        // relative to the energy of the fundamental
+        fn relative_aliasing_energy(mut f: impl FnMut(f32) -> f32) -> f32 {
+            let mut output = vec![0.0; NUM_SAMPLES];
+            for i in 0..NUM_SAMPLES * 2 {
+                let phase = (i * SINE_BIN % NUM_SAMPLES) as f32 / NUM_SAMPLES as f32 * 2.0 * PI;
+                let y = f(phase.sin() * 0.5);
+                if i >= NUM_SAMPLES {
+                    output[i - NUM_SAMPLES] = y;
+                }
+            }
+
+            let cos_table: Vec<f32> = (0..NUM_SAMPLES)
+                .map(|i| (i as f32 / NUM_SAMPLES as f32 * 2.0 * PI).cos())
+                .collect();
+            let bin_energy = |bin: usize| {
+                let (mut re, mut im) = (0.0f32, 0.0f32);
+                for (i, y) in output.iter().enumerate() {
+                    let idx = i * bin % NUM_SAMPLES;
+                    re += y * cos_table[idx];
+                    im += y * cos_table[(idx + NUM_SAMPLES * 3 / 4) % NUM_SAMPLES];
+                }
+                re * re + im * im
+            };
+
+            let aliasing_energy: f32 = (1..NUM_SAMPLES / 2)
+                .filter(|bin| bin % SINE_BIN != 0)
+                .map(bin_energy)
+                .sum();
+            aliasing_energy / bin_energy(SINE_BIN)
+        }
+
+        #[test]
+        fn matches_cubic_for_slow_signals() {
+            let mut adaa = CubicADAA::new();
+            for i in 0..1000 {
+                let x = (i as f32 / 1000.0 * 2.0 * PI).sin() * 0.5;
+                let expected = cubic(x, 0.25, 0.0);
+                assert!((adaa.process(x, 0.25, 0.0) - expected).abs() < 0.05);
+            }
+        }
+
+        #[test]
+        fn reduces_aliasing() {
+            let naive = relative_aliasing_energy(|x| cubic(x, 0.5, 0.0));
+
+            let mut adaa = CubicADAA::new();
+            let anti_aliased = relative_aliasing_energy(|x| adaa.process(x, 0.5, 0.0));
+
+            assert!(
+                anti_aliased < naive * 0.25,
+                "naive = {naive}, anti-aliased = {anti_aliased}"
+            );
+        }
+
+        #[test]
+        fn constant_input_is_stable() {
+            let mut adaa = CubicADAA::new();
+            for _ in 0..10 {
+                adaa.process(0.3, 0.0, 0.5);
+            }
+
+            assert!((adaa.process(0.3, 0.0, 0.5) - cubic(0.3, 0.0, 0.5)).abs() < 1e-6);
+        }
+    }
+
+    mod atan_sat {
+        use super::*;
+
+        // A tanh saturator with the same pregain and normalization as `atan_sat()`
+        fn tanh_sat(x: f32, drive: f32) -> f32 {
+            let pregain = 10.0f32.powf(2.0 * drive);
+            (pregain * x).tanh() / pregain.tanh()
+        }
+
+        #[test]
+        fn unity_at_minimum_drive() {
+            assert!((atan_sat(1.0, 0.0) - 1.0).abs() < 1e-6);
+            assert!((atan_sat(-1.0, 0.0) + 1.0).abs() < 1e-6);
+        }
+
+        #[test]
+        fn is_bounded() {
+            for drive in [0.0, 0.5, 1.0, 2.0] {
+                assert!(atan_sat(1000.0, drive).abs() <= 2.0);
+            }
+        }
+
+        #[test]
+        fn gentler_knee_than_tanh() {
+            let drive = 0.5;
+
+            // Around the knee tanh has already flattened out, while atan is still rising
+            let atan_slope = atan_sat(0.4, drive) - atan_sat(0.2, drive);
+            let tanh_slope = tanh_sat(0.4, drive) - tanh_sat(0.2, drive);
+            assert!(atan_slope > tanh_slope * 2.0);
+
+            // And it compresses less before the knee
+            assert!(atan_sat(0.2, drive) < tanh_sat(0.2, drive));
+        }
+    }
+
+    mod hard_clip {
+        use super::*;
+
+        #[test]
+        fn clamps_to_threshold() {
+            assert_eq!(hard_clip(2.0, 0.5), 0.5);
+            assert_eq!(hard_clip(-2.0, 0.5), -0.5);
+            assert_eq!(hard_clip(0.5, 0.5), 0.5);
+        }
+
+        #[test]
+        fn passes_values_within_threshold() {
+            for x in [-0.49, -0.25, 0.0, 0.1, 0.3, 0.49] {
+                assert_eq!(hard_clip(x, 0.5), x);
+            }
+        }
+    }
+
+    mod foldback {
+        use super::*;
+
+        // The reference implementation with a loop
+        fn foldback_loop(mut x: f32, threshold: f32) -> f32 {
+            while x.abs() > threshold {
+                x = x.signum() * 2.0 * threshold - x;
+            }
+            x
+        }
+
+        #[test]
+        fn folds_past_threshold() {
+            assert!((foldback(1.5, 1.0) - 0.5).abs() < 1e-6);
+            assert!((foldback(-1.5, 1.0) + 0.5).abs() < 1e-6);
+        }
+
+        #[test]
+        fn passes_values_within_threshold() {
+            for x in [-0.9, -0.5, 0.0, 0.25, 0.75] {
+                assert!((foldback(x, 1.0) - x).abs() < 1e-6);
+            }
+        }
+
+        #[test]
+        fn matches_loop() {
+            for threshold in [0.25, 0.5, 1.0] {
+                for i in -500..=500 {
+                    let x = i as f32 / 100.0;
+                    let expected = foldback_loop(x, threshold);
+                    assert!(
+                        (foldback(x, threshold) - expected).abs() < 1e-5,
+                        "x = {x}, threshold = {threshold}"
+                    );
+                }
+            }
+        }
+    }
+
+    mod chebyshev {
+        use super::*;
+
+        #[test]
+        fn known_polynomials() {
+            for i in -10..=10 {
+                let x = i as f32 / 10.0;
+                assert_eq!(chebyshev(x, 0), 1.0);
+                assert!((chebyshev(x, 1) - x).abs() < 1e-6);
+                assert!((chebyshev(x, 2) - (2.0 * x * x - 1.0)).abs() < 1e-6);
+                assert!((chebyshev(x, 3) - (4.0 * x * x * x - 3.0 * x)).abs() < 1e-6);
+            }
+        }
+
+        #[test]
+        fn order_2_produces_the_second_harmonic() {
+            let fundamental = harmonic_amplitude(|x| chebyshev(x, 2), 1);
+            let second = harmonic_amplitude(|x| chebyshev(x, 2), 2);
+            let third = harmonic_amplitude(|x| chebyshev(x, 2), 3);
+
+            assert!((second - 1.0).abs() < 1e-3);
+            assert!(fundamental < 1e-4);
+            assert!(third < 1e-4);
+        }
+    }
+
+    mod wavefold {
+        use super::*;
+
+        // Counts how many times the output changes direction as the input goes from 0 to 1
+        fn count_folds(drive: f32) -> usize {
+            let mut folds = 0;
+            let mut prev_output = wavefold(0.0, drive);
+            let mut prev_delta = 0.0f32;
+            for i in 1..=1000 {
+                let output = wavefold(i as f32 / 1000.0, drive);
+                let delta = output - prev_output;
+                if delta * prev_delta < 0.0 {
+                    folds += 1;
+                }
+                prev_output = output;
+                prev_delta = delta;
+            }
+
+            folds
+        }
+
+        #[test]
+        fn unity_at_minimum_drive() {
+            assert!((wavefold(1.0, 0.0) - 1.0).abs() < 1e-6);
+            assert_eq!(count_folds(0.0), 0);
+        }
+
+        #[test]
+        fn folds_as_input_grows() {
+            assert!(count_folds(1.0) >= 2);
+        }
+
+        #[test]
+        fn more_drive_means_more_folds() {
+            assert!(count_folds(2.0) > count_folds(1.0));
+            assert!(count_folds(1.0) > count_folds(0.5));
+        }
+    }
+
+    mod bit_crush {
+        use super::*;
+
+        #[test]
+        fn one_bit_is_plus_minus_one() {
+            for i in -100..=100 {
+                let output = bit_crush(i as f32 / 100.0, 1.0);
+                assert!(output == 1.0 || output == -1.0, "output = {output}");
+            }
+        }
+
+        #[test]
+        fn sixteen_bits_is_near_transparent() {
+            for i in -100..=100 {
+                let x = i as f32 / 100.0;
+                assert!((bit_crush(x, 16.0) - x).abs() < 1e-4);
+            }
+        }
+    }
+
+    mod diode_clip {
+        use super::*;
+
+        #[test]
+        fn symmetric_has_no_even_harmonics() {
+            let fundamental = harmonic_amplitude(|x| diode_clip(x, 0.25), 1);
+            let second = harmonic_amplitude(|x| diode_clip(x, 0.25), 2);
+            assert!(second < fundamental * 1e-4);
+        }
+
+        #[test]
+        fn asymmetric_has_even_harmonics() {
+            let fundamental = harmonic_amplitude(|x| diode_clip_asymmetric(x, 0.25), 1);
+            let second = harmonic_amplitude(|x| diode_clip_asymmetric(x, 0.25), 2);
+            assert!(second > fundamental * 0.05);
+        }
+
+        #[test]
+        fn asymmetric_is_continuous_at_zero() {
+            let slope_above = diode_clip_asymmetric(1e-4, 0.0) / 1e-4;
+            let slope_below = diode_clip_asymmetric(-1e-4, 0.0) / -1e-4;
+            assert!((slope_above - slope_below).abs() < 1e-2);
+        }
+    }
+
+    mod decimator {
+        use super::*;
+
+        #[test]
+        fn unity_factor_passes_through() {
+            let mut decimator = Decimator::new();
+            for i in 0..100 {
+                let x = i as f32 / 100.0;
+                assert_eq!(decimator.process(x, 1.0), x);
+            }
+        }
+
+        #[test]
+        fn holds_for_factor_samples() {
+            let mut decimator = Decimator::new();
+            let outputs: Vec<f32> = (0..8).map(|i| decimator.process(i as f32, 4.0)).collect();
+
+            assert_eq!(outputs, [0.0, 0.0, 0.0, 0.0, 4.0, 4.0, 4.0, 4.0]);
+        }
+
+        #[test]
+        fn fractional_factor_averages_out() {
+            let mut decimator = Decimator::new();
+            let mut prev_output = f32::NAN;
+            let mut num_holds = 0;
+            for i in 0..300 {
+                let output = decimator.process(i as f32, 1.5);
+                if output != prev_output {
+                    num_holds += 1;
+                }
+                prev_output = output;
+            }
+
+            assert_eq!(num_holds, 200);
+        }
+
+        #[test]
+        fn reset_clears_state() {
+            let mut decimator = Decimator::new();
+            decimator.process(1.0, 8.0);
+            decimator.reset();
+
+            assert_eq!(decimator.process(0.5, 8.0), 0.5);
+        }
+    }
+
+    mod dry_wet {
+        use super::*;
+
+        #[test]
+        fn zero_mix_is_passthrough() {
+            for i in -100..=100 {
+                let x = i as f32 / 37.0;
+                assert_eq!(dry_wet(x, cubic(x, 1.0, 0.5), 0.0), x);
+                assert_eq!(dry_wet(x, hard_clip(x, 0.1), 0.0), x);
+            }
+        }
+
+        #[test]
+        fn full_mix_is_wet() {
+            for i in -100..=100 {
+                let x = i as f32 / 37.0;
+                let wet = cubic(x, 1.0, 0.5);
+                assert_eq!(dry_wet(x, wet, 1.0), wet);
+            }
+        }
+    }
+
+    mod slew {
+        use super::*;
+
+        const SAMPLE_RATE: f32 = 48000.0;
+
+        #[test]
+        fn limits_square_wave_edges() {
+            let mut slew = SlewDistortion::new(SAMPLE_RATE);
+            slew.set_pos_rate(4800.0);
+            slew.set_neg_rate(4800.0);
+            let max_step = 4800.0 / SAMPLE_RATE;
+
+            // A square wave with a period of 100 samples, the edges take 20 samples to traverse
+            let mut prev_output = 0.0;
+            let mut reached_peaks = (false, false);
+            for i in 0..1000 {
+                let input = if (i / 50) % 2 == 0 { 1.0 } else { -1.0 };
+                let output = slew.process(input);
+
+                assert!((output - prev_output).abs() <= max_step + 1e-6);
+                reached_peaks.0 |= (output - 1.0).abs() < 1e-5;
+                reached_peaks.1 |= (output + 1.0).abs() < 1e-5;
+                prev_output = output;
+            }
+            assert!(reached_peaks.0 && reached_peaks.1);
+        }
+
+        #[test]
+        fn asymmetric_rates() {
+            let mut slew = SlewDistortion::new(SAMPLE_RATE);
+            slew.set_pos_rate(4800.0);
+            slew.set_neg_rate(480.0);
+
+            assert!((slew.process(1.0) - 0.1).abs() < 1e-6);
+            slew.reset();
+            assert!((slew.process(-1.0) + 0.01).abs() < 1e-6);
+        }
+
+        #[test]
+        fn independent_of_sample_rate() {
+            // The same rate should cover the same distance in the same amount of time
+            let mut slew = SlewDistortion::new(SAMPLE_RATE);
+            let mut oversampled_slew = SlewDistortion::new(SAMPLE_RATE * 4.0);
+            slew.set_pos_rate(1000.0);
+            oversampled_slew.set_pos_rate(1000.0);
+
+            let mut output = 0.0;
+            let mut oversampled_output = 0.0;
+            for _ in 0..100 {
+                output = slew.process(1.0);
+                for _ in 0..4 {
+                    oversampled_output = oversampled_slew.process(1.0);
+                }
+            }
+            assert!((output - oversampled_output).abs() < 1e-4);
+        }
+
+        #[test]
+        fn reset_clears_state() {
+            let mut slew = SlewDistortion::new(SAMPLE_RATE);
+            slew.process(1.0);
+            slew.reset();
+
+            assert_eq!(slew.process(0.0), 0.0);
+        }
+    }
+
+    mod distortion_type {
+        use super::*;
+
+        #[test]
+        fn from_index_clamps() {
+            assert_eq!(DistortionType::from_index(-1), DistortionType::Cubic);
+            assert_eq!(DistortionType::from_index(2), DistortionType::Slew);
+            assert_eq!(
+                DistortionType::from_index(100),
+                *DistortionType::ALL.last().unwrap()
+            );
+        }
+    }
+}
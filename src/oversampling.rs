@@ -63,6 +63,122 @@ const LANCZOS3_DOWNSAMPLING_KERNEL: [f32; 11] = [
 /// The latency introduced by the two filter kernels defined above, in samples.
 const LANZCOS3_KERNEL_LATENCY: usize = LANCZOS3_UPSAMPLING_KERNEL.len() / 2;
 
+/// The kernel used in `MinimumPhaseOversampler`. This is the minimum-phase version of
+/// `LANCZOS3_UPSAMPLING_KERNEL`, so it has the same magnitude response but most of its energy comes
+/// right at the start instead of being centered around the middle tap.
+///
+/// Computed from the real cepstrum using:
+///
+/// ```python
+/// n = 512
+/// log_magnitude = np.log(np.maximum(np.abs(np.fft.fft(LANCZOS3_UPSAMPLING_KERNEL, n)), 1e-9))
+/// cepstrum = np.fft.ifft(log_magnitude).real
+/// cepstrum[1:n // 2] *= 2
+/// cepstrum[n // 2 + 1:] = 0
+/// np.fft.ifft(np.exp(np.fft.fft(cepstrum))).real[:11]
+/// ```
+const MINIMUM_PHASE_UPSAMPLING_KERNEL: [f32; 11] = [
+    0.23205015,
+    0.757453,
+    0.9461385,
+    0.3914612,
+    -0.21902022,
+    -0.2136246,
+    0.04983813,
+    0.06732845,
+    -0.01155637,
+    -0.00831587,
+    0.00255056,
+];
+
+/// `MINIMUM_PHASE_UPSAMPLING_KERNEL` divided by two, for the same reason as
+/// `LANCZOS3_DOWNSAMPLING_KERNEL`.
+const MINIMUM_PHASE_DOWNSAMPLING_KERNEL: [f32; 11] = [
+    0.11602508,
+    0.3787265,
+    0.47306925,
+    0.1957306,
+    -0.10951011,
+    -0.1068123,
+    0.02491907,
+    0.03366422,
+    -0.00577819,
+    -0.00415793,
+    0.00127528,
+];
+
+/// The number of taps in each of the two polyphase branches of the minimum-phase upsampling
+/// filter. The odd branch has one tap less.
+const MINIMUM_PHASE_BRANCH_LEN: usize = MINIMUM_PHASE_UPSAMPLING_KERNEL.len().div_ceil(2);
+
+/// A single channel oversampler. This is implemented by the different oversampling algorithms so
+/// the plugin can switch between them. The oversampling factor is the 2-logarithm of the
+/// oversampling amount, so 0 means no oversampling, 1 means 2x oversampling, etc.
+pub trait Oversampler: Send {
+    /// Upsample `block` using the specified oversampling factor, process the upsampled version
+    /// using `f`, and then downsample it again and write the results back to `block` with a
+    /// [`latency()`][Self::latency()] sample delay. A factor of 0 processes `block` directly
+    /// without any latency.
+    fn process(&mut self, block: &mut [f32], factor: usize, f: &mut dyn FnMut(&mut [f32]));
+
+    /// Get the latency in samples for the given oversampling factor.
+    fn latency(&self, factor: usize) -> u32;
+
+    /// Reset the oversampling filters to their initial states.
+    fn reset(&mut self);
+}
+
+/// The available oversampling algorithms, in parameter order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversamplingQuality {
+    /// [`LinearOversampler`], cheap but with a lot more aliasing and high frequency loss.
+    Linear,
+    /// [`Lanczos3Oversampler`].
+    Lanczos3,
+    /// [`MinimumPhaseOversampler`], the same filter as `Lanczos3` with a lot less latency.
+    MinimumPhase,
+}
+
+impl OversamplingQuality {
+    pub const ALL: [OversamplingQuality; 3] = [
+        OversamplingQuality::Linear,
+        OversamplingQuality::Lanczos3,
+        OversamplingQuality::MinimumPhase,
+    ];
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL[(index.max(0) as usize).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OversamplingQuality::Linear => "Linear",
+            OversamplingQuality::Lanczos3 => "Lanczos3",
+            OversamplingQuality::MinimumPhase => "Minimum Phase",
+        }
+    }
+
+    /// Create an oversampler for this algorithm. See [`Lanczos3Oversampler::new()`] for the
+    /// meaning of the arguments.
+    pub fn create(self, maximum_block_size: usize, max_factor: usize) -> Box<dyn Oversampler> {
+        match self {
+            OversamplingQuality::Linear => {
+                Box::new(LinearOversampler::new(maximum_block_size, max_factor))
+            }
+            OversamplingQuality::Lanczos3 => {
+                Box::new(Lanczos3Oversampler::new(maximum_block_size, max_factor))
+            }
+            OversamplingQuality::MinimumPhase => {
+                Box::new(MinimumPhaseOversampler::new(maximum_block_size, max_factor))
+            }
+        }
+    }
+}
+
 /// A barebones multi-stage linear-phase oversampler that uses the lanzcos kernel with a=3 for a
 /// good approximation of a windowed sinc with only a 11 point kernel function (the kernel is
 /// actually 13 points, but the outer two points are both zero can can thus be omitted). This can be
@@ -274,6 +390,364 @@ impl Lanczos3Oversampler {
     }
 }
 
+impl Oversampler for Lanczos3Oversampler {
+    fn process(&mut self, block: &mut [f32], factor: usize, f: &mut dyn FnMut(&mut [f32])) {
+        Lanczos3Oversampler::process(self, block, factor, f)
+    }
+
+    fn latency(&self, factor: usize) -> u32 {
+        Lanczos3Oversampler::latency(self, factor)
+    }
+
+    fn reset(&mut self) {
+        Lanczos3Oversampler::reset(self)
+    }
+}
+
+/// A cheap single-stage oversampler for when CPU usage matters more than quality. Upsampling uses
+/// linear interpolation, and downsampling uses a triangular filter with the same width. This has
+/// a lot less aliasing rejection than [`Lanczos3Oversampler`] and it rolls off the high
+/// frequencies, but it only costs a couple of multiplications per sample and always has a single
+/// sample of latency.
+///
+/// This only handles a single audio channel. Use multiple instances for multichannel audio.
+#[derive(Debug)]
+pub struct LinearOversampler {
+    /// The last input sample from the previous block, the first upsampled samples of a block are
+    /// interpolated from it.
+    prev_input: f32,
+
+    /// A ring buffer with the last `2 * max_oversampling_amount - 1` upsampled samples, used for
+    /// the triangular downsampling filter.
+    downsampling_rb: Vec<f32>,
+    downsampling_write_pos: usize,
+
+    /// Contains room for `maximum_block_size * max_oversampling_amount` samples.
+    scratch_buffer: Vec<f32>,
+
+    max_factor: usize,
+}
+
+impl LinearOversampler {
+    /// Create a new oversampler that can oversample to up to the specified oversampling factor. See
+    /// [`Lanczos3Oversampler::new()`].
+    pub fn new(maximum_block_size: usize, max_factor: usize) -> Self {
+        let max_oversampling_amount = 2usize.pow(max_factor as u32);
+
+        Self {
+            prev_input: 0.0,
+
+            downsampling_rb: vec![0.0; 2 * max_oversampling_amount - 1],
+            downsampling_write_pos: 0,
+
+            scratch_buffer: vec![0.0; maximum_block_size * max_oversampling_amount],
+
+            max_factor,
+        }
+    }
+}
+
+impl Oversampler for LinearOversampler {
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`, or if `block`'s length is longer than the maximum block
+    /// size.
+    fn process(&mut self, block: &mut [f32], factor: usize, f: &mut dyn FnMut(&mut [f32])) {
+        assert!(factor <= self.max_factor);
+
+        // This is the 1x oversampling case, this should also modify the block to be consistent
+        if factor == 0 {
+            f(block);
+            return;
+        }
+
+        let oversampling_amount = 2usize.pow(factor as u32);
+        let upsampled_len = block.len() * oversampling_amount;
+        assert!(
+            upsampled_len <= self.scratch_buffer.len(),
+            "The block's size exceeds the maximum block size"
+        );
+
+        // The upsampled samples for input sample `i` lie between the previous input sample and
+        // sample `i`, which delays the signal by exactly one sample
+        for (input_sample_idx, input_sample) in block.iter().enumerate() {
+            let delta = input_sample - self.prev_input;
+            let upsampled = &mut self.scratch_buffer[input_sample_idx * oversampling_amount..]
+                [..oversampling_amount];
+            for (offset, sample) in upsampled.iter_mut().enumerate() {
+                *sample = self.prev_input + delta * (offset as f32 / oversampling_amount as f32);
+            }
+
+            self.prev_input = *input_sample;
+        }
+
+        f(&mut self.scratch_buffer[..upsampled_len]);
+
+        // The triangular filter spans `2 * oversampling_amount - 1` samples and is centered on the
+        // first upsampled sample for each output sample, so this doesn't add any more latency. The
+        // ring buffer is always the same size, only the most recent samples are used.
+        let rb_len = self.downsampling_rb.len();
+        let filter_radius = oversampling_amount - 1;
+        let normalization = 1.0 / (oversampling_amount * oversampling_amount) as f32;
+        for (output_sample_idx, output_sample) in block.iter_mut().enumerate() {
+            let upsampled = &self.scratch_buffer[output_sample_idx * oversampling_amount..]
+                [..oversampling_amount];
+            for sample in upsampled {
+                self.downsampling_rb[self.downsampling_write_pos] = *sample;
+                self.downsampling_write_pos = (self.downsampling_write_pos + 1) % rb_len;
+            }
+
+            // `center_pos` is the first sample that was just written
+            let center_pos = (self.downsampling_write_pos + rb_len - oversampling_amount) % rb_len;
+            let mut total = self.downsampling_rb[center_pos] * oversampling_amount as f32;
+            for distance in 1..=filter_radius {
+                let weight = (oversampling_amount - distance) as f32;
+                total += weight
+                    * (self.downsampling_rb[(center_pos + distance) % rb_len]
+                        + self.downsampling_rb[(center_pos + rb_len - distance) % rb_len]);
+            }
+
+            *output_sample = total * normalization;
+        }
+    }
+
+    fn latency(&self, factor: usize) -> u32 {
+        if factor == 0 {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.downsampling_rb.fill(0.0);
+        self.downsampling_write_pos = 0;
+    }
+}
+
+/// A multi-stage oversampler with the same structure as [`Lanczos3Oversampler`], but with the
+/// minimum-phase version of its filter. The magnitude response is the same, but the filters
+/// don't delay the signal by half their length. That brings the latency down from 5 to 1 samples
+/// at 2x oversampling and from 11 to 2 samples at 16x, at the cost of the high frequencies
+/// being delayed a bit more than the low frequencies. The upsampling filter is split into its even
+/// and odd taps so each upsampled sample only needs half of the kernel.
+///
+/// Because the delay depends on the frequency, the reported latency is the filters' group delay
+/// at DC rounded to the nearest sample. There is no extra delay to make it an integer amount.
+///
+/// This only handles a single audio channel. Use multiple instances for multichannel audio.
+#[derive(Debug)]
+pub struct MinimumPhaseOversampler {
+    /// The state for each oversampling stage, see [`Lanczos3Oversampler`]'s `stages`.
+    stages: Vec<MinimumPhaseStage>,
+
+    /// The oversampler's latency. Precomputed for each possible number of active stages.
+    latencies: Vec<u32>,
+}
+
+/// A single minimum-phase oversampling stage.
+#[derive(Debug, Clone)]
+struct MinimumPhaseStage {
+    /// The last `MINIMUM_PHASE_BRANCH_LEN` input samples at this stage's input sample rate. Both
+    /// polyphase branches of the upsampling filter are convolved with these.
+    upsampling_rb: [f32; MINIMUM_PHASE_BRANCH_LEN],
+    upsampling_write_pos: usize,
+
+    downsampling_rb: [f32; MINIMUM_PHASE_DOWNSAMPLING_KERNEL.len()],
+    downsampling_write_pos: usize,
+
+    scratch_buffer: Vec<f32>,
+}
+
+impl MinimumPhaseOversampler {
+    /// Create a new oversampler that can oversample to up to the specified oversampling factor.
+    /// See [`Lanczos3Oversampler::new()`].
+    pub fn new(maximum_block_size: usize, max_factor: usize) -> Self {
+        let stages = (0..max_factor)
+            .map(|stage| MinimumPhaseStage::new(maximum_block_size, stage))
+            .collect();
+
+        // The group delays of the stages add up. A stage's upsampling and downsampling filters
+        // both run at the stage's output sample rate, so the `n`th stage's delay is divided by
+        // `2^(n + 1)` to get the delay at the base sample rate.
+        let kernel_group_delay = MINIMUM_PHASE_UPSAMPLING_KERNEL
+            .iter()
+            .enumerate()
+            .map(|(tap, coefficient)| tap as f32 * coefficient)
+            .sum::<f32>()
+            / MINIMUM_PHASE_UPSAMPLING_KERNEL.iter().sum::<f32>();
+        let latencies = (0..max_factor)
+            .scan(0.0, |total_group_delay, stage| {
+                *total_group_delay += kernel_group_delay * 2.0 / 2.0f32.powi(stage as i32 + 1);
+                Some(total_group_delay.round() as u32)
+            })
+            .collect();
+
+        Self { stages, latencies }
+    }
+
+    /// Get the latency in samples for the given oversampling factor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`.
+    pub fn latency(&self, factor: usize) -> u32 {
+        if factor == 0 {
+            0
+        } else {
+            self.latencies[factor - 1]
+        }
+    }
+}
+
+impl Oversampler for MinimumPhaseOversampler {
+    /// # Panics
+    ///
+    /// Panics if `factor > max_factor`, or if `block`'s length is longer than the maximum block
+    /// size.
+    fn process(&mut self, block: &mut [f32], factor: usize, f: &mut dyn FnMut(&mut [f32])) {
+        assert!(factor <= self.stages.len());
+
+        // This is the 1x oversampling case, this should also modify the block to be consistent
+        if factor == 0 {
+            f(block);
+            return;
+        }
+
+        assert!(
+            block.len() <= self.stages[0].scratch_buffer.len() / 2,
+            "The block's size exceeds the maximum block size"
+        );
+
+        // This works the same way as `Lanczos3Oversampler::upsample_from()` and
+        // `Lanczos3Oversampler::downsample_to()`
+        self.stages[0].upsample_from(block);
+        let mut upsampled_block_len = block.len() * 2;
+        for to_stage_idx in 1..factor {
+            let ([.., from], [to, ..]) = self.stages.split_at_mut(to_stage_idx) else {
+                unreachable!()
+            };
+
+            to.upsample_from(&from.scratch_buffer[..upsampled_block_len]);
+            upsampled_block_len *= 2;
+        }
+
+        f(&mut self.stages[factor - 1].scratch_buffer[..upsampled_block_len]);
+
+        for to_stage_idx in (1..factor).rev() {
+            upsampled_block_len /= 2;
+            let ([.., to], [from, ..]) = self.stages.split_at_mut(to_stage_idx) else {
+                unreachable!()
+            };
+
+            from.downsample_to(&mut to.scratch_buffer[..upsampled_block_len]);
+        }
+        self.stages[0].downsample_to(block);
+    }
+
+    fn latency(&self, factor: usize) -> u32 {
+        MinimumPhaseOversampler::latency(self, factor)
+    }
+
+    fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+}
+
+impl MinimumPhaseStage {
+    /// Create a `stage_number`th oversampling stage. See [`Lanzcos3Stage::new()`].
+    pub fn new(maximum_block_size: usize, stage_number: usize) -> Self {
+        let oversampling_amount = 2usize.pow(stage_number as u32 + 1);
+
+        Self {
+            upsampling_rb: [0.0; MINIMUM_PHASE_BRANCH_LEN],
+            upsampling_write_pos: 0,
+
+            downsampling_rb: [0.0; MINIMUM_PHASE_DOWNSAMPLING_KERNEL.len()],
+            downsampling_write_pos: 0,
+
+            scratch_buffer: vec![0.0; maximum_block_size * oversampling_amount],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.upsampling_rb.fill(0.0);
+        self.upsampling_write_pos = 0;
+
+        self.downsampling_rb.fill(0.0);
+        self.downsampling_write_pos = 0;
+    }
+
+    /// Upsample `block` 2x and write the results to this stage's scratch buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block`'s times two exceeds the scratch buffer's size.
+    pub fn upsample_from(&mut self, block: &[f32]) {
+        assert!(block.len() * 2 <= self.scratch_buffer.len());
+
+        // Convolving the zero-stuffed input with the kernel means that the even output samples
+        // only see the even taps and the odd output samples only see the odd taps, so the zeroes
+        // never need to be written out
+        for (input_sample_idx, input_sample) in block.iter().enumerate() {
+            self.upsampling_rb[self.upsampling_write_pos] = *input_sample;
+
+            for (phase, output_sample) in self.scratch_buffer[input_sample_idx * 2..][..2]
+                .iter_mut()
+                .enumerate()
+            {
+                *output_sample = MINIMUM_PHASE_UPSAMPLING_KERNEL
+                    .iter()
+                    .skip(phase)
+                    .step_by(2)
+                    .enumerate()
+                    .map(|(age, coefficient)| {
+                        coefficient
+                            * self.upsampling_rb[(self.upsampling_write_pos
+                                + MINIMUM_PHASE_BRANCH_LEN
+                                - age)
+                                % MINIMUM_PHASE_BRANCH_LEN]
+                    })
+                    .sum();
+            }
+
+            self.upsampling_write_pos = (self.upsampling_write_pos + 1) % MINIMUM_PHASE_BRANCH_LEN;
+        }
+    }
+
+    /// Downsample this stage's scratch buffer to `block`, see [`Lanzcos3Stage::downsample_to()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block`'s divided by two exceeds the scratch buffer's size.
+    pub fn downsample_to(&mut self, block: &mut [f32]) {
+        let input_length = block.len() * 2;
+        assert!(input_length <= self.scratch_buffer.len());
+
+        for input_sample_idx in 0..input_length {
+            self.downsampling_rb[self.downsampling_write_pos] =
+                self.scratch_buffer[input_sample_idx];
+
+            self.downsampling_write_pos += 1;
+            if self.downsampling_write_pos == MINIMUM_PHASE_DOWNSAMPLING_KERNEL.len() {
+                self.downsampling_write_pos = 0;
+            }
+
+            // Only the samples that are kept after decimation need to be filtered
+            if input_sample_idx % 2 == 0 {
+                block[input_sample_idx / 2] = convolve_rb(
+                    &self.downsampling_rb,
+                    &MINIMUM_PHASE_DOWNSAMPLING_KERNEL,
+                    self.downsampling_write_pos,
+                );
+            }
+        }
+    }
+}
+
 impl Lanzcos3Stage {
     /// Create a `stage_number`th oversampling stage, where `stage_number` is this stage's
     /// zero-based index in a list of stages. Stage 0 handles the 2x oversampling, stage 1 handles
@@ -547,12 +1021,11 @@ mod tests {
         }
 
         /// Makes sure that the reported latency is correct and is (more or less) an integer value
-        fn test_latency(oversampling_factor: usize) {
+        fn test_latency(quality: OversamplingQuality, oversampling_factor: usize) {
             let mut delta_impulse = [0.0f32; 64];
             delta_impulse[0] = 1.0;
 
-            let mut oversampler =
-                Lanczos3Oversampler::new(delta_impulse.len(), oversampling_factor);
+            let mut oversampler = quality.create(delta_impulse.len(), oversampling_factor);
 
             let reported_latency = oversampler.latency(oversampling_factor) as usize;
             assert!(
@@ -561,7 +1034,7 @@ mod tests {
                  {oversampling_factor}, this is an error with the test case"
             );
 
-            oversampler.process(&mut delta_impulse, oversampling_factor, |_| ());
+            oversampler.process(&mut delta_impulse, oversampling_factor, &mut |_| ());
 
             let new_impulse_idx = argmax(delta_impulse);
             assert_eq!(new_impulse_idx, reported_latency);
@@ -573,20 +1046,26 @@ mod tests {
 
         /// Checks whether the output matches the input when compensating for the latency. Also
         /// applies a gain offset to make sure the process callback actually works.
-        fn test_sine_output(oversampling_factor: usize) {
+        ///
+        /// The frequency is given as a fraction of the sampling frequency, and the tolerance
+        /// depends on the algorithm's filter's ringing and high frequency loss.
+        fn test_sine_output(
+            quality: OversamplingQuality,
+            oversampling_factor: usize,
+            frequency: f32,
+            epsilon: f32,
+        ) {
             // The gain applied to the oversampled version
             const GAIN: f32 = 2.0;
-            // As a fraction of the sampling frequency
-            const FREQUENCY: f32 = 0.125;
 
             let mut input = [0.0f32; 128];
             for (i, sample) in input.iter_mut().enumerate() {
-                *sample = (i as f32 * (FREQUENCY * 2.0 * std::f32::consts::PI)).sin();
+                *sample = (i as f32 * (frequency * 2.0 * std::f32::consts::PI)).sin();
             }
 
             let mut output = input;
-            let mut oversampler = Lanczos3Oversampler::new(output.len(), oversampling_factor);
-            oversampler.process(&mut output, oversampling_factor, |upsampled| {
+            let mut oversampler = quality.create(output.len(), oversampling_factor);
+            oversampler.process(&mut output, oversampling_factor, &mut |upsampled| {
                 for sample in upsampled {
                     *sample *= GAIN;
                 }
@@ -603,48 +1082,165 @@ mod tests {
 
                 // There can be quite a big difference between the input and output thanks to the
                 // filter's ringing
-                approx::assert_relative_eq!(input_sample * GAIN, output_sample, epsilon = 0.1);
+                approx::assert_relative_eq!(input_sample * GAIN, output_sample, epsilon = epsilon);
             }
         }
 
         #[test]
         fn latency_2x() {
-            test_latency(1);
+            test_latency(OversamplingQuality::Lanczos3, 1);
         }
 
         #[test]
         fn latency_4x() {
-            test_latency(2);
+            test_latency(OversamplingQuality::Lanczos3, 2);
         }
 
         #[test]
         fn latency_8x() {
-            test_latency(3);
+            test_latency(OversamplingQuality::Lanczos3, 3);
         }
 
         #[test]
         fn latency_16x() {
-            test_latency(4);
+            test_latency(OversamplingQuality::Lanczos3, 4);
         }
 
         #[test]
         fn sine_output_2x() {
-            test_sine_output(1);
+            test_sine_output(OversamplingQuality::Lanczos3, 1, 0.125, 0.1);
         }
 
         #[test]
         fn sine_output_4x() {
-            test_sine_output(2);
+            test_sine_output(OversamplingQuality::Lanczos3, 2, 0.125, 0.1);
         }
 
         #[test]
         fn sine_output_8x() {
-            test_sine_output(3);
+            test_sine_output(OversamplingQuality::Lanczos3, 3, 0.125, 0.1);
         }
 
         #[test]
         fn sine_output_16x() {
-            test_sine_output(4);
+            test_sine_output(OversamplingQuality::Lanczos3, 4, 0.125, 0.1);
+        }
+
+        #[test]
+        fn linear_latency_2x() {
+            test_latency(OversamplingQuality::Linear, 1);
+        }
+
+        #[test]
+        fn linear_latency_16x() {
+            test_latency(OversamplingQuality::Linear, 4);
+        }
+
+        #[test]
+        fn linear_sine_output_2x() {
+            test_sine_output(OversamplingQuality::Linear, 1, 0.02, 0.05);
+        }
+
+        #[test]
+        fn linear_sine_output_16x() {
+            test_sine_output(OversamplingQuality::Linear, 4, 0.02, 0.05);
+        }
+
+        /// The minimum-phase filters don't delay the signal by a fixed amount, so instead of
+        /// looking for the impulse's peak this checks that the impulse response's center of mass,
+        /// which is its group delay at DC, rounds to the reported latency.
+        fn test_group_delay(quality: OversamplingQuality, oversampling_factor: usize) {
+            let mut delta_impulse = [0.0f32; 64];
+            delta_impulse[0] = 1.0;
+
+            let mut oversampler = quality.create(delta_impulse.len(), oversampling_factor);
+            oversampler.process(&mut delta_impulse, oversampling_factor, &mut |_| ());
+
+            let group_delay = delta_impulse
+                .iter()
+                .enumerate()
+                .map(|(i, sample)| i as f32 * sample)
+                .sum::<f32>()
+                / delta_impulse.iter().sum::<f32>();
+            let reported_latency = oversampler.latency(oversampling_factor);
+            assert_eq!(
+                group_delay.round() as u32,
+                reported_latency,
+                "{group_delay}"
+            );
+        }
+
+        /// Like [`test_sine_output()`], but the sine is already running before the compared block
+        /// starts. The minimum-phase filters' onset smears the first couple of samples too much
+        /// to compare them directly.
+        fn test_sine_output_settled(
+            quality: OversamplingQuality,
+            oversampling_factor: usize,
+            frequency: f32,
+            epsilon: f32,
+        ) {
+            const GAIN: f32 = 2.0;
+
+            let mut input = [0.0f32; 256];
+            for (i, sample) in input.iter_mut().enumerate() {
+                *sample = (i as f32 * (frequency * 2.0 * std::f32::consts::PI)).sin();
+            }
+
+            let mut output = input;
+            let mut oversampler = quality.create(output.len() / 2, oversampling_factor);
+            for block in output.chunks_mut(128) {
+                oversampler.process(block, oversampling_factor, &mut |upsampled| {
+                    for sample in upsampled {
+                        *sample *= GAIN;
+                    }
+                });
+            }
+
+            let reported_latency = oversampler.latency(oversampling_factor) as usize;
+            for output_sample_idx in 128..output.len() {
+                approx::assert_relative_eq!(
+                    input[output_sample_idx - reported_latency] * GAIN,
+                    output[output_sample_idx],
+                    epsilon = epsilon
+                );
+            }
+        }
+
+        #[test]
+        fn minimum_phase_latency_2x() {
+            test_group_delay(OversamplingQuality::MinimumPhase, 1);
+        }
+
+        #[test]
+        fn minimum_phase_latency_16x() {
+            test_group_delay(OversamplingQuality::MinimumPhase, 4);
+        }
+
+        #[test]
+        fn minimum_phase_latency_is_lower() {
+            let lanczos3 = OversamplingQuality::Lanczos3.create(64, 4);
+            let minimum_phase = OversamplingQuality::MinimumPhase.create(64, 4);
+            assert_eq!(minimum_phase.latency(0), 0);
+            for factor in 1..=4 {
+                assert!(
+                    minimum_phase.latency(factor) < lanczos3.latency(factor),
+                    "{} >= {} at factor {factor}",
+                    minimum_phase.latency(factor),
+                    lanczos3.latency(factor)
+                );
+            }
+        }
+
+        // The latency is rounded and the higher frequencies are delayed more, so this uses a
+        // lower frequency than the Lanczos3 tests
+        #[test]
+        fn minimum_phase_sine_output_2x() {
+            test_sine_output_settled(OversamplingQuality::MinimumPhase, 1, 0.005, 0.1);
+        }
+
+        #[test]
+        fn minimum_phase_sine_output_16x() {
+            test_sine_output_settled(OversamplingQuality::MinimumPhase, 4, 0.005, 0.1);
         }
     }
 }
@@ -0,0 +1,337 @@
+use nih_plug::prelude::*;
+
+use crate::nonlinearity::DistortionType;
+use crate::MelterParams;
+
+/// The distortion types a randomized patch picks from. The more extreme types like the slew
+/// limiter and the Chebyshev shaper rarely sound good with random settings, so they're left out.
+const RANDOM_DISTORTION_TYPES: [DistortionType; 7] = [
+    DistortionType::Cubic,
+    DistortionType::Arctan,
+    DistortionType::HardClip,
+    DistortionType::Diode,
+    DistortionType::AsymmetricDiode,
+    DistortionType::Wavefold,
+    DistortionType::EvenHarmonics,
+];
+
+/// A named set of parameter values that can be recalled from an editor or with
+/// [`Melter::load_preset()`][crate::Melter::load_preset()]. Parameters that aren't part of the
+/// preset are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub gain_db: f32,
+    pub drive: f32,
+    pub bias: f32,
+    pub low_boost: f32,
+    pub mid_boost: f32,
+    pub high_boost: f32,
+    pub oversampling_factor: i32,
+}
+
+pub const FACTORY_PRESETS: [Preset; 4] = [
+    Preset {
+        name: "Warm Tube",
+        gain_db: 6.0,
+        drive: 0.6,
+        bias: 0.2,
+        low_boost: 2.0,
+        mid_boost: 1.0,
+        high_boost: -3.0,
+        oversampling_factor: 2,
+    },
+    Preset {
+        name: "Fuzz",
+        gain_db: 24.0,
+        drive: 1.8,
+        bias: 0.7,
+        low_boost: -2.0,
+        mid_boost: 4.0,
+        high_boost: 0.0,
+        oversampling_factor: 3,
+    },
+    Preset {
+        name: "Clean Boost",
+        gain_db: 6.0,
+        drive: 0.0,
+        bias: 0.0,
+        low_boost: 0.0,
+        mid_boost: 0.0,
+        high_boost: 1.5,
+        oversampling_factor: 1,
+    },
+    Preset {
+        name: "Lo-Fi",
+        gain_db: 12.0,
+        drive: 1.0,
+        bias: 0.5,
+        low_boost: -6.0,
+        mid_boost: 3.0,
+        high_boost: -9.0,
+        oversampling_factor: 0,
+    },
+];
+
+impl Preset {
+    /// Recall the preset from an editor. Every parameter gets its own automation gesture so the
+    /// host records the change.
+    #[cfg(feature = "gui")]
+    pub fn apply(&self, setter: &ParamSetter, params: &MelterParams) {
+        self.for_each_value(
+            params,
+            |param, value| set_parameter(setter, param, value),
+            |param, value| set_parameter(setter, param, value),
+        );
+    }
+
+    /// Call `set_float` or `set_int` with every parameter stored in the preset and the plain value
+    /// it should be set to.
+    pub fn for_each_value(
+        &self,
+        params: &MelterParams,
+        mut set_float: impl FnMut(&FloatParam, f32),
+        mut set_int: impl FnMut(&IntParam, i32),
+    ) {
+        set_float(&params.gain, util::db_to_gain(self.gain_db));
+        set_float(&params.drive, self.drive);
+        set_float(&params.bias, self.bias);
+        set_float(&params.low_boost, self.low_boost);
+        set_float(&params.mid_boost, self.mid_boost);
+        set_float(&params.high_boost, self.high_boost);
+        set_int(&params.oversampling_factor, self.oversampling_factor);
+    }
+}
+
+/// Random but musical values for the main parameters, for sound design. The ranges are narrower
+/// than the parameters' own ranges so most patches are usable, and the same seed always gives the
+/// same patch. [`Melter::randomize_params()`][crate::Melter::randomize_params()] sets them without
+/// an editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomPatch {
+    pub gain_db: f32,
+    pub drive: f32,
+    pub bias: f32,
+    pub low_boost: f32,
+    pub mid_boost: f32,
+    pub high_boost: f32,
+    pub distortion_type: DistortionType,
+}
+
+impl RandomPatch {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+
+        // Squaring the drive favors the lower, more subtle settings
+        RandomPatch {
+            gain_db: rng.range(0.0, 24.0).round(),
+            drive: rng.range(0.0, 1.0).powi(2) * 1.5,
+            bias: rng.range(-0.5, 0.5),
+            low_boost: (rng.range(-6.0, 6.0) * 2.0).round() / 2.0,
+            mid_boost: (rng.range(-6.0, 6.0) * 2.0).round() / 2.0,
+            high_boost: (rng.range(-6.0, 6.0) * 2.0).round() / 2.0,
+            distortion_type: RANDOM_DISTORTION_TYPES[rng.index(RANDOM_DISTORTION_TYPES.len())],
+        }
+    }
+
+    /// Set the parameters to the patch from an editor, like [`Preset::apply()`].
+    #[cfg(feature = "gui")]
+    pub fn apply(&self, setter: &ParamSetter, params: &MelterParams) {
+        self.for_each_value(
+            params,
+            |param, value| set_parameter(setter, param, value),
+            |param, value| set_parameter(setter, param, value),
+        );
+    }
+
+    /// Call `set_float` or `set_int` with every parameter in the patch and the plain value it
+    /// should be set to.
+    pub fn for_each_value(
+        &self,
+        params: &MelterParams,
+        mut set_float: impl FnMut(&FloatParam, f32),
+        mut set_int: impl FnMut(&IntParam, i32),
+    ) {
+        set_float(&params.gain, util::db_to_gain(self.gain_db));
+        set_float(&params.drive, self.drive);
+        set_float(&params.bias, self.bias);
+        set_float(&params.low_boost, self.low_boost);
+        set_float(&params.mid_boost, self.mid_boost);
+        set_float(&params.high_boost, self.high_boost);
+        set_int(
+            &params.distortion_type,
+            DistortionType::ALL
+                .iter()
+                .position(|distortion_type| *distortion_type == self.distortion_type)
+                .unwrap() as i32,
+        );
+    }
+}
+
+/// Set a parameter from an editor in its own automation gesture.
+#[cfg(feature = "gui")]
+fn set_parameter<P: Param>(setter: &ParamSetter, param: &P, value: P::Plain) {
+    setter.begin_set_parameter(param);
+    setter.set_parameter(param, value);
+    setter.end_set_parameter(param);
+}
+
+/// A small xorshift random number generator. This doesn't need to be any good, it just needs to
+/// give the same sequence for the same seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero, and similar seeds should still give different patches
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A random value in `[min, max)`.
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (self.next() >> 40) as f32 / (1u64 << 24) as f32 * (max - min)
+    }
+
+    /// A random index below `len`.
+    fn index(&mut self, len: usize) -> usize {
+        (self.next() % len as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads a preset by collecting the values it would set, keyed by parameter name.
+    fn load(preset: &Preset, params: &MelterParams) -> Vec<(String, f32)> {
+        let values = std::cell::RefCell::new(Vec::new());
+        preset.for_each_value(
+            params,
+            |param, value| values.borrow_mut().push((param.name().to_owned(), value)),
+            |param, value| {
+                values
+                    .borrow_mut()
+                    .push((param.name().to_owned(), value as f32))
+            },
+        );
+
+        values.into_inner()
+    }
+
+    #[test]
+    fn presets_match_snapshot() {
+        let params = MelterParams::default();
+        let expected: [[f32; 7]; 4] = [
+            [6.0, 0.6, 0.2, 2.0, 1.0, -3.0, 2.0],
+            [24.0, 1.8, 0.7, -2.0, 4.0, 0.0, 3.0],
+            [6.0, 0.0, 0.0, 0.0, 0.0, 1.5, 1.0],
+            [12.0, 1.0, 0.5, -6.0, 3.0, -9.0, 0.0],
+        ];
+
+        for (preset, expected) in FACTORY_PRESETS.iter().zip(expected) {
+            let values = load(preset, &params);
+            let names: Vec<&str> = values.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(
+                names,
+                [
+                    "Gain",
+                    "Drive",
+                    "Bias",
+                    "Low Boost",
+                    "Mid Boost",
+                    "High Boost",
+                    "Oversampling"
+                ]
+            );
+
+            approx::assert_relative_eq!(util::gain_to_db(values[0].1), expected[0], epsilon = 1e-4);
+            for ((_, value), expected) in values.iter().zip(expected).skip(1) {
+                assert_eq!(*value, expected, "{}", preset.name);
+            }
+        }
+    }
+
+    /// The plain values a parameter can be set to.
+    fn plain_range<P: Param>(param: &P) -> std::ops::RangeInclusive<P::Plain> {
+        param.preview_plain(0.0)..=param.preview_plain(1.0)
+    }
+
+    #[test]
+    fn presets_are_in_range() {
+        let params = MelterParams::default();
+        for preset in &FACTORY_PRESETS {
+            let gain = plain_range(&params.gain);
+            let gain_db = util::gain_to_db(*gain.start())..=util::gain_to_db(*gain.end());
+            assert!(gain_db.contains(&preset.gain_db), "{}", preset.name);
+            assert!(
+                plain_range(&params.drive).contains(&preset.drive),
+                "{}",
+                preset.name
+            );
+            assert!(
+                plain_range(&params.bias).contains(&preset.bias),
+                "{}",
+                preset.name
+            );
+            for (param, boost) in [
+                (&params.low_boost, preset.low_boost),
+                (&params.mid_boost, preset.mid_boost),
+                (&params.high_boost, preset.high_boost),
+            ] {
+                assert!(plain_range(param).contains(&boost), "{}", preset.name);
+            }
+            assert!(
+                plain_range(&params.oversampling_factor).contains(&preset.oversampling_factor),
+                "{}",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn random_patch_is_deterministic() {
+        assert_eq!(RandomPatch::new(1234), RandomPatch::new(1234));
+        assert_ne!(RandomPatch::new(1234), RandomPatch::new(1235));
+    }
+
+    #[test]
+    fn random_patches_are_in_range() {
+        let params = MelterParams::default();
+        let mut distortion_types = Vec::new();
+        for seed in 0..1000 {
+            let patch = RandomPatch::new(seed);
+            let mut gain = 0.0;
+            let mut distortion_type = 0;
+            patch.for_each_value(
+                &params,
+                |param, value| {
+                    if param.name() == "Gain" {
+                        gain = value;
+                    }
+                },
+                |_, value| distortion_type = value as usize,
+            );
+
+            approx::assert_relative_eq!(util::gain_to_db(gain), patch.gain_db, epsilon = 1e-4);
+            assert!((0.0..=24.0).contains(&patch.gain_db), "{seed}");
+            assert!((0.0..=1.5).contains(&patch.drive), "{seed}");
+            assert!((-0.5..=0.5).contains(&patch.bias), "{seed}");
+            for boost in [patch.low_boost, patch.mid_boost, patch.high_boost] {
+                assert!((-6.0..=6.0).contains(&boost), "{seed}");
+            }
+            assert!(distortion_type < DistortionType::ALL.len(), "{seed}");
+            if !distortion_types.contains(&distortion_type) {
+                distortion_types.push(distortion_type);
+            }
+        }
+
+        // Every type in the list gets picked eventually
+        assert_eq!(distortion_types.len(), RANDOM_DISTORTION_TYPES.len());
+    }
+}
@@ -0,0 +1,94 @@
+/// Convert a left/right channel pair to mid/side in place. The left channel becomes the mid
+/// channel and the right channel becomes the side channel.
+pub fn encode_mid_side(left: &mut [f32], right: &mut [f32]) {
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        let mid = (*l + *r) * 0.5;
+        let side = (*l - *r) * 0.5;
+        *l = mid;
+        *r = side;
+    }
+}
+
+/// The inverse of [`encode_mid_side()`]. Converts a mid/side channel pair back to left/right in
+/// place.
+pub fn decode_mid_side(mid: &mut [f32], side: &mut [f32]) {
+    for (m, s) in mid.iter_mut().zip(side.iter_mut()) {
+        let left = *m + *s;
+        let right = *m - *s;
+        *m = left;
+        *s = right;
+    }
+}
+
+/// Scale the side component of a left/right channel pair in place. A width of 0 makes both
+/// channels the same mono signal, 1 leaves the pair unchanged, and values above 1 widen the stereo
+/// image. `width` contains one value per sample.
+pub fn apply_width(left: &mut [f32], right: &mut [f32], width: &[f32]) {
+    for ((l, r), width) in left.iter_mut().zip(right.iter_mut()).zip(width) {
+        let mid = (*l + *r) * 0.5;
+        let side = (*l - *r) * 0.5 * width;
+        *l = mid + side;
+        *r = mid - side;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mid_side_round_trip() {
+        let left_input = [0.5, -0.25, 1.0, 0.0];
+        let right_input = [0.5, 0.75, -1.0, 0.3];
+
+        let mut left = left_input;
+        let mut right = right_input;
+        encode_mid_side(&mut left, &mut right);
+        assert_eq!(left, [0.5, 0.25, 0.0, 0.15]);
+        assert_eq!(right, [0.0, -0.5, 1.0, -0.15]);
+
+        decode_mid_side(&mut left, &mut right);
+        for (output, input) in left.iter().zip(left_input) {
+            approx::assert_relative_eq!(*output, input);
+        }
+        for (output, input) in right.iter().zip(right_input) {
+            approx::assert_relative_eq!(*output, input);
+        }
+    }
+
+    #[test]
+    fn zero_width_is_mono() {
+        let mut left = [0.5, -0.25, 1.0, 0.0];
+        let mut right = [0.5, 0.75, -1.0, 0.3];
+        apply_width(&mut left, &mut right, &[0.0; 4]);
+
+        assert_eq!(left, right);
+        assert_eq!(left, [0.5, 0.25, 0.0, 0.15]);
+    }
+
+    #[test]
+    fn unity_width_is_unchanged() {
+        let left_input = [0.5, -0.25, 1.0, 0.0];
+        let right_input = [0.5, 0.75, -1.0, 0.3];
+
+        let mut left = left_input;
+        let mut right = right_input;
+        apply_width(&mut left, &mut right, &[1.0; 4]);
+        for (output, input) in left.iter().zip(left_input) {
+            approx::assert_relative_eq!(*output, input);
+        }
+        for (output, input) in right.iter().zip(right_input) {
+            approx::assert_relative_eq!(*output, input);
+        }
+    }
+
+    #[test]
+    fn double_width_doubles_side() {
+        let mut left = [1.0, 0.5];
+        let mut right = [0.0, 0.5];
+        apply_width(&mut left, &mut right, &[2.0; 2]);
+
+        assert_eq!(left, [1.5, 0.5]);
+        assert_eq!(right, [-0.5, 0.5]);
+    }
+}
@@ -0,0 +1,19 @@
+use std::f32::consts::TAU;
+
+/// The amplitude of the `frequency` component of `signal`. The signal is Hann windowed to keep
+/// other frequencies from leaking into the measurement.
+pub fn amplitude_at(signal: &[f32], frequency: f32, sample_rate: f32) -> f32 {
+    let window = |i: usize| 0.5 - 0.5 * (i as f32 / signal.len() as f32 * TAU).cos();
+    let window_sum: f32 = (0..signal.len()).map(window).sum();
+
+    let (re, im) = signal
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (i, sample)| {
+            let phase = i as f32 * frequency / sample_rate * TAU;
+            let sample = sample * window(i);
+            (re + sample * phase.cos(), im + sample * phase.sin())
+        });
+
+    (re * re + im * im).sqrt() * 2.0 / window_sum
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use melter::nonlinearity::{
+    atan_sat, bridge_rectifier, cubic, cubic_block, diode_clip_asymmetric, even_harmonics,
+};
+use std::f32::consts::TAU;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const FREQUENCY: f32 = 1000.0;
+const NUM_SAMPLES: usize = 4800;
+
+/// Pass a 1 kHz sine wave with the given amplitude through `curve`.
+fn render(curve: impl Fn(f32) -> f32, amplitude: f32) -> Vec<f32> {
+    (0..NUM_SAMPLES)
+        .map(|i| curve((i as f32 * FREQUENCY / SAMPLE_RATE * TAU).sin() * amplitude))
+        .collect()
+}
+
+/// The amplitude of each of the first `num_harmonics` harmonics of a signal from [`render()`],
+/// starting with the fundamental.
+fn harmonics(signal: &[f32], num_harmonics: usize) -> Vec<f32> {
+    (1..=num_harmonics)
+        .map(|harmonic| common::amplitude_at(signal, harmonic as f32 * FREQUENCY, SAMPLE_RATE))
+        .collect()
+}
+
+/// The total harmonic distortion of the harmonics from [`harmonics()`], as a ratio.
+fn thd(amplitudes: &[f32]) -> f32 {
+    amplitudes[1..]
+        .iter()
+        .map(|amplitude| amplitude * amplitude)
+        .sum::<f32>()
+        .sqrt()
+        / amplitudes[0]
+}
+
+mod symmetry {
+    use super::*;
+
+    #[test]
+    fn cubic_is_odd_symmetric() {
+        for drive in [-0.5, 0.0, 0.5, 1.0] {
+            for i in -100..=100 {
+                let x = i as f32 / 50.0;
+                assert_eq!(
+                    cubic(-x, drive, 0.0),
+                    -cubic(x, drive, 0.0),
+                    "{x} at {drive}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cubic_only_adds_odd_harmonics() {
+        let amplitudes = harmonics(&render(|x| cubic(x, 0.25, 0.0), 1.0), 6);
+        assert!(amplitudes[2] > 0.01, "{:?}", amplitudes);
+        for even in [1, 3, 5] {
+            assert!(amplitudes[even] < 1e-4, "{:?}", amplitudes);
+        }
+    }
+
+    #[test]
+    fn cubic_offset_adds_even_harmonics() {
+        let amplitudes = harmonics(&render(|x| cubic(x, 0.25, 0.3), 1.0), 3);
+        assert!(amplitudes[1] > 0.01, "{:?}", amplitudes);
+    }
+
+    #[test]
+    fn bridge_rectifier_doubles_the_frequency() {
+        let amplitudes = harmonics(&render(bridge_rectifier, 1.0), 4);
+        assert!(amplitudes[0] < 1e-4, "{:?}", amplitudes);
+        assert!(amplitudes[1] > 0.1, "{:?}", amplitudes);
+    }
+}
+
+/// These pin down the harmonic character of the shapers, so changes to the curves show up here.
+mod thd {
+    use super::*;
+
+    /// Below the clipping point the cubic is `x - x^3 / 3`. For a sine with amplitude `a` that's a
+    /// fundamental of `a - a^3 / 4` and a third harmonic of `a^3 / 12`, and nothing else.
+    #[test]
+    fn cubic_matches_its_polynomial() {
+        const AMPLITUDE: f32 = 0.5;
+
+        let amplitudes = harmonics(&render(|x| cubic(x, 0.0, 0.0), AMPLITUDE), 7);
+        let fundamental = AMPLITUDE - AMPLITUDE.powi(3) / 4.0;
+        let third = AMPLITUDE.powi(3) / 12.0;
+        approx::assert_relative_eq!(amplitudes[0], fundamental, max_relative = 1e-3);
+        approx::assert_relative_eq!(amplitudes[2], third, max_relative = 1e-3);
+        approx::assert_relative_eq!(thd(&amplitudes), third / fundamental, max_relative = 1e-3);
+        for (harmonic, amplitude) in amplitudes.iter().enumerate().skip(3) {
+            assert!(
+                *amplitude < 1e-5,
+                "harmonic {}: {}",
+                harmonic + 1,
+                amplitude
+            );
+        }
+    }
+
+    /// An offset adds `-offset * x^2`, which shows up as a second harmonic of `offset * a^2 / 2`
+    /// next to the unchanged third harmonic.
+    #[test]
+    fn cubic_offset_adds_second_harmonic() {
+        const AMPLITUDE: f32 = 0.5;
+        const OFFSET: f32 = 0.1;
+
+        let amplitudes = harmonics(&render(|x| cubic(x, 0.0, OFFSET), AMPLITUDE), 3);
+        approx::assert_relative_eq!(
+            amplitudes[1],
+            OFFSET * AMPLITUDE.powi(2) / 2.0,
+            max_relative = 1e-3
+        );
+        approx::assert_relative_eq!(amplitudes[2], AMPLITUDE.powi(3) / 12.0, max_relative = 1e-3);
+    }
+
+    /// Driving the cubic into clipping raises the distortion, and the harmonics fall off with their
+    /// order.
+    #[test]
+    fn cubic_drive_increases_distortion() {
+        let mut prev_thd = 0.0;
+        for drive in [0.0, 0.25, 0.5] {
+            let amplitudes = harmonics(&render(|x| cubic(x, drive, 0.0), 0.5), 9);
+            let thd = thd(&amplitudes);
+            assert!(thd > prev_thd, "{} at {}", thd, drive);
+            prev_thd = thd;
+
+            assert!(amplitudes[4] < amplitudes[2], "{:?}", amplitudes);
+        }
+        assert!((0.1..0.5).contains(&prev_thd), "{}", prev_thd);
+    }
+
+    /// The arctangent is a soft curve, so even at a high drive it only adds odd harmonics that fall
+    /// off quickly.
+    #[test]
+    fn atan_is_soft_and_odd() {
+        let amplitudes = harmonics(&render(|x| atan_sat(x, 0.5), 0.5), 7);
+        let thd = thd(&amplitudes);
+        assert!((0.05..0.5).contains(&thd), "{}", thd);
+        for even in [1, 3, 5] {
+            assert!(amplitudes[even] < 1e-4, "{:?}", amplitudes);
+        }
+        assert!(amplitudes[4] < amplitudes[2], "{:?}", amplitudes);
+        assert!(amplitudes[6] < amplitudes[4], "{:?}", amplitudes);
+    }
+
+    /// The mismatched diodes add a strong second harmonic on top of the odd ones.
+    #[test]
+    fn asymmetric_diode_adds_second_harmonic() {
+        let amplitudes = harmonics(&render(|x| diode_clip_asymmetric(x, 0.25), 0.5), 3);
+        assert!(amplitudes[1] / amplitudes[0] > 0.05, "{:?}", amplitudes);
+        assert!(amplitudes[2] / amplitudes[0] > 0.05, "{:?}", amplitudes);
+    }
+
+    /// The even harmonics mode leaves out the odd harmonics entirely, so the second harmonic
+    /// dominates the third. The symmetric cubic at the same drive is the other way around.
+    #[test]
+    fn even_harmonics_dominate() {
+        for drive in [0.0, 0.25, 0.5] {
+            let even = harmonics(&render(|x| even_harmonics(x, drive), 0.5), 5);
+            assert!(even[1] / even[0] > 0.05, "{:?} at {}", even, drive);
+            assert!(even[2] < even[1] * 1e-3, "{:?} at {}", even, drive);
+            assert!(even[4] < even[1] * 1e-3, "{:?} at {}", even, drive);
+
+            let cubic = harmonics(&render(|x| cubic(x, drive, 0.0), 0.5), 3);
+            assert!(cubic[1] < cubic[2], "{:?} at {}", cubic, drive);
+        }
+    }
+}
+
+mod block {
+    use super::*;
+
+    /// The block version shapes several samples at once, but it should produce exactly the same
+    /// samples as the scalar version. The length isn't a multiple of the number of lanes, so the
+    /// leftover samples are covered as well.
+    #[test]
+    fn cubic_block_matches_scalar() {
+        for drive in [-0.5, 0.0, 0.25, 1.0] {
+            for offset in [0.0, 0.3] {
+                let input = render(|x| x, 1.5);
+                let expected: Vec<f32> = input.iter().map(|&x| cubic(x, drive, offset)).collect();
+
+                let mut output = input[..NUM_SAMPLES - 3].to_vec();
+                cubic_block(&mut output, drive, offset);
+                assert_eq!(output, expected[..NUM_SAMPLES - 3], "{drive}, {offset}");
+            }
+        }
+    }
+}
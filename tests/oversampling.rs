@@ -0,0 +1,46 @@
+use melter::{Melter, MAX_OVERSAMPLING_FACTOR};
+use std::f32::consts::TAU;
+
+mod common;
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BUFFER_SIZE: usize = 1024;
+const FREQUENCY: f32 = 15000.0;
+// The third harmonic at 45 kHz folds back to 3 kHz
+const ALIAS_FREQUENCY: f32 = SAMPLE_RATE - 3.0 * FREQUENCY;
+
+/// Run a loud 15 kHz sine through the plugin with its default settings, and return the level of
+/// the aliased third harmonic relative to the fundamental.
+fn alias_level(oversampling_factor: usize) -> f32 {
+    const SETTLE_BUFFERS: usize = 8;
+    const MEASURE_BUFFERS: usize = 8;
+
+    let mut melter = Melter::new_offline(1, SAMPLE_RATE, BUFFER_SIZE as u32, oversampling_factor);
+    let mut output = Vec::new();
+    for buffer_idx in 0..SETTLE_BUFFERS + MEASURE_BUFFERS {
+        let mut channels = vec![(0..BUFFER_SIZE)
+            .map(|i| {
+                let t = (buffer_idx * BUFFER_SIZE + i) as f32;
+                (t * FREQUENCY / SAMPLE_RATE * TAU).sin() * 0.5
+            })
+            .collect::<Vec<f32>>()];
+        melter.process_offline(&mut channels);
+
+        if buffer_idx >= SETTLE_BUFFERS {
+            output.extend_from_slice(&channels[0]);
+        }
+    }
+
+    common::amplitude_at(&output, ALIAS_FREQUENCY, SAMPLE_RATE)
+        / common::amplitude_at(&output, FREQUENCY, SAMPLE_RATE)
+}
+
+#[test]
+fn oversampling_reduces_aliasing() {
+    let without_oversampling = alias_level(0);
+    let with_oversampling = alias_level(MAX_OVERSAMPLING_FACTOR);
+
+    // This is about 13 dB with the default Lanczos3 oversampler
+    let reduction_db = 20.0 * (without_oversampling / with_oversampling).log10();
+    assert!(reduction_db > 10.0, "{} dB", reduction_db);
+}